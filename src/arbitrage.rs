@@ -0,0 +1,184 @@
+//! Arbitrage detection: swap routes that start and end in the same mint.
+
+use crate::constants::tokens;
+use crate::instruction_index::InstructionIndex;
+use crate::quote_assets::QuoteAssets;
+use crate::types::{ParseResult, RouteLeg, TradeInfo};
+use crate::utils::{convert_to_ui_amount_u128, get_final_swap};
+use serde::{Deserialize, Serialize};
+
+/// A circular route within one transaction and what it earned in its start mint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Arbitrage {
+    /// Mint the route starts and ends in; profits are denominated in it.
+    pub mint: String,
+    pub decimals: u8,
+    pub amount_in: f64,
+    pub amount_out: f64,
+    /// `amount_out - amount_in`, negative for a losing round trip.
+    pub gross_profit: f64,
+    /// Transaction fee in SOL (base plus priority fee).
+    pub fee: f64,
+    /// Detected tips in SOL (see [crate::tips]).
+    pub tips: f64,
+    /// `fee + tips` expressed in `mint`: 1:1 for SOL, else priced from a SOL hop of the route.
+    pub cost_in_mint: Option<f64>,
+    /// `gross_profit - cost_in_mint`, when the cost could be expressed in `mint`.
+    pub net_profit: Option<f64>,
+    pub compute_units: u64,
+    /// Instruction indexes of the hops, in execution order.
+    pub hops: Vec<InstructionIndex>,
+}
+
+/// Detect a circular route in a parsed transaction, from its aggregated swap when it
+/// has one and from its per-instruction trades otherwise. Only hops of one user that
+/// chain back to their start mint count (see [get_final_swap]); two unrelated swaps
+/// into and out of the same mint are not an arbitrage.
+pub fn detect_arbitrage(result: &ParseResult) -> Option<Arbitrage> {
+    let cycle = match &result.aggregate_trade {
+        Some(trade) if trade.circular => trade.clone(),
+        _ => get_final_swap(&result.trades, None, None, QuoteAssets::shared())
+            .filter(|t| t.circular)?,
+    };
+    let legs = cycle.legs.as_deref().unwrap_or_default();
+    let raw = |amount: &str| amount.parse::<u128>().unwrap_or(0);
+    let (amount_in, amount_out) = (
+        raw(&cycle.input_token.amount_raw),
+        raw(&cycle.output_token.amount_raw),
+    );
+    if amount_in == 0 || amount_out == 0 {
+        return None;
+    }
+    let decimals = cycle.input_token.decimals;
+    let gross = if amount_out >= amount_in {
+        convert_to_ui_amount_u128(amount_out - amount_in, decimals)
+    } else {
+        -convert_to_ui_amount_u128(amount_in - amount_out, decimals)
+    };
+    let fee = result.fee.ui_amount.unwrap_or_default();
+    let tips: f64 = result
+        .tips
+        .iter()
+        .filter_map(|tip| tip.amount.ui_amount)
+        .sum();
+    let cost_in_mint = sol_rate(&cycle, legs).map(|rate| (fee + tips) * rate);
+    Some(Arbitrage {
+        mint: cycle.input_token.mint.clone(),
+        decimals,
+        amount_in: cycle.input_token.amount,
+        amount_out: cycle.output_token.amount,
+        gross_profit: gross,
+        fee,
+        tips,
+        cost_in_mint,
+        net_profit: cost_in_mint.map(|cost| gross - cost),
+        compute_units: result.compute_units,
+        hops: legs.iter().map(|l| l.idx).collect(),
+    })
+}
+
+/// Units of the cycle's mint per SOL, from the first hop trading one against the other.
+fn sol_rate(cycle: &TradeInfo, legs: &[RouteLeg]) -> Option<f64> {
+    let mint = cycle.input_token.mint.as_str();
+    if mint == tokens::SOL {
+        return Some(1.0);
+    }
+    legs.iter().find_map(|leg| {
+        let (a, b) = (&leg.input_token, &leg.output_token);
+        let (sol, other) = match (a.mint.as_str(), b.mint.as_str()) {
+            (tokens::SOL, m) if m == mint => (a, b),
+            (m, tokens::SOL) if m == mint => (b, a),
+            _ => return None,
+        };
+        (sol.amount > 0.0).then(|| other.amount / sol.amount)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{result, trade};
+    use crate::tips::Tip;
+    use crate::types::TokenAmount;
+
+    const SOL: &str = tokens::SOL;
+
+    #[test]
+    fn reports_a_chained_cycle_net_of_fees_and_tips() {
+        let mut r = result(
+            1,
+            "u",
+            5_000,
+            vec![
+                trade("u", "0-1", "p1", (SOL, 1_000_000_000), ("A", 50)),
+                trade("u", "0-2", "p2", ("A", 50), (SOL, 1_500_000_000)),
+            ],
+        );
+        r.tips.push(Tip {
+            idx: InstructionIndex::outer(1),
+            from: "u".to_string(),
+            recipient: "tip".to_string(),
+            provider: "Jito".to_string(),
+            amount: TokenAmount {
+                amount: "1000000".to_string(),
+                ui_amount: Some(0.001),
+                decimals: 9,
+            },
+        });
+        let arb = detect_arbitrage(&r).unwrap();
+        assert_eq!(arb.mint, SOL);
+        assert_eq!((arb.amount_in, arb.amount_out), (1.0, 1.5));
+        assert_eq!(arb.gross_profit, 0.5);
+        assert_eq!((arb.fee, arb.tips), (0.000005, 0.001));
+        assert_eq!(arb.hops.len(), 2);
+        let net = arb.net_profit.unwrap();
+        assert!((net - 0.498995).abs() < 1e-12, "{net}");
+    }
+
+    #[test]
+    fn prices_the_cost_through_a_sol_hop() {
+        let r = result(
+            1,
+            "u",
+            5_000,
+            vec![
+                trade("u", "0-1", "p1", ("A", 100), (SOL, 1_000_000_000)),
+                trade("u", "0-2", "p2", (SOL, 1_000_000_000), ("A", 110)),
+            ],
+        );
+        let arb = detect_arbitrage(&r).unwrap();
+        assert_eq!(arb.mint, "A");
+        assert_eq!(arb.gross_profit, 10.0);
+        // 100 A per SOL: the 0.000005 SOL fee costs 0.0005 A
+        let net = arb.net_profit.unwrap();
+        assert!((net - 9.9995).abs() < 1e-12, "{net}");
+    }
+
+    #[test]
+    fn ignores_unrelated_swaps_into_and_out_of_one_mint() {
+        let r = result(
+            1,
+            "u",
+            0,
+            vec![
+                trade("u", "0", "p1", (SOL, 1_000), ("A", 50)),
+                trade("u", "1", "p2", ("B", 70), (SOL, 1_500)),
+            ],
+        );
+        assert!(detect_arbitrage(&r).is_none());
+    }
+
+    #[test]
+    fn ignores_cycles_across_users() {
+        let r = result(
+            1,
+            "u",
+            0,
+            vec![
+                trade("u", "0", "p1", (SOL, 1_000), ("A", 50)),
+                trade("v", "1", "p2", ("A", 50), (SOL, 1_500)),
+            ],
+        );
+        assert!(detect_arbitrage(&r).is_none());
+    }
+}
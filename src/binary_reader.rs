@@ -1,8 +1,9 @@
 //! Binary buffer reader for instruction data.
 
+use solana_sdk::pubkey::Pubkey;
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum BinaryReaderError {
     #[error("buffer overflow: tried to read {requested} bytes at offset {offset} in buffer of length {length}")]
     Overflow {
@@ -10,6 +11,12 @@ pub enum BinaryReaderError {
         offset: usize,
         length: usize,
     },
+    #[error("invalid {kind} tag {value} at offset {offset}")]
+    InvalidTag {
+        kind: &'static str,
+        value: u32,
+        offset: usize,
+    },
 }
 
 pub struct BinaryReader<'a> {
@@ -23,7 +30,11 @@ impl<'a> BinaryReader<'a> {
     }
 
     fn check_bounds(&self, length: usize) -> Result<(), BinaryReaderError> {
-        if self.offset + length > self.data.len() {
+        if self
+            .offset
+            .checked_add(length)
+            .is_none_or(|end| end > self.data.len())
+        {
             return Err(BinaryReaderError::Overflow {
                 requested: length,
                 offset: self.offset,
@@ -81,6 +92,114 @@ impl<'a> BinaryReader<'a> {
         Ok(bs58::encode(slice).into_string())
     }
 
+    pub fn read_i32_le(&mut self) -> Result<i32, BinaryReaderError> {
+        Ok(i32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u128_le(&mut self) -> Result<u128, BinaryReaderError> {
+        Ok(u128::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_i128_le(&mut self) -> Result<i128, BinaryReaderError> {
+        Ok(i128::from_le_bytes(self.read_array()?))
+    }
+
+    /// Borsh bool: exactly 0 or 1.
+    pub fn read_bool(&mut self) -> Result<bool, BinaryReaderError> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(self.invalid_tag("bool", value)),
+        }
+    }
+
+    /// Borsh `Option<T>`: a 0/1 tag, then `T` if present.
+    pub fn read_option<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<T, BinaryReaderError>,
+    ) -> Result<Option<T>, BinaryReaderError> {
+        match self.read_u8()? {
+            0 => Ok(None),
+            1 => read(self).map(Some),
+            value => Err(self.invalid_tag("option", value)),
+        }
+    }
+
+    /// Solana `COption<T>`: a u32 0/1 tag, then `T` if present.
+    pub fn read_coption<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<T, BinaryReaderError>,
+    ) -> Result<Option<T>, BinaryReaderError> {
+        match self.read_u32_le()? {
+            0 => Ok(None),
+            1 => read(self).map(Some),
+            value => Err(BinaryReaderError::InvalidTag {
+                kind: "coption",
+                value,
+                offset: self.offset - 4,
+            }),
+        }
+    }
+
+    /// Borsh `Vec<T>`: a u32 length, then that many `T`.
+    pub fn read_vec<T>(
+        &mut self,
+        mut read: impl FnMut(&mut Self) -> Result<T, BinaryReaderError>,
+    ) -> Result<Vec<T>, BinaryReaderError> {
+        let len = self.read_u32_le()? as usize;
+        // Each element is at least one byte; don't trust the length for the allocation.
+        let mut out = Vec::with_capacity(len.min(self.remaining()));
+        for _ in 0..len {
+            out.push(read(self)?);
+        }
+        Ok(out)
+    }
+
+    /// Borsh enum variant index. Fails if it is not below `variants`.
+    pub fn read_enum_tag(&mut self, variants: u8) -> Result<u8, BinaryReaderError> {
+        let tag = self.read_u8()?;
+        if tag >= variants {
+            return Err(self.invalid_tag("enum", tag));
+        }
+        Ok(tag)
+    }
+
+    /// Decode any [FromReader] type.
+    pub fn read<T: FromReader>(&mut self) -> Result<T, BinaryReaderError> {
+        T::from_reader(self)
+    }
+
+    /// Next `length` bytes without advancing.
+    pub fn peek(&self, length: usize) -> Result<&'a [u8], BinaryReaderError> {
+        self.check_bounds(length)?;
+        Ok(&self.data[self.offset..self.offset + length])
+    }
+
+    pub fn peek_u8(&self) -> Result<u8, BinaryReaderError> {
+        Ok(self.peek(1)?[0])
+    }
+
+    pub fn skip(&mut self, length: usize) -> Result<(), BinaryReaderError> {
+        self.check_bounds(length)?;
+        self.offset += length;
+        Ok(())
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], BinaryReaderError> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.read_fixed_array(N)?);
+        Ok(out)
+    }
+
+    /// Error for the one-byte tag just read.
+    fn invalid_tag(&self, kind: &'static str, value: u8) -> BinaryReaderError {
+        BinaryReaderError::InvalidTag {
+            kind,
+            value: value.into(),
+            offset: self.offset - 1,
+        }
+    }
+
     pub fn remaining(&self) -> usize {
         self.data.len().saturating_sub(self.offset)
     }
@@ -99,3 +218,259 @@ impl BinaryReader<'_> {
         Ok(v)
     }
 }
+
+/// A type with a fixed Borsh layout that can be read from a [BinaryReader].
+/// Declare event structs with [crate::binary_struct] to get an implementation.
+pub trait FromReader: Sized {
+    fn from_reader(reader: &mut BinaryReader) -> Result<Self, BinaryReaderError>;
+}
+
+macro_rules! impl_from_reader {
+    ($($ty:ty => $read:ident),* $(,)?) => {
+        $(impl FromReader for $ty {
+            fn from_reader(reader: &mut BinaryReader) -> Result<Self, BinaryReaderError> {
+                reader.$read()
+            }
+        })*
+    };
+}
+
+impl_from_reader! {
+    u8 => read_u8,
+    u16 => read_u16_le,
+    u32 => read_u32_le,
+    u64 => read_u64_le,
+    u128 => read_u128_le,
+    i32 => read_i32_le,
+    i64 => read_i64_le,
+    i128 => read_i128_le,
+    bool => read_bool,
+    String => read_string_u32_len,
+}
+
+impl FromReader for Pubkey {
+    fn from_reader(reader: &mut BinaryReader) -> Result<Self, BinaryReaderError> {
+        Ok(Pubkey::new_from_array(reader.read_array()?))
+    }
+}
+
+impl<const N: usize> FromReader for [u8; N] {
+    fn from_reader(reader: &mut BinaryReader) -> Result<Self, BinaryReaderError> {
+        reader.read_array()
+    }
+}
+
+impl<T: FromReader> FromReader for Option<T> {
+    fn from_reader(reader: &mut BinaryReader) -> Result<Self, BinaryReaderError> {
+        reader.read_option(T::from_reader)
+    }
+}
+
+impl<T: FromReader> FromReader for Vec<T> {
+    fn from_reader(reader: &mut BinaryReader) -> Result<Self, BinaryReaderError> {
+        reader.read_vec(T::from_reader)
+    }
+}
+
+/// Declare a struct and its [FromReader] impl from one field list, in Borsh order.
+///
+/// Fields in the optional `trailing` block were appended by later program versions:
+/// they become `Option<T>` and are `None` when the data ends before them, so older
+/// and newer layouts decode with the same type.
+///
+/// ```
+/// use solana_tx_parser::binary_reader::BinaryReader;
+/// use solana_tx_parser::binary_struct;
+///
+/// binary_struct! {
+///     pub struct SwapEvent {
+///         pub amount_in: u64,
+///         pub amount_out: u64,
+///     }
+///     trailing {
+///         pub fee: u64,
+///     }
+/// }
+///
+/// let old_layout = [1u64.to_le_bytes(), 2u64.to_le_bytes()].concat();
+/// let event: SwapEvent = BinaryReader::new(&old_layout).read().unwrap();
+/// assert_eq!((event.amount_in, event.amount_out, event.fee), (1, 2, None));
+/// ```
+#[macro_export]
+macro_rules! binary_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$fmeta:meta])* $fvis:vis $field:ident : $ty:ty),* $(,)?
+        }
+        $(trailing {
+            $($(#[$tmeta:meta])* $tvis:vis $tfield:ident : $tty:ty),* $(,)?
+        })?
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$fmeta])* $fvis $field: $ty,)*
+            $($($(#[$tmeta])* $tvis $tfield: Option<$tty>,)*)?
+        }
+
+        impl $crate::binary_reader::FromReader for $name {
+            fn from_reader(
+                reader: &mut $crate::binary_reader::BinaryReader,
+            ) -> Result<Self, $crate::binary_reader::BinaryReaderError> {
+                Ok(Self {
+                    $($field: <$ty as $crate::binary_reader::FromReader>::from_reader(reader)?,)*
+                    $($($tfield: if reader.remaining() > 0 {
+                        Some(<$tty as $crate::binary_reader::FromReader>::from_reader(reader)?)
+                    } else {
+                        None
+                    },)*)?
+                })
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    binary_struct! {
+        struct Event {
+            amount: u64,
+            side: bool,
+            memo: Option<u16>,
+            hops: Vec<u8>,
+            owner: Pubkey,
+        }
+        trailing {
+            fee: u64,
+            creator: [u8; 2],
+        }
+    }
+
+    fn event(trailing: &[u8]) -> Vec<u8> {
+        let mut data = 7u64.to_le_bytes().to_vec();
+        data.push(1);
+        data.extend([1, 5, 0]);
+        data.extend(2u32.to_le_bytes());
+        data.extend([8, 9]);
+        data.extend([3; 32]);
+        data.extend(trailing);
+        data
+    }
+
+    #[test]
+    fn reads_borsh_bools_options_and_vecs() {
+        let mut r = BinaryReader::new(&[0, 1, 0, 1, 42, 2, 0, 0, 0, 1, 2]);
+        assert!(!r.read_bool().unwrap());
+        assert!(r.read_bool().unwrap());
+        assert_eq!(r.read_option(BinaryReader::read_u8).unwrap(), None);
+        assert_eq!(r.read_option(BinaryReader::read_u8).unwrap(), Some(42));
+        assert_eq!(r.read_vec(BinaryReader::read_u8).unwrap(), [1, 2]);
+        assert_eq!(r.remaining(), 0);
+
+        let mut r = BinaryReader::new(&[2, 2]);
+        assert!(matches!(
+            r.read_bool(),
+            Err(BinaryReaderError::InvalidTag {
+                kind: "bool",
+                value: 2,
+                offset: 0
+            })
+        ));
+        assert!(matches!(
+            r.read_option(BinaryReader::read_u8),
+            Err(BinaryReaderError::InvalidTag {
+                kind: "option",
+                value: 2,
+                offset: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn reads_coptions_with_a_four_byte_tag() {
+        let mut data = 0u32.to_le_bytes().to_vec();
+        data.extend(1u32.to_le_bytes());
+        data.push(9);
+        data.extend(2u32.to_le_bytes());
+        let mut r = BinaryReader::new(&data);
+        assert_eq!(r.read_coption(BinaryReader::read_u8).unwrap(), None);
+        assert_eq!(r.read_coption(BinaryReader::read_u8).unwrap(), Some(9));
+        assert!(matches!(
+            r.read_coption(BinaryReader::read_u8),
+            Err(BinaryReaderError::InvalidTag {
+                kind: "coption",
+                value: 2,
+                offset: 9
+            })
+        ));
+    }
+
+    #[test]
+    fn checks_enum_tags_against_the_variant_count() {
+        let mut r = BinaryReader::new(&[2, 3]);
+        assert_eq!(r.read_enum_tag(3).unwrap(), 2);
+        assert!(matches!(
+            r.read_enum_tag(3),
+            Err(BinaryReaderError::InvalidTag {
+                kind: "enum",
+                value: 3,
+                offset: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn peeks_and_skips() {
+        let mut r = BinaryReader::new(&[1, 2, 3, 4]);
+        assert_eq!(r.peek(2).unwrap(), [1, 2]);
+        assert_eq!(r.peek_u8().unwrap(), 1);
+        assert_eq!(r.offset(), 0);
+        r.skip(3).unwrap();
+        assert_eq!(r.read_u8().unwrap(), 4);
+        assert!(r.peek_u8().is_err());
+        assert!(r.skip(1).is_err());
+    }
+
+    #[test]
+    fn huge_lengths_overflow_instead_of_panicking() {
+        let mut r = BinaryReader::new(&[1, 2, 3]);
+        r.skip(1).unwrap();
+        for result in [r.peek(usize::MAX).map(|_| ()), r.skip(usize::MAX)] {
+            assert!(matches!(
+                result,
+                Err(BinaryReaderError::Overflow {
+                    requested: usize::MAX,
+                    offset: 1,
+                    length: 3
+                })
+            ));
+        }
+        let mut r = BinaryReader::new(&[0xff, 0xff, 0xff, 0xff, 1]);
+        assert!(matches!(
+            r.read_vec(BinaryReader::read_u8),
+            Err(BinaryReaderError::Overflow { .. })
+        ));
+    }
+
+    #[test]
+    fn reads_declared_structs_with_and_without_trailing_fields() {
+        let old: Event = BinaryReader::new(&event(&[])).read().unwrap();
+        assert_eq!(
+            (old.amount, old.side, old.memo, old.hops.as_slice()),
+            (7, true, Some(5), &[8, 9][..])
+        );
+        assert_eq!(old.owner, Pubkey::new_from_array([3; 32]));
+        assert_eq!((old.fee, old.creator), (None, None));
+
+        let mut trailing = 11u64.to_le_bytes().to_vec();
+        trailing.extend([4, 5]);
+        let new: Event = BinaryReader::new(&event(&trailing)).read().unwrap();
+        assert_eq!((new.fee, new.creator), (Some(11), Some([4, 5])));
+
+        // a trailing field that starts must be complete
+        let truncated = event(&11u64.to_le_bytes()[..4]);
+        assert!(BinaryReader::new(&truncated).read::<Event>().is_err());
+    }
+}
@@ -0,0 +1,310 @@
+//! Compute Budget program instructions and the fee breakdown they imply.
+
+use crate::binary_reader::{BinaryReader, BinaryReaderError};
+use crate::constants::{COMPUTE_BUDGET_PROGRAM, SYSTEM_PROGRAM};
+use crate::transaction_adapter::{ParsedInstructionRef, TransactionAdapter};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+/// Compute units granted per instruction of an on-chain program when no limit is set.
+pub const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+/// Compute units granted per built-in program instruction when no limit is set.
+pub const DEFAULT_BUILTIN_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 3_000;
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+
+/// Programs the runtime executes natively, which get the built-in default limit.
+const BUILTIN_PROGRAMS: &[Pubkey] = &[
+    SYSTEM_PROGRAM,
+    COMPUTE_BUDGET_PROGRAM,
+    Pubkey::from_str_const("Vote111111111111111111111111111111111111111"),
+    Pubkey::from_str_const("Stake11111111111111111111111111111111111111"),
+    Pubkey::from_str_const("AddressLookupTab1e1111111111111111111111111"),
+    Pubkey::from_str_const("BPFLoader1111111111111111111111111111111111"),
+    Pubkey::from_str_const("BPFLoader2111111111111111111111111111111111"),
+    Pubkey::from_str_const("BPFLoaderUpgradeab1e11111111111111111111111"),
+    Pubkey::from_str_const("LoaderV411111111111111111111111111111111111"),
+];
+
+/// The limit the runtime applies to top-level instructions of `program_ids` when the
+/// transaction sets none: the built-in default per built-in program instruction and the
+/// instruction default per other one, capped at [MAX_COMPUTE_UNIT_LIMIT].
+pub fn default_compute_unit_limit<'a>(program_ids: impl IntoIterator<Item = &'a Pubkey>) -> u32 {
+    program_ids
+        .into_iter()
+        .map(|id| {
+            if BUILTIN_PROGRAMS.contains(id) {
+                DEFAULT_BUILTIN_INSTRUCTION_COMPUTE_UNIT_LIMIT
+            } else {
+                DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT
+            }
+        })
+        .fold(0u32, u32::saturating_add)
+        .min(MAX_COMPUTE_UNIT_LIMIT)
+}
+
+/// One decoded Compute Budget instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ComputeBudgetInstruction {
+    /// Deprecated single instruction setting units and an additional fee in lamports.
+    RequestUnits {
+        units: u32,
+        additional_fee: u32,
+    },
+    RequestHeapFrame(u32),
+    SetComputeUnitLimit(u32),
+    /// Price in micro-lamports per compute unit.
+    SetComputeUnitPrice(u64),
+    SetLoadedAccountsDataSizeLimit(u32),
+}
+
+impl ComputeBudgetInstruction {
+    pub fn decode(data: &[u8]) -> Result<Self, BinaryReaderError> {
+        let mut reader = BinaryReader::new(data);
+        let offset = reader.offset();
+        Ok(match reader.read_u8()? {
+            0 => Self::RequestUnits {
+                units: reader.read_u32_le()?,
+                additional_fee: reader.read_u32_le()?,
+            },
+            1 => Self::RequestHeapFrame(reader.read_u32_le()?),
+            2 => Self::SetComputeUnitLimit(reader.read_u32_le()?),
+            3 => Self::SetComputeUnitPrice(reader.read_u64_le()?),
+            4 => Self::SetLoadedAccountsDataSizeLimit(reader.read_u32_le()?),
+            value => {
+                return Err(BinaryReaderError::InvalidTag {
+                    kind: "compute budget instruction",
+                    value: value.into(),
+                    offset,
+                })
+            }
+        })
+    }
+}
+
+/// What a transaction requested from the Compute Budget program.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComputeBudget {
+    pub compute_unit_limit: Option<u32>,
+    /// Micro-lamports per compute unit.
+    pub compute_unit_price: Option<u64>,
+    pub heap_frame: Option<u32>,
+    pub loaded_accounts_data_size_limit: Option<u32>,
+    /// Lamports from a deprecated RequestUnits instruction.
+    pub additional_fee: Option<u32>,
+}
+
+impl ComputeBudget {
+    /// Decode the transaction's top-level Compute Budget instructions; undecodable ones are skipped.
+    pub fn from_adapter(adapter: &TransactionAdapter) -> Self {
+        Self::from_instructions(&adapter.instructions())
+    }
+
+    fn from_instructions(instructions: &[ParsedInstructionRef]) -> Self {
+        let mut budget = Self::default();
+        for ix in instructions {
+            if ix.program_id != COMPUTE_BUDGET_PROGRAM {
+                continue;
+            }
+            match ComputeBudgetInstruction::decode(ix.data) {
+                Ok(ComputeBudgetInstruction::RequestUnits {
+                    units,
+                    additional_fee,
+                }) => {
+                    budget.compute_unit_limit = Some(units);
+                    budget.additional_fee = Some(additional_fee);
+                }
+                Ok(ComputeBudgetInstruction::RequestHeapFrame(bytes)) => {
+                    budget.heap_frame = Some(bytes)
+                }
+                Ok(ComputeBudgetInstruction::SetComputeUnitLimit(units)) => {
+                    budget.compute_unit_limit = Some(units)
+                }
+                Ok(ComputeBudgetInstruction::SetComputeUnitPrice(price)) => {
+                    budget.compute_unit_price = Some(price)
+                }
+                Ok(ComputeBudgetInstruction::SetLoadedAccountsDataSizeLimit(bytes)) => {
+                    budget.loaded_accounts_data_size_limit = Some(bytes)
+                }
+                Err(_) => {}
+            }
+        }
+        budget
+    }
+
+    /// Priority fee in lamports: price times requested limit, rounded up.
+    pub fn priority_fee(&self, compute_unit_limit: u32) -> u64 {
+        if let Some(fee) = self.additional_fee {
+            return fee as u64;
+        }
+        let micro = self.compute_unit_price.unwrap_or(0) as u128 * compute_unit_limit as u128;
+        micro.div_ceil(MICRO_LAMPORTS_PER_LAMPORT) as u64
+    }
+}
+
+/// Transaction cost split into base and priority fee, with requested vs consumed compute.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeeBreakdown {
+    /// Total fee charged (meta fee), in lamports.
+    pub total_fee: u64,
+    /// Signature fees: `total_fee - priority_fee`.
+    pub base_fee: u64,
+    pub priority_fee: u64,
+    /// Requested limit, or the runtime default when the transaction didn't set one.
+    pub compute_unit_limit: u32,
+    pub compute_units_consumed: u64,
+    /// Requested price in micro-lamports per compute unit.
+    pub compute_unit_price: u64,
+    /// Priority fee per consumed compute unit, in micro-lamports.
+    pub effective_compute_unit_price: f64,
+    pub budget: ComputeBudget,
+}
+
+impl FeeBreakdown {
+    pub fn from_adapter(adapter: &TransactionAdapter) -> Self {
+        let instructions = adapter.instructions();
+        let budget = ComputeBudget::from_instructions(&instructions);
+        let compute_unit_limit = budget
+            .compute_unit_limit
+            .unwrap_or_else(|| {
+                default_compute_unit_limit(instructions.iter().map(|ix| &ix.program_id))
+            })
+            .min(MAX_COMPUTE_UNIT_LIMIT);
+        let total_fee = adapter.fee().amount.parse::<u64>().unwrap_or(0);
+        let priority_fee = budget.priority_fee(compute_unit_limit);
+        let compute_units_consumed = adapter.compute_units();
+        Self {
+            total_fee,
+            base_fee: total_fee.saturating_sub(priority_fee),
+            priority_fee,
+            compute_unit_limit,
+            compute_units_consumed,
+            compute_unit_price: budget.compute_unit_price.unwrap_or(0),
+            effective_compute_unit_price: if compute_units_consumed > 0 {
+                priority_fee as f64 * MICRO_LAMPORTS_PER_LAMPORT as f64
+                    / compute_units_consumed as f64
+            } else {
+                0.0
+            },
+            budget,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{COMPUTE_BUDGET_PROGRAM_ID, SYSTEM_PROGRAM_ID};
+
+    fn data(tag: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        out.extend_from_slice(payload);
+        out
+    }
+
+    #[test]
+    fn decodes_every_instruction() {
+        let cases = [
+            (
+                data(
+                    0,
+                    &[&300_000u32.to_le_bytes()[..], &5u32.to_le_bytes()].concat(),
+                ),
+                ComputeBudgetInstruction::RequestUnits {
+                    units: 300_000,
+                    additional_fee: 5,
+                },
+            ),
+            (
+                data(1, &(256 * 1024u32).to_le_bytes()),
+                ComputeBudgetInstruction::RequestHeapFrame(256 * 1024),
+            ),
+            (
+                data(2, &1_400_000u32.to_le_bytes()),
+                ComputeBudgetInstruction::SetComputeUnitLimit(1_400_000),
+            ),
+            (
+                data(3, &u64::MAX.to_le_bytes()),
+                ComputeBudgetInstruction::SetComputeUnitPrice(u64::MAX),
+            ),
+            (
+                data(4, &65_536u32.to_le_bytes()),
+                ComputeBudgetInstruction::SetLoadedAccountsDataSizeLimit(65_536),
+            ),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(ComputeBudgetInstruction::decode(&bytes).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_tags_and_short_data() {
+        assert!(matches!(
+            ComputeBudgetInstruction::decode(&[9]),
+            Err(BinaryReaderError::InvalidTag {
+                value: 9,
+                offset: 0,
+                ..
+            })
+        ));
+        assert!(matches!(
+            ComputeBudgetInstruction::decode(&[2, 1, 0]),
+            Err(BinaryReaderError::Overflow { .. })
+        ));
+        assert!(ComputeBudgetInstruction::decode(&[]).is_err());
+    }
+
+    #[test]
+    fn default_limit_grants_builtins_less() {
+        let program = Pubkey::new_unique();
+        assert_eq!(
+            default_compute_unit_limit(&[COMPUTE_BUDGET_PROGRAM, SYSTEM_PROGRAM, program]),
+            206_000
+        );
+        assert_eq!(default_compute_unit_limit(&[]), 0);
+        assert_eq!(
+            default_compute_unit_limit(&[program; 8]),
+            MAX_COMPUTE_UNIT_LIMIT
+        );
+    }
+
+    #[test]
+    fn fee_breakdown_defaults_the_limit_from_the_instructions() {
+        use crate::test_support::{ix, key, tx};
+        let price = data(3, &1_000_000u64.to_le_bytes());
+        let input = tx(
+            vec![
+                key(1),
+                SYSTEM_PROGRAM_ID.to_string(),
+                COMPUTE_BUDGET_PROGRAM_ID.to_string(),
+                key(4),
+            ],
+            vec![
+                ix(2, &[], price),
+                ix(1, &[0], vec![2, 0, 0, 0]),
+                ix(3, &[0], vec![]),
+            ],
+            vec![],
+        );
+        let adapter = TransactionAdapter::new(&input, None);
+        let breakdown = FeeBreakdown::from_adapter(&adapter);
+        // 3_000 per built-in instruction plus 200_000 for the program, at 1 lamport per unit
+        assert_eq!(breakdown.compute_unit_limit, 206_000);
+        assert_eq!(breakdown.priority_fee, 206_000);
+    }
+
+    #[test]
+    fn priority_fee_rounds_micro_lamports_up() {
+        let budget = ComputeBudget {
+            compute_unit_price: Some(1_500),
+            ..Default::default()
+        };
+        assert_eq!(budget.priority_fee(1_000), 2);
+        assert_eq!(budget.priority_fee(0), 0);
+        let legacy = ComputeBudget {
+            additional_fee: Some(7),
+            ..budget
+        };
+        assert_eq!(legacy.priority_fee(1_000), 7);
+    }
+}
@@ -1,10 +1,21 @@
 //! DEX program IDs, discriminators, tokens, and instruction types.
 
+use solana_sdk::pubkey::Pubkey;
 use std::collections::HashMap;
+use std::sync::LazyLock;
 
 pub const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
 pub const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
 pub const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+pub const SYSTEM_PROGRAM_ID: &str = "11111111111111111111111111111111";
+pub const COMPUTE_BUDGET_PROGRAM_ID: &str = "ComputeBudget111111111111111111111111111111";
+
+/// The program ids above decoded, for comparing against account keys.
+pub const TOKEN_PROGRAM: Pubkey = Pubkey::from_str_const(TOKEN_PROGRAM_ID);
+pub const TOKEN_2022_PROGRAM: Pubkey = Pubkey::from_str_const(TOKEN_2022_PROGRAM_ID);
+pub const ASSOCIATED_TOKEN_PROGRAM: Pubkey = Pubkey::from_str_const(ASSOCIATED_TOKEN_PROGRAM_ID);
+pub const SYSTEM_PROGRAM: Pubkey = Pubkey::from_str_const(SYSTEM_PROGRAM_ID);
+pub const COMPUTE_BUDGET_PROGRAM: Pubkey = Pubkey::from_str_const(COMPUTE_BUDGET_PROGRAM_ID);
 
 pub mod tokens {
     pub const NATIVE: &str = "11111111111111111111111111111111";
@@ -19,204 +30,235 @@ pub mod tokens {
     pub const FDUSD: &str = "9zNQRsGLjNKwCUU5Gq5LR8beUCPzQMVMqKAi3SSZh54u";
 }
 
+/// Decimals of well-known mints.
 pub fn token_decimals(mint: &str) -> Option<u8> {
-    let m: HashMap<&str, u8> = [
-        (tokens::SOL, 9),
-        (tokens::USDC, 6),
-        (tokens::USDT, 6),
-        (tokens::USD1, 6),
-        (tokens::USDG, 6),
-        (tokens::PYUSD, 6),
-        (tokens::EURC, 6),
-        (tokens::USDY, 6),
-        (tokens::FDUSD, 6),
-    ]
-    .into_iter()
-    .collect();
-    m.get(mint).copied()
+    match mint {
+        tokens::SOL => Some(9),
+        tokens::USDC
+        | tokens::USDT
+        | tokens::USD1
+        | tokens::USDG
+        | tokens::PYUSD
+        | tokens::EURC
+        | tokens::USDY
+        | tokens::FDUSD => Some(6),
+        _ => None,
+    }
+}
+
+/// Pumpfun bonding curve parameters (token amounts in raw 6-decimal units).
+pub mod pumpfun {
+    pub const TOKEN_DECIMALS: u8 = 6;
+    pub const INITIAL_VIRTUAL_TOKEN_RESERVES: u64 = 1_073_000_000_000_000;
+    pub const INITIAL_REAL_TOKEN_RESERVES: u64 = 793_100_000_000_000;
 }
 
 #[derive(Clone, Debug)]
 pub struct DexProgram {
     pub id: &'static str,
+    /// `id` decoded, for comparing against account keys.
+    pub key: Pubkey,
     pub name: &'static str,
     pub tags: &'static [&'static str],
 }
 
+impl DexProgram {
+    const fn new(id: &'static str, name: &'static str, tags: &'static [&'static str]) -> Self {
+        Self {
+            id,
+            key: Pubkey::from_str_const(id),
+            name,
+            tags,
+        }
+    }
+}
+
 pub mod dex_programs {
     use super::DexProgram;
 
-    pub const JUPITER: DexProgram = DexProgram {
-        id: "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4",
-        name: "Jupiter",
-        tags: &["route"],
-    };
-    pub const JUPITER_DCA: DexProgram = DexProgram {
-        id: "DCA265Vj8a9CEuX1eb1LWRnDT7uK6q1xMipnNyatn23M",
-        name: "JupiterDCA",
-        tags: &["route"],
-    };
-    pub const JUPITER_DCA_KEEPER1: DexProgram = DexProgram {
-        id: "DCAKxn5PFNN1mBREPWGdk1RXg5aVH9rPErLfBFEi2Emb",
-        name: "JupiterDcaKeeper1",
-        tags: &["route"],
-    };
-    pub const JUPITER_DCA_KEEPER2: DexProgram = DexProgram {
-        id: "DCAKuApAuZtVNYLk3KTAVW9GLWVvPbnb5CxxRRmVgcTr",
-        name: "JupiterDcaKeeper2",
-        tags: &["route"],
-    };
-    pub const JUPITER_DCA_KEEPER3: DexProgram = DexProgram {
-        id: "DCAK36VfExkPdAkYUQg6ewgxyinvcEyPLyHjRbmveKFw",
-        name: "JupiterDcaKeeper3",
-        tags: &["route"],
-    };
-    pub const JUPITER_LIMIT_ORDER: DexProgram = DexProgram {
-        id: "jupoNjAxXgZ4rjzxzPMP4oxduvQsQtZzyknqvzYNrNu",
-        name: "JupiterLimit",
-        tags: &["route"],
-    };
-    pub const JUPITER_LIMIT_ORDER_V2: DexProgram = DexProgram {
-        id: "j1o2qRpjcyUwEvwtcfhEQefh773ZgjxcVRry7LDqg5X",
-        name: "JupiterLimitV2",
-        tags: &["route"],
-    };
-    pub const JUPITER_VA: DexProgram = DexProgram {
-        id: "VALaaymxQh2mNy2trH9jUqHT1mTow76wpTcGmSWSwJe",
-        name: "JupiterVA",
-        tags: &["route"],
-    };
-    pub const RAYDIUM_ROUTE: DexProgram = DexProgram {
-        id: "routeUGWgWzqBWFcrCfv8tritsqukccJPu3q5GPP3xS",
-        name: "RaydiumRoute",
-        tags: &["route"],
-    };
-    pub const RAYDIUM_V4: DexProgram = DexProgram {
-        id: "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8",
-        name: "RaydiumV4",
-        tags: &["amm"],
-    };
-    pub const RAYDIUM_AMM: DexProgram = DexProgram {
-        id: "5quBtoiQqxF9Jv6KYKctB59NT3gtJD2Y65kdnB1Uev3h",
-        name: "RaydiumAMM",
-        tags: &["amm"],
-    };
-    pub const RAYDIUM_CPMM: DexProgram = DexProgram {
-        id: "CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C",
-        name: "RaydiumCPMM",
-        tags: &["amm"],
-    };
-    pub const RAYDIUM_CL: DexProgram = DexProgram {
-        id: "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK",
-        name: "RaydiumCL",
-        tags: &["amm"],
-    };
-    pub const RAYDIUM_LCP: DexProgram = DexProgram {
-        id: "LanMV9sAd7wArD4vJFi2qDdfnVhFxYSUg6eADduJ3uj",
-        name: "RaydiumLaunchpad",
-        tags: &["amm"],
-    };
-    pub const ORCA: DexProgram = DexProgram {
-        id: "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc",
-        name: "Orca",
-        tags: &["amm"],
-    };
-    pub const METEORA: DexProgram = DexProgram {
-        id: "LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo",
-        name: "MeteoraDLMM",
-        tags: &["amm"],
-    };
-    pub const METEORA_DAMM: DexProgram = DexProgram {
-        id: "Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB",
-        name: "MeteoraDamm",
-        tags: &["amm"],
-    };
-    pub const METEORA_DAMM_V2: DexProgram = DexProgram {
-        id: "cpamdpZCGKUy5JxQXB4dcpGPiikHawvSWAd6mEn1sGG",
-        name: "MeteoraDammV2",
-        tags: &["amm"],
-    };
-    pub const METEORA_DBC: DexProgram = DexProgram {
-        id: "dbcij3LWUppWqq96dh6gJWwBifmcGfLSB5D4DuSMaqN",
-        name: "MeteoraDBC",
-        tags: &["amm"],
-    };
-    pub const PUMP_FUN: DexProgram = DexProgram {
-        id: "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P",
-        name: "Pumpfun",
-        tags: &["amm"],
-    };
-    pub const PUMP_SWAP: DexProgram = DexProgram {
-        id: "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA",
-        name: "Pumpswap",
-        tags: &["amm"],
-    };
-    pub const MOONIT: DexProgram = DexProgram {
-        id: "MoonCVVNZFSYkqNXP6bxHLPL6QQJiMagDL3qcqUQTrG",
-        name: "Moonit",
-        tags: &["amm"],
-    };
-    pub const BOOP_FUN: DexProgram = DexProgram {
-        id: "boop8hVGQGqehUK2iVEMEnMrL5RbjywRzHKBmBE7ry4",
-        name: "Boopfun",
-        tags: &["amm"],
-    };
-    pub const SUGAR: DexProgram = DexProgram {
-        id: "deus4Bvftd5QKcEkE5muQaWGWDoma8GrySvPFrBPjhS",
-        name: "Sugar",
-        tags: &["amm"],
-    };
-    pub const HEAVEN: DexProgram = DexProgram {
-        id: "HEAVENoP2qxoeuF8Dj2oT1GHEnu49U5mJYkdeC8BAX2o",
-        name: "Heaven",
-        tags: &["amm"],
-    };
+    pub const JUPITER: DexProgram = DexProgram::new(
+        "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4",
+        "Jupiter",
+        &["route"],
+    );
+    pub const JUPITER_DCA: DexProgram = DexProgram::new(
+        "DCA265Vj8a9CEuX1eb1LWRnDT7uK6q1xMipnNyatn23M",
+        "JupiterDCA",
+        &["route"],
+    );
+    pub const JUPITER_DCA_KEEPER1: DexProgram = DexProgram::new(
+        "DCAKxn5PFNN1mBREPWGdk1RXg5aVH9rPErLfBFEi2Emb",
+        "JupiterDcaKeeper1",
+        &["route"],
+    );
+    pub const JUPITER_DCA_KEEPER2: DexProgram = DexProgram::new(
+        "DCAKuApAuZtVNYLk3KTAVW9GLWVvPbnb5CxxRRmVgcTr",
+        "JupiterDcaKeeper2",
+        &["route"],
+    );
+    pub const JUPITER_DCA_KEEPER3: DexProgram = DexProgram::new(
+        "DCAK36VfExkPdAkYUQg6ewgxyinvcEyPLyHjRbmveKFw",
+        "JupiterDcaKeeper3",
+        &["route"],
+    );
+    pub const JUPITER_LIMIT_ORDER: DexProgram = DexProgram::new(
+        "jupoNjAxXgZ4rjzxzPMP4oxduvQsQtZzyknqvzYNrNu",
+        "JupiterLimit",
+        &["route"],
+    );
+    pub const JUPITER_LIMIT_ORDER_V2: DexProgram = DexProgram::new(
+        "j1o2qRpjcyUwEvwtcfhEQefh773ZgjxcVRry7LDqg5X",
+        "JupiterLimitV2",
+        &["route"],
+    );
+    pub const JUPITER_VA: DexProgram = DexProgram::new(
+        "VALaaymxQh2mNy2trH9jUqHT1mTow76wpTcGmSWSwJe",
+        "JupiterVA",
+        &["route"],
+    );
+    pub const RAYDIUM_ROUTE: DexProgram = DexProgram::new(
+        "routeUGWgWzqBWFcrCfv8tritsqukccJPu3q5GPP3xS",
+        "RaydiumRoute",
+        &["route"],
+    );
+    pub const RAYDIUM_V4: DexProgram = DexProgram::new(
+        "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8",
+        "RaydiumV4",
+        &["amm"],
+    );
+    pub const RAYDIUM_AMM: DexProgram = DexProgram::new(
+        "5quBtoiQqxF9Jv6KYKctB59NT3gtJD2Y65kdnB1Uev3h",
+        "RaydiumAMM",
+        &["amm"],
+    );
+    pub const RAYDIUM_CPMM: DexProgram = DexProgram::new(
+        "CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C",
+        "RaydiumCPMM",
+        &["amm"],
+    );
+    pub const RAYDIUM_CL: DexProgram = DexProgram::new(
+        "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK",
+        "RaydiumCL",
+        &["amm"],
+    );
+    pub const RAYDIUM_LCP: DexProgram = DexProgram::new(
+        "LanMV9sAd7wArD4vJFi2qDdfnVhFxYSUg6eADduJ3uj",
+        "RaydiumLaunchpad",
+        &["amm"],
+    );
+    pub const ORCA: DexProgram = DexProgram::new(
+        "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc",
+        "Orca",
+        &["amm"],
+    );
+    pub const METEORA: DexProgram = DexProgram::new(
+        "LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo",
+        "MeteoraDLMM",
+        &["amm"],
+    );
+    pub const METEORA_DAMM: DexProgram = DexProgram::new(
+        "Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB",
+        "MeteoraDamm",
+        &["amm"],
+    );
+    pub const METEORA_DAMM_V2: DexProgram = DexProgram::new(
+        "cpamdpZCGKUy5JxQXB4dcpGPiikHawvSWAd6mEn1sGG",
+        "MeteoraDammV2",
+        &["amm"],
+    );
+    pub const METEORA_DBC: DexProgram = DexProgram::new(
+        "dbcij3LWUppWqq96dh6gJWwBifmcGfLSB5D4DuSMaqN",
+        "MeteoraDBC",
+        &["amm"],
+    );
+    pub const PUMP_FUN: DexProgram = DexProgram::new(
+        "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P",
+        "Pumpfun",
+        &["amm"],
+    );
+    pub const PUMP_SWAP: DexProgram = DexProgram::new(
+        "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA",
+        "Pumpswap",
+        &["amm"],
+    );
+    pub const MOONIT: DexProgram = DexProgram::new(
+        "MoonCVVNZFSYkqNXP6bxHLPL6QQJiMagDL3qcqUQTrG",
+        "Moonit",
+        &["amm"],
+    );
+    pub const BOOP_FUN: DexProgram = DexProgram::new(
+        "boop8hVGQGqehUK2iVEMEnMrL5RbjywRzHKBmBE7ry4",
+        "Boopfun",
+        &["amm"],
+    );
+    pub const SUGAR: DexProgram = DexProgram::new(
+        "deus4Bvftd5QKcEkE5muQaWGWDoma8GrySvPFrBPjhS",
+        "Sugar",
+        &["amm"],
+    );
+    pub const HEAVEN: DexProgram = DexProgram::new(
+        "HEAVENoP2qxoeuF8Dj2oT1GHEnu49U5mJYkdeC8BAX2o",
+        "Heaven",
+        &["amm"],
+    );
 }
 
+/// DEX programs with a known name.
+const NAMED_PROGRAMS: &[DexProgram] = &[
+    dex_programs::JUPITER,
+    dex_programs::JUPITER_DCA,
+    dex_programs::JUPITER_VA,
+    dex_programs::JUPITER_LIMIT_ORDER_V2,
+    dex_programs::RAYDIUM_ROUTE,
+    dex_programs::RAYDIUM_V4,
+    dex_programs::RAYDIUM_AMM,
+    dex_programs::RAYDIUM_CPMM,
+    dex_programs::RAYDIUM_CL,
+    dex_programs::RAYDIUM_LCP,
+    dex_programs::ORCA,
+    dex_programs::METEORA,
+    dex_programs::METEORA_DAMM,
+    dex_programs::METEORA_DAMM_V2,
+    dex_programs::METEORA_DBC,
+    dex_programs::PUMP_FUN,
+    dex_programs::PUMP_SWAP,
+    dex_programs::MOONIT,
+    dex_programs::BOOP_FUN,
+    dex_programs::SUGAR,
+    dex_programs::HEAVEN,
+];
+
+/// Known DEX programs by id, built once.
+static PROGRAM_NAMES: LazyLock<HashMap<&'static str, &'static str>> =
+    LazyLock::new(|| NAMED_PROGRAMS.iter().map(|p| (p.id, p.name)).collect());
+
+/// [PROGRAM_NAMES] by decoded id.
+static PROGRAM_KEY_NAMES: LazyLock<HashMap<Pubkey, &'static str>> =
+    LazyLock::new(|| NAMED_PROGRAMS.iter().map(|p| (p.key, p.name)).collect());
+
 pub fn get_program_name(program_id: &str) -> &'static str {
-    let all: &[(&DexProgram, &str)] = &[
-        (&dex_programs::JUPITER, dex_programs::JUPITER.name),
-        (&dex_programs::JUPITER_DCA, dex_programs::JUPITER_DCA.name),
-        (&dex_programs::JUPITER_VA, dex_programs::JUPITER_VA.name),
-        (&dex_programs::JUPITER_LIMIT_ORDER_V2, dex_programs::JUPITER_LIMIT_ORDER_V2.name),
-        (&dex_programs::RAYDIUM_ROUTE, dex_programs::RAYDIUM_ROUTE.name),
-        (&dex_programs::RAYDIUM_V4, dex_programs::RAYDIUM_V4.name),
-        (&dex_programs::RAYDIUM_AMM, dex_programs::RAYDIUM_AMM.name),
-        (&dex_programs::RAYDIUM_CPMM, dex_programs::RAYDIUM_CPMM.name),
-        (&dex_programs::RAYDIUM_CL, dex_programs::RAYDIUM_CL.name),
-        (&dex_programs::RAYDIUM_LCP, dex_programs::RAYDIUM_LCP.name),
-        (&dex_programs::ORCA, dex_programs::ORCA.name),
-        (&dex_programs::METEORA, dex_programs::METEORA.name),
-        (&dex_programs::METEORA_DAMM, dex_programs::METEORA_DAMM.name),
-        (&dex_programs::METEORA_DAMM_V2, dex_programs::METEORA_DAMM_V2.name),
-        (&dex_programs::METEORA_DBC, dex_programs::METEORA_DBC.name),
-        (&dex_programs::PUMP_FUN, dex_programs::PUMP_FUN.name),
-        (&dex_programs::PUMP_SWAP, dex_programs::PUMP_SWAP.name),
-        (&dex_programs::MOONIT, dex_programs::MOONIT.name),
-        (&dex_programs::BOOP_FUN, dex_programs::BOOP_FUN.name),
-        (&dex_programs::SUGAR, dex_programs::SUGAR.name),
-        (&dex_programs::HEAVEN, dex_programs::HEAVEN.name),
-    ];
-    for (prog, name) in all {
-        if prog.id == program_id {
-            return name;
-        }
-    }
-    "Unknown"
+    PROGRAM_NAMES.get(program_id).copied().unwrap_or("Unknown")
+}
+
+pub fn get_program_name_by_key(program_id: &Pubkey) -> &'static str {
+    PROGRAM_KEY_NAMES
+        .get(program_id)
+        .copied()
+        .unwrap_or("Unknown")
 }
 
-pub const SYSTEM_PROGRAMS: &[&str] = &[
-    "ComputeBudget111111111111111111111111111111",
-    "11111111111111111111111111111111",
-    TOKEN_PROGRAM_ID,
-    TOKEN_2022_PROGRAM_ID,
-    ASSOCIATED_TOKEN_PROGRAM_ID,
-    "srmqPvymJeFKQ4zGQed1GFppgkRHL9kaELCbyksJtPX",
+pub const SYSTEM_PROGRAMS: &[Pubkey] = &[
+    COMPUTE_BUDGET_PROGRAM,
+    SYSTEM_PROGRAM,
+    TOKEN_PROGRAM,
+    TOKEN_2022_PROGRAM,
+    ASSOCIATED_TOKEN_PROGRAM,
+    Pubkey::from_str_const("srmqPvymJeFKQ4zGQed1GFppgkRHL9kaELCbyksJtPX"),
 ];
 
-pub const SKIP_PROGRAM_IDS: &[&str] = &[
+pub const SKIP_PROGRAM_IDS: &[Pubkey] = &[Pubkey::from_str_const(
     "pfeeUxB6jkeY1Hxd7CsFCAjcbHA9rWtchMGdZ6VojVZ",
-];
+)];
 
 pub const FEE_ACCOUNTS: &[&str] = &[
     "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
@@ -243,6 +285,60 @@ pub const FEE_ACCOUNTS: &[&str] = &[
     "CdQTNULjDiTsvyR5UKjYBMqWvYpxXj6HY4m6atm2hErk",
 ];
 
+// Tip accounts of block engines and transaction relayers
+pub mod tip_accounts {
+    pub const JITO: &[&str] = &[
+        "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
+        "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe",
+        "Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY",
+        "ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49",
+        "DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh",
+        "ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt",
+        "DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL",
+        "3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT",
+    ];
+    pub const NOZOMI: &[&str] = &[
+        "TEMPaMeCRFAS9EKF53Jd6KpHxgL47uWLcpFArU1Fanq",
+        "noz3jAjPiHuBPqiSPkkugaJDkJscPuRhYnSpbi8UvC4",
+        "noz3str9KXfpKknefHji8L1mPgimezaiUyCHYMDv1GE",
+        "noz6uoYCDijhu1V7cutCpwxNiSovEwLdRHPwmgCGDNo",
+        "noz9EPNcT7WH6Sou3sr3GGjHQYVkN3DNirpbvDkv9YJ",
+        "nozc5yT15LazbLTFVZzoNZCwjh3yUtW86LoUyqsBu4L",
+        "nozFrhfnNGoyqwVuwPAW4aaGqempx4PU6g6D9CJMv7Z",
+        "nozievPk7HyK1Rqy1MPJwVQ7qQg2QoJGyP71oeDwbsu",
+        "noznbgwYnBLDHu8wcQVCEw6kDrXkPdKkydGJGNXGvL7",
+        "nozNVWs5N8mgzuD3qigrCG2UoKxZttxzZ85pvAQVrbP",
+        "nozpEGbwx4BcGp6pvEdAh1JoC2CQGZdU6HbNP1v2p6P",
+        "nozrhjhkCr3zXT3BiT4WCodYCUFeQvcdUkM7MqhKqge",
+        "nozrwQtWhEdrA6W8dkbt9gnUaMs52PdAv5byipnadq3",
+        "nozUacTVWub3cL4mJmGCYjKZTnE9RbdY5AP46iQgbPJ",
+        "nozWCyTPppJjRuw2fpzDhhWbW355fzosWSzrrMYB1Qk",
+        "nozWNju6dY353eMkMqURqwQEoM3SFgEKC6psLCSfUne",
+        "nozxNBgWohjR75vdspfxR5H9ceC7XXH99xpxhVGt3Bb",
+    ];
+    pub const BLOXROUTE: &[&str] = &[
+        "HWEoBxYs7ssKuudEjzjmpfJVX7Dvi7wescFsVx2L5yoY",
+        "95cfoy472fcQHaw4tPGBTKpn6ZQnfEPfBgDQx6gcRmRg",
+    ];
+    pub const ZERO_SLOT: &[&str] = &[
+        "Eb2KpSC8uMt9GmzyAEm5Eb1AAAgTjRaXWFjKyFXHZxF3",
+        "FCjUJZ1qozm1e8romw216qyfQMaaWKxWsuySnumVCCNe",
+        "ENxTEjSQ1YabmUpXAdCgevnHQ9MHdLv8tzFiuiYJqa13",
+        "6rYLG55Q9RpsPGvqdPNJs4z5WTxJVatMB8zV3WJhs5EK",
+        "Cix2bHfqPcKcM233mzxbLk14kSggUUiz2A87fJtGivXr",
+    ];
+    pub const NEXTBLOCK: &[&str] = &[
+        "NextbLoCkVtMGcV47JzewQdvBpLqT9TxQFozQkN98pE",
+        "NexTbLoCkWykbLuB1NkjXgFWkX9oAtcoagQegygXXA2",
+        "NeXTBLoCKs9F1y5PJS9CKrFNNLU1keHW71rfh7KgA1X",
+        "NexTBLockJYZ7QD7p2byrUa6df8ndV2WSd8GkbWqfbb",
+        "neXtBLock1LeC67jYd1QdAa32kbVeubsfPNTJC1V5At",
+        "nEXTBLockYgngeRmRrjDV31mGSekVPqZoMGhQEZtPVG",
+        "NEXTbLoCkB51HpLBLojQfpyVAMorm3zzKg7w9NFdqid",
+        "nextBLoCkPMgmG8ZgJtABeScP35qLa2AMCNKntAP7Xc",
+    ];
+}
+
 // SPL Token instruction discriminators (first byte)
 pub mod spl_token_instruction {
     pub const INITIALIZE_MINT: u8 = 0;
@@ -262,6 +358,8 @@ pub mod system_instruction {
 
 // Discriminators (first N bytes of instruction data)
 pub mod discriminators {
+    // Anchor `emit_cpi!` self-CPI prefix that precedes the 8-byte event discriminator
+    pub const ANCHOR_EVENT_CPI: [u8; 8] = [228, 69, 165, 46, 81, 203, 154, 29];
     // Jupiter route event (16 bytes)
     pub const JUPITER_ROUTE_EVENT: [u8; 16] = [
         228, 69, 165, 46, 81, 203, 154, 29, 64, 198, 205, 232, 38, 8, 113, 226,
@@ -273,6 +371,23 @@ pub mod discriminators {
     // Pumpswap
     pub const PUMPSWAP_BUY: [u8; 8] = [102, 6, 61, 18, 1, 218, 235, 234];
     pub const PUMPSWAP_SELL: [u8; 8] = [51, 230, 133, 164, 1, 127, 131, 173];
+    // Jupiter route instructions
+    pub const JUPITER_ROUTE: [u8; 8] = [229, 23, 203, 151, 122, 227, 173, 42];
+    pub const JUPITER_SHARED_ACCOUNTS_ROUTE: [u8; 8] = [193, 32, 155, 51, 65, 214, 156, 129];
+    pub const JUPITER_EXACT_OUT_ROUTE: [u8; 8] = [208, 51, 239, 151, 123, 43, 237, 92];
+    // Raydium V4 / AMM swaps (1 byte)
+    pub const RAYDIUM_SWAP_BASE_IN: [u8; 1] = [9];
+    pub const RAYDIUM_SWAP_BASE_OUT: [u8; 1] = [11];
+    // Raydium CPMM swaps
+    pub const RAYDIUM_CPMM_SWAP_BASE_INPUT: [u8; 8] = [143, 190, 90, 218, 196, 30, 51, 222];
+    pub const RAYDIUM_CPMM_SWAP_BASE_OUTPUT: [u8; 8] = [55, 217, 98, 86, 163, 74, 180, 173];
+    // Orca Whirlpool swaps
+    pub const ORCA_SWAP: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+    pub const ORCA_SWAP_V2: [u8; 8] = [43, 4, 237, 11, 26, 201, 30, 98];
+    // Meteora DLMM swaps
+    pub const METEORA_DLMM_SWAP: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+    pub const METEORA_DLMM_SWAP2: [u8; 8] = [65, 75, 63, 76, 235, 91, 91, 136];
+    pub const METEORA_DLMM_SWAP_EXACT_OUT: [u8; 8] = [250, 73, 101, 33, 38, 207, 75, 184];
     // Raydium CPMM
     pub const RAYDIUM_CPMM_CREATE: [u8; 8] = [175, 175, 109, 31, 13, 152, 155, 237];
     pub const RAYDIUM_CPMM_ADD_LIQUIDITY: [u8; 8] = [242, 35, 198, 137, 82, 225, 242, 182];
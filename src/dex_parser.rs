@@ -1,26 +1,49 @@
 //! Main DEX parser: trades, liquidity, transfers, meme events.
 
-use crate::constants::{dex_programs, get_program_name};
+use crate::arbitrage::detect_arbitrage;
+use crate::compute_budget::FeeBreakdown;
+use crate::constants::get_program_name;
+use crate::error::ParseError;
 use crate::instruction_classifier::InstructionClassifier;
-use crate::parsers::{
-    jupiter::JupiterParser,
-    meteora::MeteoraParser,
-    orca::OrcaParser,
-    pumpfun::PumpfunParser,
-    pumpswap::PumpswapParser,
-    raydium::RaydiumParser,
-};
+use crate::parsers::{unknown::UnknownParser, ParseContext, ParserRegistry, ProtocolParser};
+use crate::tips::detect_tips;
+use crate::token_metadata::TokenMetadataProvider;
 use crate::transaction_adapter::TransactionAdapter;
 use crate::transaction_utils::TransactionUtils;
-use crate::types::{ParseConfig, ParseResult, TokenAmount, TradeInfo, TransactionStatus};
+use crate::types::{
+    BlockInfo, ParseConfig, ParseDiagnostic, ParseOutput, ParseResult, SolanaTransactionInput,
+    TokenAmount, TradeInfo, TransactionStatus,
+};
 use crate::utils::get_final_swap;
-use std::collections::HashMap;
+use crate::valuation::{apply_valuation, PriceSource};
+use std::collections::HashSet;
+use std::sync::Arc;
 
-pub struct DexParser;
+/// Parses trades by dispatching each program's instructions to its registered [ProtocolParser].
+#[derive(Clone)]
+pub struct DexParser {
+    registry: ParserRegistry,
+    token_metadata: Option<Arc<dyn TokenMetadataProvider>>,
+    price_source: Option<Arc<dyn PriceSource>>,
+}
 
 impl DexParser {
+    /// Parser with the built-in protocol parsers.
     pub fn new() -> Self {
-        Self
+        Self::builder().build()
+    }
+
+    /// Start from the built-in parsers and [DexParserBuilder::register] more.
+    pub fn builder() -> DexParserBuilder {
+        DexParserBuilder {
+            registry: ParserRegistry::with_builtins(),
+            token_metadata: None,
+            price_source: None,
+        }
+    }
+
+    pub fn registry(&self) -> &ParserRegistry {
+        &self.registry
     }
 
     pub fn parse_trades(
@@ -28,8 +51,20 @@ impl DexParser {
         tx: &crate::types::SolanaTransactionInput,
         config: Option<ParseConfig>,
     ) -> Vec<TradeInfo> {
-        self.parse_with_classifier(tx, config.unwrap_or_default(), ParseType::Trades)
-            .trades
+        self.parse_valued(tx, config, ParseType::Trades, false)
+            .map(|r| r.trades)
+            .unwrap_or_default()
+    }
+
+    /// Like [DexParser::parse_trades], but fails on malformed input or missing meta, and on
+    /// the first decode error when [ParseConfig::throw_error] is set.
+    pub fn try_parse_trades(
+        &self,
+        tx: &crate::types::SolanaTransactionInput,
+        config: Option<ParseConfig>,
+    ) -> Result<Vec<TradeInfo>, ParseError> {
+        self.parse_valued(tx, config, ParseType::Trades, true)
+            .map(|r| r.trades)
     }
 
     pub fn parse_all(
@@ -37,16 +72,118 @@ impl DexParser {
         tx: &crate::types::SolanaTransactionInput,
         config: Option<ParseConfig>,
     ) -> ParseResult {
-        self.parse_with_classifier(tx, config.unwrap_or_default(), ParseType::All)
+        self.parse_valued(tx, config, ParseType::All, false)
+            .unwrap_or_else(|e| Self::failed_result(tx, &e))
     }
 
-    fn parse_with_classifier(
+    /// Like [DexParser::parse_all], but fails on malformed input or missing meta, and on
+    /// the first decode error when [ParseConfig::throw_error] is set. Otherwise decode
+    /// errors are collected into [ParseResult::diagnostics].
+    pub fn try_parse_all(
         &self,
         tx: &crate::types::SolanaTransactionInput,
-        config: ParseConfig,
-        parse_type: ParseType,
+        config: Option<ParseConfig>,
+    ) -> Result<ParseResult, ParseError> {
+        self.parse_valued(tx, config, ParseType::All, true)
+    }
+
+    /// [DexParser::parse_all] for every transaction of one block, in block order, with
+    /// [ParseResult::block] set. Trades are valued once over the whole block, so they share
+    /// one SOL price.
+    pub fn parse_block(
+        &self,
+        transactions: &[SolanaTransactionInput],
+        block: &BlockInfo,
+        config: Option<ParseConfig>,
+    ) -> Vec<ParseResult> {
+        let config = self.with_defaults(config.unwrap_or_default());
+        let mut results: Vec<ParseResult> = transactions
+            .iter()
+            .enumerate()
+            .map(|(position, tx)| self.parse_block_tx(tx, block, position, &config))
+            .collect();
+        Self::value_trades(&mut results, &config);
+        results
+    }
+
+    /// [DexParser::parse_block] across the rayon thread pool. Results keep block order.
+    #[cfg(feature = "rayon")]
+    pub fn par_parse_block(
+        &self,
+        transactions: &[SolanaTransactionInput],
+        block: &BlockInfo,
+        config: Option<ParseConfig>,
+    ) -> Vec<ParseResult> {
+        use rayon::prelude::*;
+        let config = self.with_defaults(config.unwrap_or_default());
+        let mut results: Vec<ParseResult> = transactions
+            .par_iter()
+            .enumerate()
+            .map(|(position, tx)| self.parse_block_tx(tx, block, position, &config))
+            .collect();
+        Self::value_trades(&mut results, &config);
+        results
+    }
+
+    fn parse_block_tx(
+        &self,
+        tx: &SolanaTransactionInput,
+        block: &BlockInfo,
+        position: usize,
+        config: &ParseConfig,
     ) -> ParseResult {
-        let mut result = ParseResult {
+        let mut result = self
+            .parse_with_classifier(tx, config, ParseType::All, false)
+            .unwrap_or_else(|e| Self::failed_result(tx, &e));
+        result.block = Some(block.at(position));
+        result
+    }
+
+    /// `config` with [ParseConfig::token_metadata] and [ParseConfig::price_source] defaulted
+    /// to the parser's own.
+    fn with_defaults(&self, mut config: ParseConfig) -> ParseConfig {
+        if config.token_metadata.is_none() {
+            config.token_metadata = self.token_metadata.clone();
+        }
+        if config.price_source.is_none() {
+            config.price_source = self.price_source.clone();
+        }
+        config
+    }
+
+    /// Parse a single transaction and value its trades.
+    fn parse_valued(
+        &self,
+        tx: &SolanaTransactionInput,
+        config: Option<ParseConfig>,
+        parse_type: ParseType,
+        validate: bool,
+    ) -> Result<ParseResult, ParseError> {
+        let config = self.with_defaults(config.unwrap_or_default());
+        let mut result = self.parse_with_classifier(tx, &config, parse_type, validate)?;
+        Self::value_trades(std::slice::from_mut(&mut result), &config);
+        Ok(result)
+    }
+
+    fn value_trades(results: &mut [ParseResult], config: &ParseConfig) {
+        apply_valuation(
+            results,
+            config.price_source.as_deref(),
+            &config.quote_assets,
+        );
+    }
+
+    /// What [DexParser::parse_all] returns for a transaction that failed to parse.
+    fn failed_result(tx: &SolanaTransactionInput, e: &ParseError) -> ParseResult {
+        let mut result = Self::empty_result(tx);
+        result.state = false;
+        result.msg = Some(e.to_string());
+        result.diagnostics.push(e.into());
+        result
+    }
+
+    fn empty_result(tx: &crate::types::SolanaTransactionInput) -> ParseResult {
+        ParseResult {
             state: true,
             fee: TokenAmount {
                 amount: "0".to_string(),
@@ -67,138 +204,268 @@ impl DexParser {
             msg: None,
             sol_balance_change: None,
             token_balance_change: None,
-        };
+            diagnostics: Vec::new(),
+            fee_breakdown: FeeBreakdown::default(),
+            tips: Vec::new(),
+            arbitrage: None,
+            block: None,
+        }
+    }
+
+    /// Per-program [DexInfo] handed to a parser: its own amm label plus the transaction's route.
+    fn program_dex_info(
+        parser: &dyn ProtocolParser,
+        program_id: &str,
+        dex_info: &crate::types::DexInfo,
+    ) -> crate::types::DexInfo {
+        let own = parser.dex_info(program_id);
+        crate::types::DexInfo {
+            program_id: own.program_id,
+            amm: own
+                .amm
+                .or_else(|| Some(get_program_name(program_id).to_string())),
+            route: dex_info.route.clone(),
+        }
+    }
+
+    /// Post-processing once all parsers ran: execution ordering, metadata enrichment and
+    /// arbitrage detection. Valuation runs afterwards, per transaction or per block.
+    fn finish_trades(result: &mut ParseResult, adapter: &TransactionAdapter, config: &ParseConfig) {
+        result.trades.sort_by_key(|t| t.idx);
+        result.transfers.sort_by_key(|t| t.idx);
+        result.liquidities.sort_by_key(|e| e.idx);
+        result.meme_events.sort_by_key(|e| e.idx);
+        if config.enrich_token_metadata {
+            Self::enrich_token_metadata(result, adapter);
+        }
+        result.arbitrage = detect_arbitrage(result);
+    }
 
-        let adapter = TransactionAdapter::new(tx, Some(config.clone()));
-        result.signature = adapter.signature();
-        result.signer = adapter.signers();
+    /// Fill symbol/name of every trade and meme event token from [ParseConfig::token_metadata].
+    fn enrich_token_metadata(result: &mut ParseResult, adapter: &TransactionAdapter) {
+        let tokens = result
+            .trades
+            .iter_mut()
+            .chain(result.aggregate_trade.iter_mut())
+            .flat_map(|t| [&mut t.input_token, &mut t.output_token])
+            .chain(
+                result
+                    .meme_events
+                    .iter_mut()
+                    .flat_map(|e| [e.input_token.as_mut(), e.output_token.as_mut()])
+                    .flatten(),
+            );
+        for token in tokens {
+            if let Some(metadata) = adapter.token_metadata(&token.mint) {
+                token.symbol = token.symbol.take().or(metadata.symbol);
+                token.name = token.name.take().or(metadata.name);
+            }
+        }
+    }
+
+    /// [get_final_swap] of `trades`. Trades of users other than the route's are left out of
+    /// the aggregate; each gets a diagnostic.
+    fn aggregate_trade(
+        trades: &[TradeInfo],
+        dex_info: &crate::types::DexInfo,
+        config: &ParseConfig,
+        diagnostics: &mut Vec<ParseDiagnostic>,
+    ) -> Option<TradeInfo> {
+        let aggregate = get_final_swap(
+            trades,
+            dex_info.amm.as_deref(),
+            dex_info.route.as_deref(),
+            &config.quote_assets,
+        )?;
+        diagnostics.extend(trades.iter().filter(|t| t.user != aggregate.user).map(|t| {
+            ParseDiagnostic {
+                program_id: t.program_id.clone(),
+                idx: Some(t.idx),
+                message: format!(
+                    "trade of {} left out of the aggregate swap of {}",
+                    t.user, aggregate.user
+                ),
+            }
+        }));
+        Some(aggregate)
+    }
+
+    /// Merge a parser's output into the result, honoring [ParseConfig::throw_error].
+    fn collect_output(
+        result: &mut ParseResult,
+        output: ParseOutput,
+        config: &ParseConfig,
+    ) -> Result<(), ParseError> {
+        if let Some(err) = output.errors.first() {
+            if config.throw_error {
+                return Err(err.clone());
+            }
+        }
+        result
+            .diagnostics
+            .extend(output.errors.iter().map(Into::into));
+        result.trades.extend(output.trades);
+        result.meme_events.extend(output.meme_events);
+        Ok(())
+    }
+
+    /// Parse one transaction; with `validate`, reject it first when
+    /// [TransactionAdapter::validate] fails.
+    fn parse_with_classifier(
+        &self,
+        tx: &crate::types::SolanaTransactionInput,
+        config: &ParseConfig,
+        parse_type: ParseType,
+        validate: bool,
+    ) -> Result<ParseResult, ParseError> {
+        let mut result = Self::empty_result(tx);
+        let adapter = TransactionAdapter::new(tx, Some(config));
+        if validate {
+            adapter.validate()?;
+        }
+        result.signature = adapter.signature().to_string();
+        result.signer = adapter.signers().to_vec();
         result.timestamp = adapter.block_time();
         result.compute_units = adapter.compute_units();
         result.tx_status = adapter.tx_status();
         result.fee = adapter.fee();
+        result.fee_breakdown = FeeBreakdown::from_adapter(&adapter);
+        result.tips = detect_tips(&adapter, &config.tip_accounts);
 
         let classifier = InstructionClassifier::new(&adapter);
         let utils = TransactionUtils::new(&adapter);
-        let dex_info = utils.get_dex_info(&classifier);
+        let dex_info = utils.get_dex_info(&classifier, &self.registry);
         let all_program_ids = classifier.get_all_program_ids();
 
         if let Some(ref filter_ids) = config.program_ids {
-            if !all_program_ids.iter().any(|id| filter_ids.contains(id)) {
+            if !all_program_ids
+                .iter()
+                .any(|id| filter_ids.contains(&id.to_string()))
+            {
                 result.state = false;
-                return result;
+                return Ok(result);
             }
         }
 
-        let transfer_actions = utils.get_transfer_actions(&[
-            "mintTo",
-            "burn",
-            "mintToChecked",
-            "burnChecked",
-        ]);
+        let transfer_actions = utils.get_transfer_actions(
+            &classifier,
+            &self.registry,
+            &["mintTo", "burn", "mintToChecked", "burnChecked"],
+        );
 
         if parse_type == ParseType::Trades || parse_type == ParseType::All {
-            // Jupiter first
-            let jupiter_ids = [
-                dex_programs::JUPITER.id,
-                dex_programs::JUPITER_DCA.id,
-                dex_programs::JUPITER_VA.id,
-                dex_programs::JUPITER_LIMIT_ORDER_V2.id,
-            ];
-            if dex_info.program_id.as_ref().map(|p| jupiter_ids.contains(&p.as_str())) == Some(true) {
-                let program_id = dex_info.program_id.as_deref().unwrap_or("");
-                let instructions = classifier.get_instructions(program_id);
-                if program_id == dex_programs::JUPITER.id {
-                    let parser = JupiterParser::new(
-                        &adapter,
-                        crate::types::DexInfo {
-                            program_id: Some(program_id.to_string()),
-                            amm: Some(get_program_name(program_id).to_string()),
-                            route: dex_info.route.clone(),
-                        },
-                        transfer_actions.clone(),
-                        instructions,
-                    );
-                    let trades = parser.process_trades();
-                    if !trades.is_empty() {
-                        if config.aggregate_trades {
-                            result.aggregate_trade =
-                                get_final_swap(&trades, dex_info.amm.as_deref(), dex_info.route.as_deref());
-                        } else {
-                            result.trades = trades;
-                        }
-                        if !result.trades.is_empty() || result.aggregate_trade.is_some() {
-                            return result;
-                        }
+            // A route aggregator's end-to-end swap takes precedence over its per-AMM legs.
+            // The program [TransactionUtils::get_dex_info] labelled the transaction by.
+            let primary = all_program_ids
+                .iter()
+                .find_map(|key| Some((key, self.registry.get(key)?)));
+            let mut routed = false;
+            if let Some((key, parser)) = primary.filter(|(_, parser)| parser.is_route()) {
+                let program_id = &key.to_string();
+                let instructions = classifier.get_instructions(key);
+                let output = parser.parse(&ParseContext {
+                    adapter: &adapter,
+                    program_id: key,
+                    dex_info: &Self::program_dex_info(parser.as_ref(), program_id, &dex_info),
+                    transfer_actions: &transfer_actions,
+                    instructions,
+                    config,
+                });
+                if config.aggregate_trades {
+                    let mut left_out = Vec::new();
+                    let aggregate =
+                        Self::aggregate_trade(&output.trades, &dex_info, config, &mut left_out);
+                    if aggregate.is_some() {
+                        result.aggregate_trade = aggregate;
+                        let output = ParseOutput {
+                            trades: Vec::new(),
+                            ..output
+                        };
+                        Self::collect_output(&mut result, output, config)?;
+                        result.diagnostics.append(&mut left_out);
+                        routed = true;
                     }
+                } else if !output.trades.is_empty() {
+                    Self::collect_output(&mut result, output, config)?;
+                    routed = true;
                 }
             }
 
-            for program_id in &all_program_ids {
-                if config.program_ids.as_ref().map(|p| !p.contains(program_id)).unwrap_or(false) {
-                    continue;
-                }
-                if config.ignore_program_ids.as_ref().map(|p| p.contains(program_id)).unwrap_or(false) {
-                    continue;
-                }
-                let instructions = classifier.get_instructions(program_id);
-                let dex_info_here = crate::types::DexInfo {
-                    program_id: Some(program_id.clone()),
-                    amm: Some(get_program_name(program_id).to_string()),
-                    route: dex_info.route.clone(),
-                };
-                if program_id == dex_programs::JUPITER.id {
-                    let parser = JupiterParser::new(&adapter, dex_info_here.clone(), transfer_actions.clone(), instructions);
-                    result.trades.extend(parser.process_trades());
-                } else if program_id == dex_programs::RAYDIUM_V4.id
-                    || program_id == dex_programs::RAYDIUM_AMM.id
-                    || program_id == dex_programs::RAYDIUM_CPMM.id
-                    || program_id == dex_programs::RAYDIUM_CL.id
-                    || program_id == dex_programs::RAYDIUM_ROUTE.id
-                {
-                    let parser = RaydiumParser::new(&adapter, dex_info_here.clone(), transfer_actions.clone(), instructions);
-                    result.trades.extend(parser.process_trades());
-                } else if program_id == dex_programs::ORCA.id {
-                    let parser = OrcaParser::new(&adapter, dex_info_here.clone(), transfer_actions.clone(), instructions);
-                    result.trades.extend(parser.process_trades());
-                } else if program_id == dex_programs::METEORA.id
-                    || program_id == dex_programs::METEORA_DAMM.id
-                    || program_id == dex_programs::METEORA_DAMM_V2.id
-                {
-                    let parser = MeteoraParser::new(&adapter, dex_info_here.clone(), transfer_actions.clone(), instructions);
-                    result.trades.extend(parser.process_trades());
-                } else if program_id == dex_programs::PUMP_FUN.id {
-                    let parser = PumpfunParser::new(&adapter, dex_info_here.clone(), transfer_actions.clone(), instructions);
-                    result.trades.extend(parser.process_trades());
-                } else if program_id == dex_programs::PUMP_SWAP.id {
-                    let parser = PumpswapParser::new(&adapter, dex_info_here.clone(), transfer_actions.clone(), instructions);
-                    result.trades.extend(parser.process_trades());
+            if !routed {
+                for key in &all_program_ids {
+                    let program_id = &key.to_string();
+                    if config
+                        .program_ids
+                        .as_ref()
+                        .map(|p| !p.contains(program_id))
+                        .unwrap_or(false)
+                    {
+                        continue;
+                    }
+                    if config
+                        .ignore_program_ids
+                        .as_ref()
+                        .map(|p| p.contains(program_id))
+                        .unwrap_or(false)
+                    {
+                        continue;
+                    }
+                    let instructions = classifier.get_instructions(key);
+                    let output = if let Some(parser) = self.registry.get(key) {
+                        parser.parse(&ParseContext {
+                            adapter: &adapter,
+                            program_id: key,
+                            dex_info: &Self::program_dex_info(
+                                parser.as_ref(),
+                                program_id,
+                                &dex_info,
+                            ),
+                            transfer_actions: &transfer_actions,
+                            instructions,
+                            config,
+                        })
+                    } else if config.try_unknown_dex {
+                        let dex_info_here = crate::types::DexInfo {
+                            program_id: Some(program_id.clone()),
+                            amm: Some(get_program_name(program_id).to_string()),
+                            route: dex_info.route.clone(),
+                        };
+                        UnknownParser::new(&adapter, dex_info_here, &transfer_actions, instructions)
+                            .process_trades()
+                    } else {
+                        continue;
+                    };
+                    Self::collect_output(&mut result, output, config)?;
                 }
-            }
 
-            if result.trades.len() > 1 {
-                let mut seen = HashMap::new();
-                result.trades.retain(|t| {
-                    let key = format!("{}-{}", t.idx, t.signature);
-                    if seen.insert(key.clone(), ()).is_some() {
-                        false
-                    } else {
-                        true
+                if result.trades.len() > 1 {
+                    let mut seen = HashSet::new();
+                    result
+                        .trades
+                        .retain(|t| seen.insert((t.idx, t.signature.clone())));
+                    if config.aggregate_trades {
+                        result.aggregate_trade = Self::aggregate_trade(
+                            &result.trades,
+                            &dex_info,
+                            config,
+                            &mut result.diagnostics,
+                        );
                     }
-                });
-                if config.aggregate_trades {
-                    result.aggregate_trade =
-                        get_final_swap(&result.trades, dex_info.amm.as_deref(), dex_info.route.as_deref());
                 }
             }
         }
 
+        Self::finish_trades(&mut result, &adapter, config);
+
         result.sol_balance_change = adapter
             .get_account_sol_balance_changes(false)
             .remove(&adapter.signer());
         let token_changes = adapter.get_account_token_balance_changes(true);
-        result.token_balance_change = token_changes.get(&adapter.signer()).cloned();
+        result.token_balance_change = token_changes
+            .get(&adapter.signer())
+            .map(|changes| changes.clone().into_iter().collect());
 
-        result
+        Ok(result)
     }
 }
 
@@ -208,8 +475,220 @@ impl Default for DexParser {
     }
 }
 
+/// Builds a [DexParser] with extra or replacement [ProtocolParser]s.
+pub struct DexParserBuilder {
+    registry: ParserRegistry,
+    token_metadata: Option<Arc<dyn TokenMetadataProvider>>,
+    price_source: Option<Arc<dyn PriceSource>>,
+}
+
+impl DexParserBuilder {
+    /// Add a parser. It replaces any parser already registered for the same program ids.
+    pub fn register(mut self, parser: impl ProtocolParser + 'static) -> Self {
+        self.registry.register(Arc::new(parser));
+        self
+    }
+
+    /// Drop the built-in parsers, leaving only those registered afterwards.
+    pub fn without_builtins(mut self) -> Self {
+        self.registry = ParserRegistry::new();
+        self
+    }
+
+    /// Default [ParseConfig::token_metadata] for configs that don't set one.
+    pub fn token_metadata(mut self, provider: impl TokenMetadataProvider + 'static) -> Self {
+        self.token_metadata = Some(Arc::new(provider));
+        self
+    }
+
+    /// Default [ParseConfig::price_source] for configs that don't set one.
+    pub fn price_source(mut self, source: impl PriceSource + 'static) -> Self {
+        self.price_source = Some(Arc::new(source));
+        self
+    }
+
+    pub fn build(self) -> DexParser {
+        DexParser {
+            registry: self.registry,
+            token_metadata: self.token_metadata,
+            price_source: self.price_source,
+        }
+    }
+}
+
 #[derive(PartialEq, Eq)]
 enum ParseType {
     Trades,
     All,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{ix, key, trade, tx};
+
+    const SOL: &str = crate::constants::tokens::SOL;
+
+    /// A route parser for program `key(9)` that reports `trades` and one decode error.
+    struct FakeRoute(Vec<TradeInfo>);
+
+    impl ProtocolParser for FakeRoute {
+        fn name(&self) -> &str {
+            "FakeRoute"
+        }
+
+        fn program_ids(&self) -> Vec<String> {
+            vec![key(9)]
+        }
+
+        fn is_route(&self) -> bool {
+            true
+        }
+
+        fn parse(&self, ctx: &ParseContext) -> ParseOutput {
+            ParseOutput {
+                trades: self.0.clone(),
+                errors: vec![ParseError::decode(
+                    ctx.program_id,
+                    "0".parse().unwrap(),
+                    "bad",
+                )],
+                ..Default::default()
+            }
+        }
+    }
+
+    fn parse(trades: Vec<TradeInfo>) -> ParseResult {
+        let parser = DexParser::builder().register(FakeRoute(trades)).build();
+        let tx = tx(vec![key(1), key(9)], vec![ix(1, &[0], vec![1])], Vec::new());
+        let config = ParseConfig {
+            aggregate_trades: true,
+            ..Default::default()
+        };
+        parser.parse_all(&tx, Some(config))
+    }
+
+    #[test]
+    fn a_route_without_aggregate_is_collected_once() {
+        let result = parse(vec![
+            trade("u", "0-1", "p1", (SOL, 10), ("A", 100)),
+            trade("u", "0-2", "p2", ("B", 100), (SOL, 12)),
+        ]);
+        assert!(result.aggregate_trade.is_none());
+        assert_eq!(result.trades.len(), 2);
+        assert_eq!(result.diagnostics.len(), 1, "{:?}", result.diagnostics);
+    }
+
+    #[test]
+    fn trades_left_out_of_the_aggregate_are_reported() {
+        let result = parse(vec![
+            trade("u", "0-1", "p1", (SOL, 10), ("A", 100)),
+            trade("v", "0-2", "p2", ("A", 100), (SOL, 12)),
+        ]);
+        let aggregate = result.aggregate_trade.unwrap();
+        assert_eq!(aggregate.user, "u");
+        assert_eq!(aggregate.legs.unwrap().len(), 1);
+        assert!(result.trades.is_empty());
+        let messages: Vec<&str> = result
+            .diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(messages.len(), 2, "{messages:?}");
+        assert!(messages[0].contains("bad"));
+        assert_eq!(
+            messages[1],
+            "trade of v left out of the aggregate swap of u"
+        );
+        assert_eq!(result.diagnostics[1].idx, Some("0-2".parse().unwrap()));
+    }
+
+    #[test]
+    fn try_parse_rejects_what_parse_tolerates() {
+        let parser = DexParser::new();
+        let mut input = tx(
+            vec![key(1), key(9)],
+            vec![ix(1, &[0, 5], vec![])],
+            Vec::new(),
+        );
+        assert!(matches!(
+            parser.try_parse_all(&input, None),
+            Err(ParseError::UnknownAccountIndex { index: 5, len: 2 })
+        ));
+        let result = parser.parse_all(&input, None);
+        assert!(result.state);
+        assert_eq!(result.signature, bs58::encode([1; 64]).into_string());
+        assert_eq!(result.signer, [key(1)]);
+
+        input.instructions[0].account_key_indexes.pop();
+        input.meta = None;
+        assert!(matches!(
+            parser.try_parse_trades(&input, None),
+            Err(ParseError::MissingMeta)
+        ));
+    }
+
+    fn block() -> (Vec<SolanaTransactionInput>, BlockInfo) {
+        let transactions: Vec<SolanaTransactionInput> = (1..=3)
+            .map(|n| {
+                let mut input = tx(vec![key(n), key(9)], vec![ix(1, &[0], vec![1])], Vec::new());
+                input.slot = 7;
+                input.signatures = vec![vec![n; 64]];
+                input
+            })
+            .collect();
+        let block = BlockInfo {
+            slot: 7,
+            blockhash: "hash".to_string(),
+            parent_slot: 6,
+        };
+        (transactions, block)
+    }
+
+    fn block_parser() -> DexParser {
+        let hop = trade(
+            "u",
+            "0",
+            "p",
+            (SOL, 1_000_000_000),
+            (crate::constants::tokens::USDC, 150_000_000),
+        );
+        DexParser::builder().register(FakeRoute(vec![hop])).build()
+    }
+
+    fn assert_block_order(results: &[ParseResult]) {
+        assert_eq!(results.len(), 3);
+        for (position, result) in results.iter().enumerate() {
+            let n = position as u8 + 1;
+            assert_eq!(result.signature, bs58::encode([n; 64]).into_string());
+            assert_eq!(result.signer, [key(n)]);
+            let meta = result.block.as_ref().unwrap();
+            assert_eq!(
+                (
+                    meta.slot,
+                    meta.blockhash.as_str(),
+                    meta.parent_slot,
+                    meta.tx_position
+                ),
+                (7, "hash", 6, position)
+            );
+            let valuation = result.trades[0].valuation.as_ref().unwrap();
+            assert_eq!(valuation.usd_value, Some(150.0));
+        }
+    }
+
+    #[test]
+    fn parse_block_keeps_block_order_and_meta() {
+        let (transactions, block) = block();
+        let results = block_parser().parse_block(&transactions, &block, None);
+        assert_block_order(&results);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_parse_block_keeps_block_order_and_meta() {
+        let (transactions, block) = block();
+        let results = block_parser().par_parse_block(&transactions, &block, None);
+        assert_block_order(&results);
+    }
+}
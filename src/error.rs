@@ -0,0 +1,57 @@
+//! Crate-wide parse errors.
+
+use crate::binary_reader::BinaryReaderError;
+use crate::instruction_index::InstructionIndex;
+use crate::types::ParseDiagnostic;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone)]
+pub enum ParseError {
+    #[error("malformed transaction data: {0}")]
+    MalformedData(String),
+    #[error("invalid IDL: {0}")]
+    InvalidIdl(String),
+    #[error("account index {index} out of range ({len} account keys)")]
+    UnknownAccountIndex { index: usize, len: usize },
+    #[error("failed to decode {program_id} instruction at {idx}: {reason}")]
+    Decode {
+        program_id: String,
+        idx: InstructionIndex,
+        reason: String,
+    },
+    #[error("transaction meta is missing")]
+    MissingMeta,
+}
+
+impl ParseError {
+    /// Decode failure for the instruction at `idx` of `program_id`.
+    pub fn decode(program_id: impl ToString, idx: InstructionIndex, reason: impl ToString) -> Self {
+        ParseError::Decode {
+            program_id: program_id.to_string(),
+            idx,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl From<BinaryReaderError> for ParseError {
+    fn from(err: BinaryReaderError) -> Self {
+        ParseError::MalformedData(err.to_string())
+    }
+}
+
+impl From<&ParseError> for ParseDiagnostic {
+    fn from(err: &ParseError) -> Self {
+        let (program_id, idx) = match err {
+            ParseError::Decode {
+                program_id, idx, ..
+            } => (Some(program_id.clone()), Some(*idx)),
+            _ => (None, None),
+        };
+        ParseDiagnostic {
+            program_id,
+            idx,
+            message: err.to_string(),
+        }
+    }
+}
@@ -0,0 +1,854 @@
+//! Anchor IDL loader: discriminators and Borsh decoding into a dynamic [IdlValue] tree.
+//!
+//! Accepts both the Anchor >= 0.30 IDL format (explicit `discriminator` arrays, event
+//! layouts under `types`) and the legacy format (camelCase names, inline event fields),
+//! for which discriminators are derived from `sha256("global:<snake_name>")` and
+//! `sha256("event:<Name>")`.
+
+use crate::binary_reader::BinaryReader;
+use crate::constants::discriminators;
+use crate::error::ParseError;
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use solana_sdk::hash::hash;
+use std::collections::HashMap;
+
+/// Borsh type as declared in an IDL.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdlType {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    F32,
+    F64,
+    String,
+    Bytes,
+    Pubkey,
+    Option(Box<IdlType>),
+    /// `COption`: 4-byte tag instead of 1.
+    COption(Box<IdlType>),
+    Vec(Box<IdlType>),
+    Array(Box<IdlType>, usize),
+    Defined(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct IdlField {
+    pub name: String,
+    pub ty: IdlType,
+}
+
+#[derive(Debug, Clone)]
+pub enum IdlFields {
+    Named(Vec<IdlField>),
+    Tuple(Vec<IdlType>),
+}
+
+#[derive(Debug, Clone)]
+pub struct IdlEnumVariant {
+    pub name: String,
+    pub fields: Option<IdlFields>,
+}
+
+#[derive(Debug, Clone)]
+pub enum IdlTypeDef {
+    Struct(IdlFields),
+    Enum(Vec<IdlEnumVariant>),
+    Alias(IdlType),
+}
+
+#[derive(Debug, Clone)]
+pub struct IdlInstruction {
+    pub name: String,
+    pub discriminator: Vec<u8>,
+    /// Account names in order, nested account groups flattened.
+    pub accounts: Vec<String>,
+    pub args: Vec<IdlField>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IdlEvent {
+    pub name: String,
+    pub discriminator: Vec<u8>,
+    pub fields: IdlFields,
+}
+
+/// A loaded Anchor IDL.
+#[derive(Debug, Clone, Default)]
+pub struct Idl {
+    pub name: Option<String>,
+    /// Program id (`address` in new IDLs, `metadata.address` in legacy ones).
+    pub address: Option<String>,
+    pub instructions: Vec<IdlInstruction>,
+    pub events: Vec<IdlEvent>,
+    pub types: HashMap<String, IdlTypeDef>,
+}
+
+/// An instruction or event decoded against an [Idl].
+#[derive(Debug, Clone, Serialize)]
+pub struct IdlDecoded {
+    pub name: String,
+    pub data: IdlValue,
+}
+
+/// Dynamically typed Borsh value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdlValue {
+    Bool(bool),
+    Unsigned(u128),
+    Signed(i128),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Pubkey(String),
+    Option(Option<Box<IdlValue>>),
+    Array(Vec<IdlValue>),
+    Struct(Vec<(String, IdlValue)>),
+    Enum {
+        variant: String,
+        value: Option<Box<IdlValue>>,
+    },
+}
+
+impl IdlValue {
+    /// Field of a struct value (or of an enum variant's struct payload).
+    pub fn get(&self, field: &str) -> Option<&IdlValue> {
+        match self {
+            IdlValue::Struct(fields) => fields.iter().find(|(n, _)| n == field).map(|(_, v)| v),
+            IdlValue::Enum { value: Some(v), .. } => v.get(field),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            IdlValue::Unsigned(v) => u64::try_from(*v).ok(),
+            IdlValue::Signed(v) => u64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            IdlValue::Unsigned(v) => i64::try_from(*v).ok(),
+            IdlValue::Signed(v) => i64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_u128(&self) -> Option<u128> {
+        match self {
+            IdlValue::Unsigned(v) => Some(*v),
+            IdlValue::Signed(v) => u128::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            IdlValue::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// String or base58 pubkey.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            IdlValue::String(s) | IdlValue::Pubkey(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for IdlValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            IdlValue::Bool(v) => serializer.serialize_bool(*v),
+            IdlValue::Unsigned(v) => match u64::try_from(*v) {
+                Ok(v) => serializer.serialize_u64(v),
+                Err(_) => serializer.serialize_str(&v.to_string()),
+            },
+            IdlValue::Signed(v) => match i64::try_from(*v) {
+                Ok(v) => serializer.serialize_i64(v),
+                Err(_) => serializer.serialize_str(&v.to_string()),
+            },
+            IdlValue::Float(v) => serializer.serialize_f64(*v),
+            IdlValue::String(s) | IdlValue::Pubkey(s) => serializer.serialize_str(s),
+            IdlValue::Bytes(b) => b.serialize(serializer),
+            IdlValue::Option(v) => v.serialize(serializer),
+            IdlValue::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            IdlValue::Struct(fields) => {
+                let mut map = serializer.serialize_map(Some(fields.len()))?;
+                for (name, value) in fields {
+                    map.serialize_entry(name, value)?;
+                }
+                map.end()
+            }
+            IdlValue::Enum {
+                variant,
+                value: None,
+            } => serializer.serialize_str(variant),
+            IdlValue::Enum {
+                variant,
+                value: Some(value),
+            } => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(variant, value)?;
+                map.end()
+            }
+        }
+    }
+}
+
+/// `sha256("global:<name>")[..8]`; `name` is the snake_case instruction name.
+pub fn instruction_discriminator(name: &str) -> [u8; 8] {
+    sighash("global", name)
+}
+
+/// `sha256("event:<Name>")[..8]`.
+pub fn event_discriminator(name: &str) -> [u8; 8] {
+    sighash("event", name)
+}
+
+fn sighash(namespace: &str, name: &str) -> [u8; 8] {
+    let digest = hash(format!("{}:{}", namespace, name).as_bytes());
+    let mut out = [0u8; 8];
+    out.copy_from_slice(&digest.to_bytes()[..8]);
+    out
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    let mut prev_lower_or_digit = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if prev_lower_or_digit {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+            prev_lower_or_digit = false;
+        } else {
+            prev_lower_or_digit = c.is_ascii_lowercase() || c.is_ascii_digit();
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// How deep types may nest while decoding, so a self-referential IDL fails instead of
+/// overflowing the stack.
+const MAX_TYPE_DEPTH: usize = 64;
+
+fn invalid(msg: impl Into<String>) -> ParseError {
+    ParseError::InvalidIdl(msg.into())
+}
+
+impl Idl {
+    pub fn from_json(json: &str) -> Result<Self, ParseError> {
+        let value: Value = serde_json::from_str(json).map_err(|e| invalid(e.to_string()))?;
+        Self::from_value(&value)
+    }
+
+    pub fn from_value(value: &Value) -> Result<Self, ParseError> {
+        let mut idl = Idl {
+            name: value
+                .pointer("/metadata/name")
+                .or_else(|| value.get("name"))
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            address: value
+                .get("address")
+                .or_else(|| value.pointer("/metadata/address"))
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            ..Default::default()
+        };
+
+        for ty in value
+            .get("types")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let name = str_field(ty, "name")?;
+            let def = parse_type_def(
+                ty.get("type")
+                    .ok_or_else(|| invalid(format!("type {} has no body", name)))?,
+            )?;
+            idl.types.insert(name, def);
+        }
+
+        for ix in value
+            .get("instructions")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let name = str_field(ix, "name")?;
+            let discriminator = match ix.get("discriminator") {
+                Some(d) => parse_bytes(d)?,
+                None => instruction_discriminator(&to_snake_case(&name)).to_vec(),
+            };
+            let mut accounts = Vec::new();
+            flatten_accounts(ix.get("accounts"), &mut accounts);
+            let args = match ix.get("args") {
+                Some(args) => parse_named_fields(args)?,
+                None => Vec::new(),
+            };
+            idl.instructions.push(IdlInstruction {
+                name,
+                discriminator,
+                accounts,
+                args,
+            });
+        }
+
+        for ev in value
+            .get("events")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let name = str_field(ev, "name")?;
+            let discriminator = match ev.get("discriminator") {
+                Some(d) => parse_bytes(d)?,
+                None => event_discriminator(&name).to_vec(),
+            };
+            let fields = match ev.get("fields") {
+                // Legacy: fields inline on the event
+                Some(fields) => IdlFields::Named(parse_named_fields(fields)?),
+                // New: layout lives in `types` under the event name
+                None => match idl.types.get(&name) {
+                    Some(IdlTypeDef::Struct(fields)) => fields.clone(),
+                    _ => return Err(invalid(format!("event {} has no struct layout", name))),
+                },
+            };
+            idl.events.push(IdlEvent {
+                name,
+                discriminator,
+                fields,
+            });
+        }
+
+        Ok(idl)
+    }
+
+    pub fn instruction(&self, name: &str) -> Option<&IdlInstruction> {
+        self.instructions.iter().find(|ix| ix.name == name)
+    }
+
+    pub fn event(&self, name: &str) -> Option<&IdlEvent> {
+        self.events.iter().find(|ev| ev.name == name)
+    }
+
+    /// Decode instruction data by its discriminator. `Ok(None)` if no instruction matches.
+    pub fn decode_instruction(&self, data: &[u8]) -> Result<Option<IdlDecoded>, ParseError> {
+        let Some(ix) = self
+            .instructions
+            .iter()
+            .find(|ix| !ix.discriminator.is_empty() && data.starts_with(&ix.discriminator))
+        else {
+            return Ok(None);
+        };
+        let mut reader = BinaryReader::new(&data[ix.discriminator.len()..]);
+        let mut fields = Vec::with_capacity(ix.args.len());
+        for arg in &ix.args {
+            fields.push((arg.name.clone(), self.decode_type(&arg.ty, &mut reader)?));
+        }
+        Ok(Some(IdlDecoded {
+            name: ix.name.clone(),
+            data: IdlValue::Struct(fields),
+        }))
+    }
+
+    /// Decode an event from a program log payload or an `emit_cpi!` inner instruction
+    /// (whose data carries the 8-byte [discriminators::ANCHOR_EVENT_CPI] prefix).
+    /// `Ok(None)` if no event matches.
+    pub fn decode_event(&self, data: &[u8]) -> Result<Option<IdlDecoded>, ParseError> {
+        let data = match data.strip_prefix(&discriminators::ANCHOR_EVENT_CPI[..]) {
+            Some(rest) if rest.len() >= 8 => rest,
+            _ => data,
+        };
+        let Some(ev) = self
+            .events
+            .iter()
+            .find(|ev| !ev.discriminator.is_empty() && data.starts_with(&ev.discriminator))
+        else {
+            return Ok(None);
+        };
+        let mut reader = BinaryReader::new(&data[ev.discriminator.len()..]);
+        Ok(Some(IdlDecoded {
+            name: ev.name.clone(),
+            data: self.decode_fields(&ev.fields, &mut reader, 0)?,
+        }))
+    }
+
+    /// Decode a value of a type declared in `types` from the start of `data`.
+    pub fn decode_defined(&self, name: &str, data: &[u8]) -> Result<IdlValue, ParseError> {
+        self.decode_type(
+            &IdlType::Defined(name.to_string()),
+            &mut BinaryReader::new(data),
+        )
+    }
+
+    fn decode_fields(
+        &self,
+        fields: &IdlFields,
+        r: &mut BinaryReader,
+        depth: usize,
+    ) -> Result<IdlValue, ParseError> {
+        Ok(match fields {
+            IdlFields::Named(fields) => {
+                let mut out = Vec::with_capacity(fields.len());
+                for field in fields {
+                    out.push((field.name.clone(), self.decode_nested(&field.ty, r, depth)?));
+                }
+                IdlValue::Struct(out)
+            }
+            IdlFields::Tuple(types) => IdlValue::Array(
+                types
+                    .iter()
+                    .map(|ty| self.decode_nested(ty, r, depth))
+                    .collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+
+    pub fn decode_type(&self, ty: &IdlType, r: &mut BinaryReader) -> Result<IdlValue, ParseError> {
+        self.decode_nested(ty, r, 0)
+    }
+
+    /// [Self::decode_type] of a type nested `depth` levels deep.
+    fn decode_nested(
+        &self,
+        ty: &IdlType,
+        r: &mut BinaryReader,
+        depth: usize,
+    ) -> Result<IdlValue, ParseError> {
+        if depth > MAX_TYPE_DEPTH {
+            return Err(invalid(format!(
+                "types nest deeper than {} levels",
+                MAX_TYPE_DEPTH
+            )));
+        }
+        let depth = depth + 1;
+        Ok(match ty {
+            IdlType::Bool => IdlValue::Bool(r.read_bool()?),
+            IdlType::U8 => IdlValue::Unsigned(r.read_u8()? as u128),
+            IdlType::U16 => IdlValue::Unsigned(r.read_u16_le()? as u128),
+            IdlType::U32 => IdlValue::Unsigned(r.read_u32_le()? as u128),
+            IdlType::U64 => IdlValue::Unsigned(r.read_u64_le()? as u128),
+            IdlType::U128 => IdlValue::Unsigned(r.read_u128_le()?),
+            IdlType::I8 => IdlValue::Signed(r.read_u8()? as i8 as i128),
+            IdlType::I16 => IdlValue::Signed(i16::from_le_bytes(read_array(r)?) as i128),
+            IdlType::I32 => IdlValue::Signed(r.read_i32_le()? as i128),
+            IdlType::I64 => IdlValue::Signed(r.read_i64_le()? as i128),
+            IdlType::I128 => IdlValue::Signed(r.read_i128_le()?),
+            IdlType::F32 => IdlValue::Float(f32::from_le_bytes(read_array(r)?) as f64),
+            IdlType::F64 => IdlValue::Float(f64::from_le_bytes(read_array(r)?)),
+            IdlType::String => IdlValue::String(r.read_string_u32_len()?),
+            IdlType::Bytes => {
+                let len = r.read_u32_le()? as usize;
+                IdlValue::Bytes(r.read_fixed_array(len)?.to_vec())
+            }
+            IdlType::Pubkey => IdlValue::Pubkey(r.read_pubkey()?),
+            IdlType::Option(inner) => match r.read_option(|_| Ok(()))? {
+                None => IdlValue::Option(None),
+                Some(()) => IdlValue::Option(Some(Box::new(self.decode_nested(inner, r, depth)?))),
+            },
+            IdlType::COption(inner) => match r.read_coption(|_| Ok(()))? {
+                None => IdlValue::Option(None),
+                Some(()) => IdlValue::Option(Some(Box::new(self.decode_nested(inner, r, depth)?))),
+            },
+            IdlType::Vec(inner) => {
+                let len = r.read_u32_le()? as usize;
+                // Guard the allocation: elements take at least one byte each.
+                if len > r.remaining() {
+                    return Err(ParseError::MalformedData(format!(
+                        "vec length {} exceeds remaining {} bytes",
+                        len,
+                        r.remaining()
+                    )));
+                }
+                IdlValue::Array(
+                    (0..len)
+                        .map(|_| self.decode_nested(inner, r, depth))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            IdlType::Array(inner, len) => IdlValue::Array(
+                (0..*len)
+                    .map(|_| self.decode_nested(inner, r, depth))
+                    .collect::<Result<_, _>>()?,
+            ),
+            IdlType::Defined(name) => match self.types.get(name) {
+                Some(IdlTypeDef::Struct(fields)) => self.decode_fields(fields, r, depth)?,
+                Some(IdlTypeDef::Alias(ty)) => self.decode_nested(ty, r, depth)?,
+                Some(IdlTypeDef::Enum(variants)) => {
+                    let tag = r.read_u8()? as usize;
+                    let variant = variants.get(tag).ok_or_else(|| {
+                        ParseError::MalformedData(format!("{} has no variant {}", name, tag))
+                    })?;
+                    IdlValue::Enum {
+                        variant: variant.name.clone(),
+                        value: match &variant.fields {
+                            Some(fields) => Some(Box::new(self.decode_fields(fields, r, depth)?)),
+                            None => None,
+                        },
+                    }
+                }
+                None => return Err(invalid(format!("undefined type {}", name))),
+            },
+        })
+    }
+}
+
+fn read_array<const N: usize>(r: &mut BinaryReader) -> Result<[u8; N], ParseError> {
+    let mut out = [0u8; N];
+    out.copy_from_slice(r.read_fixed_array(N)?);
+    Ok(out)
+}
+
+fn str_field(value: &Value, key: &str) -> Result<String, ParseError> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| invalid(format!("missing `{}` in {}", key, value)))
+}
+
+fn parse_bytes(value: &Value) -> Result<Vec<u8>, ParseError> {
+    value
+        .as_array()
+        .ok_or_else(|| invalid("discriminator is not an array"))?
+        .iter()
+        .map(|b| {
+            b.as_u64()
+                .and_then(|b| u8::try_from(b).ok())
+                .ok_or_else(|| invalid(format!("bad discriminator byte {}", b)))
+        })
+        .collect()
+}
+
+fn flatten_accounts(value: Option<&Value>, out: &mut Vec<String>) {
+    for account in value.and_then(Value::as_array).into_iter().flatten() {
+        if let Some(nested) = account.get("accounts") {
+            flatten_accounts(Some(nested), out);
+        } else if let Some(name) = account.get("name").and_then(Value::as_str) {
+            out.push(name.to_string());
+        }
+    }
+}
+
+fn parse_named_fields(value: &Value) -> Result<Vec<IdlField>, ParseError> {
+    value
+        .as_array()
+        .ok_or_else(|| invalid("fields is not an array"))?
+        .iter()
+        .map(|f| {
+            Ok(IdlField {
+                name: str_field(f, "name")?,
+                ty: parse_type(
+                    f.get("type")
+                        .ok_or_else(|| invalid(format!("field without type: {}", f)))?,
+                )?,
+            })
+        })
+        .collect()
+}
+
+/// Struct/variant fields: named (`[{name, type}]`) or tuple (`[type, ...]`).
+fn parse_fields(value: &Value) -> Result<IdlFields, ParseError> {
+    let items = value
+        .as_array()
+        .ok_or_else(|| invalid("fields is not an array"))?;
+    if items
+        .iter()
+        .all(|f| f.get("name").is_some() && f.get("type").is_some())
+    {
+        Ok(IdlFields::Named(parse_named_fields(value)?))
+    } else {
+        Ok(IdlFields::Tuple(
+            items.iter().map(parse_type).collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+fn parse_type_def(value: &Value) -> Result<IdlTypeDef, ParseError> {
+    match value.get("kind").and_then(Value::as_str) {
+        Some("struct") => Ok(IdlTypeDef::Struct(match value.get("fields") {
+            Some(fields) => parse_fields(fields)?,
+            None => IdlFields::Named(Vec::new()),
+        })),
+        Some("enum") => Ok(IdlTypeDef::Enum(
+            value
+                .get("variants")
+                .and_then(Value::as_array)
+                .ok_or_else(|| invalid("enum without variants"))?
+                .iter()
+                .map(|v| {
+                    Ok(IdlEnumVariant {
+                        name: str_field(v, "name")?,
+                        fields: v.get("fields").map(parse_fields).transpose()?,
+                    })
+                })
+                .collect::<Result<_, ParseError>>()?,
+        )),
+        Some("type") | Some("alias") => Ok(IdlTypeDef::Alias(parse_type(
+            value
+                .get("alias")
+                .or_else(|| value.get("value"))
+                .ok_or_else(|| invalid("alias without type"))?,
+        )?)),
+        other => Err(invalid(format!("unsupported type kind {:?}", other))),
+    }
+}
+
+fn parse_type(value: &Value) -> Result<IdlType, ParseError> {
+    if let Some(name) = value.as_str() {
+        return Ok(match name {
+            "bool" => IdlType::Bool,
+            "u8" => IdlType::U8,
+            "u16" => IdlType::U16,
+            "u32" => IdlType::U32,
+            "u64" => IdlType::U64,
+            "u128" => IdlType::U128,
+            "i8" => IdlType::I8,
+            "i16" => IdlType::I16,
+            "i32" => IdlType::I32,
+            "i64" => IdlType::I64,
+            "i128" => IdlType::I128,
+            "f32" => IdlType::F32,
+            "f64" => IdlType::F64,
+            "string" => IdlType::String,
+            "bytes" => IdlType::Bytes,
+            "pubkey" | "publicKey" => IdlType::Pubkey,
+            other => return Err(invalid(format!("unsupported type {}", other))),
+        });
+    }
+    if let Some(inner) = value.get("option") {
+        return Ok(IdlType::Option(Box::new(parse_type(inner)?)));
+    }
+    if let Some(inner) = value.get("coption") {
+        return Ok(IdlType::COption(Box::new(parse_type(inner)?)));
+    }
+    if let Some(inner) = value.get("vec") {
+        return Ok(IdlType::Vec(Box::new(parse_type(inner)?)));
+    }
+    if let Some(array) = value.get("array").and_then(Value::as_array) {
+        if let [inner, len] = array.as_slice() {
+            let len = len
+                .as_u64()
+                .ok_or_else(|| invalid(format!("unsupported array length {}", len)))?;
+            return Ok(IdlType::Array(Box::new(parse_type(inner)?), len as usize));
+        }
+    }
+    if let Some(defined) = value.get("defined") {
+        // Legacy: `{"defined": "Name"}`; new: `{"defined": {"name": "Name"}}`
+        let name = defined
+            .as_str()
+            .or_else(|| defined.get("name").and_then(Value::as_str))
+            .ok_or_else(|| invalid(format!("bad defined type {}", defined)))?;
+        return Ok(IdlType::Defined(name.to_string()));
+    }
+    Err(invalid(format!("unsupported type {}", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn idl(value: Value) -> Idl {
+        Idl::from_value(&value).unwrap()
+    }
+
+    /// Anchor 0.30+ IDL: explicit discriminators, event layout under `types`.
+    fn new_format() -> Idl {
+        idl(json!({
+            "address": "Prog111111111111111111111111111111111111111",
+            "metadata": { "name": "prog" },
+            "instructions": [{
+                "name": "swap",
+                "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                "accounts": [{ "name": "user" }, { "name": "pool", "accounts": [{ "name": "vault" }] }],
+                "args": [
+                    { "name": "amount", "type": "u64" },
+                    { "name": "params", "type": { "option": { "defined": { "name": "Params" } } } }
+                ]
+            }],
+            "events": [{ "name": "Swapped", "discriminator": [9, 9, 9, 9, 9, 9, 9, 9] }],
+            "types": [
+                { "name": "Swapped", "type": { "kind": "struct", "fields": [
+                    { "name": "side", "type": { "defined": { "name": "Side" } } },
+                    { "name": "hops", "type": { "vec": { "defined": { "name": "Side" } } } },
+                    { "name": "limits", "type": { "array": ["u16", 2] } }
+                ] } },
+                { "name": "Params", "type": { "kind": "struct", "fields": [
+                    { "name": "flag", "type": "bool" },
+                    { "name": "delta", "type": "i16" }
+                ] } },
+                { "name": "Side", "type": { "kind": "enum", "variants": [
+                    { "name": "Buy" },
+                    { "name": "Sell", "fields": ["u8"] },
+                    { "name": "Route", "fields": [{ "name": "hops", "type": "u8" }] }
+                ] } }
+            ]
+        }))
+    }
+
+    #[test]
+    fn reads_new_format_discriminators() {
+        let idl = new_format();
+        assert_eq!(idl.name.as_deref(), Some("prog"));
+        assert_eq!(
+            idl.address.as_deref(),
+            Some("Prog111111111111111111111111111111111111111")
+        );
+        let swap = idl.instruction("swap").unwrap();
+        assert_eq!(swap.discriminator, [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(swap.accounts, ["user", "vault"]);
+        assert_eq!(idl.event("Swapped").unwrap().discriminator, [9; 8]);
+    }
+
+    #[test]
+    fn derives_legacy_discriminators() {
+        let idl = idl(json!({
+            "name": "legacy",
+            "metadata": { "address": "Legacy11111111111111111111111111111111111111" },
+            "instructions": [
+                { "name": "buy", "accounts": [], "args": [] },
+                { "name": "swapBaseInput", "accounts": [], "args": [] }
+            ],
+            "events": [{ "name": "SwapEvent", "fields": [{ "name": "amm", "type": "publicKey" }] }]
+        }));
+        assert_eq!(
+            idl.address.as_deref(),
+            Some("Legacy11111111111111111111111111111111111111")
+        );
+        assert_eq!(
+            idl.instruction("buy").unwrap().discriminator,
+            discriminators::PUMPFUN_BUY
+        );
+        assert_eq!(
+            idl.instruction("swapBaseInput").unwrap().discriminator,
+            discriminators::RAYDIUM_CPMM_SWAP_BASE_INPUT
+        );
+        assert_eq!(
+            idl.event("SwapEvent").unwrap().discriminator,
+            discriminators::JUPITER_ROUTE_EVENT[8..]
+        );
+    }
+
+    #[test]
+    fn decodes_nested_defined_types() {
+        let idl = new_format();
+        let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        data.extend(500u64.to_le_bytes());
+        data.extend([1, 1]); // Some(Params { flag: true, .. })
+        data.extend((-3i16).to_le_bytes());
+        let decoded = idl.decode_instruction(&data).unwrap().unwrap();
+        assert_eq!(decoded.name, "swap");
+        assert_eq!(
+            decoded.data.get("amount").and_then(IdlValue::as_u64),
+            Some(500)
+        );
+        let Some(IdlValue::Option(Some(params))) = decoded.data.get("params") else {
+            panic!("expected Some(params): {:?}", decoded.data);
+        };
+        assert_eq!(params.get("flag").and_then(IdlValue::as_bool), Some(true));
+        assert_eq!(params.get("delta").and_then(IdlValue::as_i64), Some(-3));
+
+        let mut event = vec![9; 8];
+        event.extend([1, 7]); // Sell(7)
+        event.extend(2u32.to_le_bytes());
+        event.extend([0, 2, 4]); // [Buy, Route { hops: 4 }]
+        event.extend(10u16.to_le_bytes());
+        event.extend(20u16.to_le_bytes());
+        let decoded = idl.decode_event(&event).unwrap().unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded.data).unwrap(),
+            json!({
+                "side": { "Sell": [7] },
+                "hops": ["Buy", { "Route": { "hops": 4 } }],
+                "limits": [10, 20]
+            })
+        );
+    }
+
+    #[test]
+    fn strips_the_emit_cpi_prefix() {
+        let idl = new_format();
+        let mut event = vec![9; 8];
+        event.extend([0, 0, 0, 0, 0, 1, 0, 2, 0]);
+        let plain = idl.decode_event(&event).unwrap().unwrap();
+        let mut cpi = discriminators::ANCHOR_EVENT_CPI.to_vec();
+        cpi.extend(&event);
+        let via_cpi = idl.decode_event(&cpi).unwrap().unwrap();
+        assert_eq!(
+            (via_cpi.name.as_str(), &via_cpi.data),
+            ("Swapped", &plain.data)
+        );
+        assert!(idl.decode_event(&[7; 16]).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_truncated_and_invalid_data() {
+        let idl = new_format();
+        let mut short = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        short.extend([0; 4]);
+        assert!(matches!(
+            idl.decode_instruction(&short),
+            Err(ParseError::MalformedData(_))
+        ));
+        let mut huge_vec = vec![9; 8];
+        huge_vec.push(0);
+        huge_vec.extend(u32::MAX.to_le_bytes());
+        assert!(matches!(
+            idl.decode_event(&huge_vec),
+            Err(ParseError::MalformedData(msg)) if msg.contains("vec length")
+        ));
+        assert!(matches!(
+            idl.decode_defined("Side", &[3]),
+            Err(ParseError::MalformedData(msg)) if msg.contains("no variant 3")
+        ));
+        assert!(matches!(
+            idl.decode_defined("Missing", &[]),
+            Err(ParseError::InvalidIdl(_))
+        ));
+    }
+
+    #[test]
+    fn self_referential_types_fail_instead_of_overflowing() {
+        let idl = idl(json!({
+            "instructions": [],
+            "types": [
+                { "name": "Loop", "type": { "kind": "type", "alias": { "defined": "Loop" } } },
+                { "name": "Node", "type": { "kind": "struct", "fields": [
+                    { "name": "next", "type": { "defined": "Node" } }
+                ] } }
+            ]
+        }));
+        for name in ["Loop", "Node"] {
+            assert!(matches!(
+                idl.decode_defined(name, &[0; 64]),
+                Err(ParseError::InvalidIdl(msg)) if msg.contains("nest deeper")
+            ));
+        }
+    }
+}
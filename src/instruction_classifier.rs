@@ -1,12 +1,23 @@
-//! Classifies instructions by program ID (outer + inner).
+//! Classifies instructions by program ID (outer + inner) and reconstructs the CPI call
+//! tree from inner instruction stack heights.
 
 use crate::constants::{SKIP_PROGRAM_IDS, SYSTEM_PROGRAMS};
+use crate::instruction_index::InstructionIndex;
 use crate::transaction_adapter::TransactionAdapter;
-use crate::types::{ClassifiedInstruction, ParsedInstruction};
+use crate::types::{ClassifiedInstruction, RawInstruction};
+use solana_sdk::pubkey::Pubkey;
 use std::collections::HashMap;
 
 pub struct InstructionClassifier<'a> {
-    instruction_map: HashMap<String, Vec<ClassifiedInstruction>>,
+    instruction_map: HashMap<Pubkey, Vec<ClassifiedInstruction>>,
+    /// Program ids by first appearance in execution order.
+    program_order: Vec<Pubkey>,
+    /// Program of every instruction.
+    programs: HashMap<InstructionIndex, Pubkey>,
+    /// Invoking instruction of each inner instruction whose stack height is known.
+    invokers: HashMap<InstructionIndex, InstructionIndex>,
+    /// Direct children of each instruction in the call tree, in execution order.
+    children: HashMap<InstructionIndex, Vec<InstructionIndex>>,
     _adapter: &'a TransactionAdapter<'a>,
 }
 
@@ -14,79 +25,185 @@ impl<'a> InstructionClassifier<'a> {
     pub fn new(adapter: &'a TransactionAdapter<'a>) -> Self {
         let mut classifier = Self {
             instruction_map: HashMap::new(),
+            program_order: Vec::new(),
+            programs: HashMap::new(),
+            invokers: HashMap::new(),
+            children: HashMap::new(),
             _adapter: adapter,
         };
         classifier.classify_instructions(adapter);
+        classifier.index_children();
         classifier
     }
 
+    /// Classify in execution order: each outer instruction followed by its inner ones.
     fn classify_instructions(&mut self, adapter: &TransactionAdapter<'a>) {
+        let classify =
+            |raw: &RawInstruction, outer_index: usize, inner_index: Option<usize>, stack_height| {
+                let instruction = adapter.get_instruction(raw)?;
+                Some(ClassifiedInstruction {
+                    program_id: instruction.program_id,
+                    instruction,
+                    outer_index,
+                    inner_index,
+                    stack_height,
+                })
+            };
+        let inner = adapter.raw_inner_instructions().unwrap_or_default();
         for (outer_index, raw) in adapter.raw_instructions().iter().enumerate() {
-            let program_id = adapter.get_instruction_program_id(raw);
-            self.add_instruction(ClassifiedInstruction {
-                instruction: ParsedInstruction {
-                    program_id: program_id.clone(),
-                    accounts: raw
-                        .account_key_indexes
-                        .iter()
-                        .filter_map(|&i| adapter.get_account_key(i as usize))
-                        .collect(),
-                    data: raw.data.clone(),
-                    parsed: None,
-                },
-                program_id,
-                outer_index,
-                inner_index: None,
-            });
-        }
-        if let Some(inner) = adapter.raw_inner_instructions() {
-            for set in inner {
+            self.add_instruction(classify(raw, outer_index, None, Some(1)));
+            // Open invocations, innermost last: an instruction at height h was invoked by
+            // the closest preceding one at height h - 1.
+            let mut call_stack = vec![(InstructionIndex::outer(outer_index), 1)];
+            for set in inner.iter().filter(|s| s.index as usize == outer_index) {
                 for (inner_index, raw) in set.instructions.iter().enumerate() {
-                    let program_id = adapter.get_instruction_program_id(raw);
-                    self.add_instruction(ClassifiedInstruction {
-                        instruction: ParsedInstruction {
-                            program_id: program_id.clone(),
-                            accounts: raw
-                                .account_key_indexes
-                                .iter()
-                                .filter_map(|&i| adapter.get_account_key(i as usize))
-                                .collect(),
-                            data: raw.data.clone(),
-                            parsed: None,
-                        },
-                        program_id,
-                        outer_index: set.index as usize,
-                        inner_index: Some(inner_index),
-                    });
+                    let idx = InstructionIndex::inner(outer_index, inner_index);
+                    if let Some(height) = raw.stack_height {
+                        while call_stack.len() > 1
+                            && call_stack.last().is_some_and(|(_, h)| *h >= height)
+                        {
+                            call_stack.pop();
+                        }
+                        if let Some((invoker, _)) = call_stack.last() {
+                            self.invokers.insert(idx, *invoker);
+                        }
+                        call_stack.push((idx, height));
+                    }
+                    self.add_instruction(classify(
+                        raw,
+                        outer_index,
+                        Some(inner_index),
+                        raw.stack_height,
+                    ));
                 }
             }
         }
     }
 
-    fn add_instruction(&mut self, classified: ClassifiedInstruction) {
-        if classified.program_id.is_empty() {
+    fn index_children(&mut self) {
+        let mut children: HashMap<InstructionIndex, Vec<InstructionIndex>> = HashMap::new();
+        for idx in self.programs.keys() {
+            if let Some(parent) = self.get_parent(idx) {
+                children.entry(parent).or_default().push(*idx);
+            }
+        }
+        for siblings in children.values_mut() {
+            siblings.sort();
+        }
+        self.children = children;
+    }
+
+    fn add_instruction(&mut self, classified: Option<ClassifiedInstruction>) {
+        let Some(classified) = classified else {
             return;
+        };
+        self.programs
+            .insert(classified.idx(), classified.program_id);
+        if !self.instruction_map.contains_key(&classified.program_id) {
+            self.program_order.push(classified.program_id);
         }
         self.instruction_map
-            .entry(classified.program_id.clone())
+            .entry(classified.program_id)
             .or_default()
             .push(classified);
     }
 
-    pub fn get_instructions(&self, program_id: &str) -> Vec<ClassifiedInstruction> {
+    /// Instructions of `program_id` in execution order, borrowed from the classifier.
+    pub fn get_instructions(&self, program_id: &Pubkey) -> &[ClassifiedInstruction] {
         self.instruction_map
             .get(program_id)
-            .cloned()
+            .map(Vec::as_slice)
             .unwrap_or_default()
     }
 
-    pub fn get_all_program_ids(&self) -> Vec<String> {
-        self.instruction_map
-            .keys()
-            .filter(|id| {
-                !SYSTEM_PROGRAMS.contains(&id.as_str()) && !SKIP_PROGRAM_IDS.contains(&id.as_str())
-            })
-            .cloned()
+    /// Invoked programs, excluding system and skipped ones, by first appearance.
+    pub fn get_all_program_ids(&self) -> Vec<Pubkey> {
+        self.program_order
+            .iter()
+            .filter(|id| !SYSTEM_PROGRAMS.contains(id) && !SKIP_PROGRAM_IDS.contains(id))
+            .copied()
             .collect()
     }
+
+    pub fn get_program_id(&self, idx: &InstructionIndex) -> Option<&Pubkey> {
+        self.programs.get(idx)
+    }
+
+    /// The instruction that invoked `idx`, known only from stack heights.
+    pub fn get_invoker(&self, idx: &InstructionIndex) -> Option<InstructionIndex> {
+        self.invokers.get(idx).copied()
+    }
+
+    /// Parent of `idx` in the call tree: its invoker when stack heights tell, else the
+    /// outer instruction it belongs to. `None` for outer instructions.
+    pub fn get_parent(&self, idx: &InstructionIndex) -> Option<InstructionIndex> {
+        self.get_invoker(idx).or_else(|| idx.parent())
+    }
+
+    /// Instructions invoked directly by `idx`, in execution order.
+    pub fn get_children(&self, idx: &InstructionIndex) -> &[InstructionIndex] {
+        self.children
+            .get(idx)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{cpi, ix, key, tx};
+    use crate::types::InnerInstructionSet;
+
+    fn i(s: &str) -> InstructionIndex {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn rebuilds_the_call_tree_from_stack_heights() {
+        // 0 invokes 0-0 and 0-3; 0-0 invokes 0-1 and 0-2; 0-1 invokes nothing.
+        let input = tx(
+            (1..=4).map(key).collect(),
+            vec![ix(1, &[], vec![])],
+            vec![InnerInstructionSet {
+                index: 0,
+                instructions: vec![
+                    cpi(2, &[], vec![], 2),
+                    cpi(3, &[], vec![], 3),
+                    cpi(3, &[], vec![], 3),
+                    cpi(2, &[], vec![], 2),
+                ],
+            }],
+        );
+        let adapter = TransactionAdapter::new(&input, None);
+        let c = InstructionClassifier::new(&adapter);
+        assert_eq!(c.get_invoker(&i("0")), None);
+        assert_eq!(c.get_parent(&i("0-2")), Some(i("0-0")));
+        assert_eq!(c.get_parent(&i("0-3")), Some(i("0")));
+        assert_eq!(c.get_children(&i("0")), [i("0-0"), i("0-3")]);
+        assert_eq!(c.get_children(&i("0-0")), [i("0-1"), i("0-2")]);
+        assert!(c.get_children(&i("0-1")).is_empty());
+    }
+
+    #[test]
+    fn without_stack_heights_inner_instructions_hang_off_their_outer_one() {
+        let input = tx(
+            (1..=3).map(key).collect(),
+            vec![ix(1, &[], vec![]), ix(1, &[], vec![])],
+            vec![InnerInstructionSet {
+                index: 1,
+                instructions: vec![ix(2, &[], vec![]), ix(2, &[], vec![])],
+            }],
+        );
+        let adapter = TransactionAdapter::new(&input, None);
+        let c = InstructionClassifier::new(&adapter);
+        assert_eq!(c.get_invoker(&i("1-1")), None);
+        assert_eq!(c.get_parent(&i("1-1")), Some(i("1")));
+        assert_eq!(c.get_children(&i("1")), [i("1-0"), i("1-1")]);
+        assert!(c.get_children(&i("0")).is_empty());
+        assert_eq!(
+            c.get_all_program_ids(),
+            [adapter.account_keys[1], adapter.account_keys[2]]
+        );
+    }
 }
@@ -0,0 +1,179 @@
+//! Position of an instruction within a transaction.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// Index of an outer instruction, or of an inner instruction within the outer one that
+/// invoked it. Formats as `"3"` for outer instruction 3 and `"3-2"` for its third inner
+/// instruction, and (de)serializes as that string.
+///
+/// Ordered by execution: an outer instruction precedes its inner ones. `stack_height`
+/// is informational and ignored by comparisons.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InstructionIndex {
+    pub outer: usize,
+    pub inner: Option<usize>,
+    /// Invocation depth when known: 1 for outer instructions, 2 for their direct CPIs, ...
+    pub stack_height: Option<u32>,
+}
+
+impl InstructionIndex {
+    pub fn new(outer: usize, inner: Option<usize>) -> Self {
+        Self {
+            outer,
+            inner,
+            stack_height: None,
+        }
+    }
+
+    pub fn outer(outer: usize) -> Self {
+        Self::new(outer, None)
+    }
+
+    pub fn inner(outer: usize, inner: usize) -> Self {
+        Self::new(outer, Some(inner))
+    }
+
+    pub fn with_stack_height(mut self, stack_height: Option<u32>) -> Self {
+        self.stack_height = stack_height;
+        self
+    }
+
+    pub fn is_outer(&self) -> bool {
+        self.inner.is_none()
+    }
+
+    /// The outer instruction an inner instruction belongs to; `None` for outer instructions.
+    pub fn parent(&self) -> Option<Self> {
+        self.inner
+            .map(|_| Self::outer(self.outer).with_stack_height(Some(1)))
+    }
+
+    /// Whether `self` executed inside the outer instruction `other`.
+    pub fn is_child_of(&self, other: &Self) -> bool {
+        other.is_outer() && !self.is_outer() && self.outer == other.outer
+    }
+
+    fn key(&self) -> (usize, Option<usize>) {
+        (self.outer, self.inner)
+    }
+}
+
+impl PartialEq for InstructionIndex {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for InstructionIndex {}
+
+impl Hash for InstructionIndex {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl PartialOrd for InstructionIndex {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for InstructionIndex {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl fmt::Display for InstructionIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.inner {
+            Some(inner) => write!(f, "{}-{}", self.outer, inner),
+            None => write!(f, "{}", self.outer),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid instruction index {0:?}")]
+pub struct ParseInstructionIndexError(String);
+
+impl FromStr for InstructionIndex {
+    type Err = ParseInstructionIndexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseInstructionIndexError(s.to_string());
+        let (outer, inner) = match s.split_once('-') {
+            Some((outer, inner)) => (outer, Some(inner.parse().map_err(|_| err())?)),
+            None => (s, None),
+        };
+        Ok(Self::new(outer.parse().map_err(|_| err())?, inner))
+    }
+}
+
+impl Serialize for InstructionIndex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for InstructionIndex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_outer_and_inner_forms() {
+        assert_eq!("3".parse(), Ok(InstructionIndex::outer(3)));
+        assert_eq!("3-2".parse(), Ok(InstructionIndex::inner(3, 2)));
+        assert_eq!("3-0".parse(), Ok(InstructionIndex::inner(3, 0)));
+        for bad in ["", "-", "3-", "-2", "a", "3-b", "3-2-1", "-1"] {
+            assert_eq!(
+                bad.parse::<InstructionIndex>(),
+                Err(ParseInstructionIndexError(bad.to_string())),
+                "{bad:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn orders_by_execution() {
+        let mut indexes = [
+            InstructionIndex::outer(1),
+            InstructionIndex::inner(0, 1),
+            InstructionIndex::inner(1, 0),
+            InstructionIndex::outer(0),
+            InstructionIndex::inner(0, 0),
+        ];
+        indexes.sort();
+        let order: Vec<String> = indexes.iter().map(ToString::to_string).collect();
+        assert_eq!(order, ["0", "0-0", "0-1", "1", "1-0"]);
+        assert_eq!(
+            InstructionIndex::inner(2, 1).with_stack_height(Some(3)),
+            InstructionIndex::inner(2, 1)
+        );
+    }
+
+    #[test]
+    fn serde_round_trips_through_the_string_form() {
+        for idx in [InstructionIndex::outer(7), InstructionIndex::inner(7, 4)] {
+            let json = serde_json::to_string(&idx).unwrap();
+            assert_eq!(json, format!("\"{idx}\""));
+            assert_eq!(
+                serde_json::from_str::<InstructionIndex>(&json).unwrap(),
+                idx
+            );
+        }
+        assert!(serde_json::from_str::<InstructionIndex>("\"x-1\"").is_err());
+        assert!(serde_json::from_str::<InstructionIndex>("3").is_err());
+    }
+}
@@ -3,25 +3,54 @@
 //! Build a [SolanaTransactionInput] from RPC/Geyser data (account keys, instructions, meta),
 //! then use [DexParser] to parse trades (Jupiter, Raydium, Meteora, Orca, Pumpfun, etc.).
 
+pub mod arbitrage;
 pub mod binary_reader;
+pub mod compute_budget;
 pub mod constants;
 pub mod dex_parser;
+pub mod error;
+pub mod idl;
 pub mod instruction_classifier;
+pub mod instruction_index;
 pub mod parsers;
+pub mod quote_assets;
+pub mod sandwich;
 pub mod shred_parser;
+#[cfg(test)]
+mod test_support;
+pub mod tips;
+pub mod token_metadata;
 pub mod transaction_adapter;
 pub mod transaction_utils;
 pub mod types;
 pub mod utils;
+pub mod valuation;
 
-pub use dex_parser::DexParser;
+pub use arbitrage::Arbitrage;
+pub use compute_budget::{ComputeBudget, FeeBreakdown};
+pub use dex_parser::{DexParser, DexParserBuilder};
+pub use error::ParseError;
+pub use idl::{Idl, IdlDecoded, IdlValue};
+pub use instruction_index::InstructionIndex;
+pub use parsers::{ParseContext, ParserRegistry, ProtocolParser};
+pub use quote_assets::QuoteAssets;
+pub use sandwich::{Sandwich, SandwichTrade, SandwichVictim};
 pub use shred_parser::ShredParser;
+pub use tips::{Tip, TipAccounts};
+pub use token_metadata::{InMemoryTokenMetadata, TokenMetadata, TokenMetadataProvider};
 pub use types::{
-    BalanceChange, ClassifiedInstruction, DexInfo, MemeEvent, ParseConfig, ParseResult,
-    ParseShredResult, PoolEvent, SolanaTransactionInput, TokenAmount, TokenInfo, TradeInfo,
-    TradeType, TransferData, TransactionStatus,
+    AddressTableLookupInput, InnerInstructionSet, LoadedAddressesInput, RawInstruction,
+    TokenBalanceInput, TransactionMetaInput, UiTokenAmountInput,
 };
 pub use types::{
-    InnerInstructionSet, RawInstruction, TokenBalanceInput, TransactionMetaInput,
-    UiTokenAmountInput,
+    BalanceChange, BlockInfo, BlockMeta, ClassifiedInstruction, DexInfo, MemeEvent, MessageHeader,
+    ParseConfig, ParseDiagnostic, ParseOutput, ParseResult, ParseShredResult, PoolEvent,
+    SolanaTransactionInput, SwapIntent, TokenAmount, TokenInfo, TradeInfo, TradeType,
+    TransactionStatus, TransferData,
 };
+pub use types::{
+    DecodedInstruction, JupiterInstruction, MeteoraInstruction, OrcaInstruction,
+    ProgramInstructions, PumpfunInstruction, PumpswapInstruction, RaydiumInstruction,
+    ShredInstruction,
+};
+pub use valuation::{PriceSource, StaticPriceSource, TradeValuation};
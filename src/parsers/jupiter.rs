@@ -1,12 +1,20 @@
 //! Jupiter DEX parser (route swap events).
 
-use borsh::BorshDeserialize;
+use crate::binary_reader::BinaryReader;
+use crate::constants::get_program_name;
 use crate::constants::{dex_programs, discriminators};
+use crate::error::ParseError;
+use crate::instruction_index::InstructionIndex;
+use crate::parsers::{ParseContext, ProtocolParser};
 use crate::transaction_adapter::TransactionAdapter;
 use crate::transaction_utils::TransactionUtils;
-use crate::types::{ClassifiedInstruction, DexInfo, TradeInfo, TransferData};
-use crate::constants::get_program_name;
-use crate::utils::{convert_to_ui_amount, get_trade_type};
+use crate::types::{
+    ClassifiedInstruction, DexInfo, JupiterInstruction, ParseOutput, SwapIntent, TradeInfo,
+    TransferData,
+};
+use crate::utils::{convert_to_ui_amount, get_instruction_discriminator};
+use borsh::BorshDeserialize;
+use solana_sdk::pubkey::Pubkey;
 use std::collections::HashMap;
 
 #[derive(BorshDeserialize)]
@@ -21,43 +29,45 @@ pub struct JupiterSwapLayout {
 pub struct JupiterParser<'a> {
     adapter: &'a TransactionAdapter<'a>,
     dex_info: DexInfo,
-    _transfer_actions: HashMap<String, Vec<TransferData>>,
-    classified_instructions: Vec<ClassifiedInstruction>,
+    transfer_actions: &'a HashMap<(Pubkey, InstructionIndex), Vec<TransferData>>,
+    classified_instructions: &'a [ClassifiedInstruction],
 }
 
 impl<'a> JupiterParser<'a> {
     pub fn new(
         adapter: &'a TransactionAdapter<'a>,
         dex_info: DexInfo,
-        transfer_actions: HashMap<String, Vec<TransferData>>,
-        classified_instructions: Vec<ClassifiedInstruction>,
+        transfer_actions: &'a HashMap<(Pubkey, InstructionIndex), Vec<TransferData>>,
+        classified_instructions: &'a [ClassifiedInstruction],
     ) -> Self {
         Self {
             adapter,
             dex_info,
-            _transfer_actions: transfer_actions,
+            transfer_actions,
             classified_instructions,
         }
     }
 
-    pub fn process_trades(&self) -> Vec<TradeInfo> {
-        let mut trades = Vec::new();
+    pub fn process_trades(&self) -> ParseOutput {
+        let mut output = ParseOutput::default();
         let utils = TransactionUtils::new(self.adapter);
-        for ci in &self.classified_instructions {
-            if ci.program_id != dex_programs::JUPITER.id {
+        for ci in self.classified_instructions {
+            if ci.program_id != dex_programs::JUPITER.key {
                 continue;
             }
             if self.is_jupiter_route_event_instruction(&ci.instruction.data) {
-                let idx = format!("{}-{}", ci.outer_index, ci.inner_index.unwrap_or(0));
-                if let Some(event) = self.parse_jupiter_route_event(&ci.instruction.data, &idx) {
-                    let trade = self.build_trade_from_event(event, &utils);
-                    if let Some(t) = trade {
-                        trades.push(t);
+                let idx = ci.idx();
+                match self.parse_jupiter_route_event(&ci.instruction.data, idx) {
+                    Ok(event) => {
+                        if let Some(t) = self.build_trade_from_event(event, &utils) {
+                            output.trades.push(t);
+                        }
                     }
+                    Err(e) => output.errors.push(e),
                 }
             }
         }
-        trades
+        output
     }
 
     fn is_jupiter_route_event_instruction(&self, data: &[u8]) -> bool {
@@ -70,62 +80,70 @@ impl<'a> JupiterParser<'a> {
     fn parse_jupiter_route_event(
         &self,
         data: &[u8],
-        idx: &str,
-    ) -> Option<JupiterSwapEventData> {
-        if data.len() < 16 {
-            return None;
-        }
+        idx: InstructionIndex,
+    ) -> Result<JupiterSwapEventData, ParseError> {
         let event_data = &data[16..];
-        let layout: JupiterSwapLayout = BorshDeserialize::try_from_slice(event_data).ok()?;
+        let layout: JupiterSwapLayout = BorshDeserialize::try_from_slice(event_data)
+            .map_err(|e| ParseError::decode(dex_programs::JUPITER.id, idx, e))?;
         let input_mint = bs58::encode(layout.input_mint).into_string();
         let output_mint = bs58::encode(layout.output_mint).into_string();
         let amm = bs58::encode(layout.amm).into_string();
-        Some(JupiterSwapEventData {
+        Ok(JupiterSwapEventData {
             amm,
             input_mint: input_mint.clone(),
             input_amount: layout.input_amount,
             output_mint: output_mint.clone(),
             output_amount: layout.output_amount,
-            input_mint_decimals: self.adapter.get_token_decimals(&input_mint),
-            output_mint_decimals: self.adapter.get_token_decimals(&output_mint),
-            idx: idx.to_string(),
+            input_mint_decimals: self.adapter.resolve_token_decimals(&input_mint, 0),
+            output_mint_decimals: self.adapter.resolve_token_decimals(&output_mint, 0),
+            idx,
         })
     }
 
     fn build_trade_from_event(
         &self,
         event: JupiterSwapEventData,
-        _utils: &TransactionUtils<'a>,
+        utils: &TransactionUtils<'a>,
     ) -> Option<TradeInfo> {
-        let signer_index = if self.adapter.account_keys.contains(&dex_programs::JUPITER_DCA.id.to_string()) {
-            2
-        } else {
-            0
-        };
-        let user = self.adapter.get_account_key(signer_index).unwrap_or_else(|| self.adapter.signer());
+        let transfers = TransactionUtils::get_transfers_for_outer_instruction(
+            self.transfer_actions,
+            event.idx.outer,
+        );
+        let user = utils.get_swap_signer(&transfers, &event.input_mint);
+        let (input_decimals, input_defaulted) = event.input_mint_decimals;
+        let (output_decimals, output_defaulted) = event.output_mint_decimals;
         Some(TradeInfo {
-            user: user.clone(),
-            trade_type: get_trade_type(&event.input_mint, &event.output_mint),
+            user,
+            trade_type: self
+                .adapter
+                .quote_assets()
+                .trade_type(&event.input_mint, &event.output_mint),
             pool: vec![],
             input_token: crate::types::TokenInfo {
                 mint: event.input_mint.clone(),
-                amount: convert_to_ui_amount(event.input_amount, event.input_mint_decimals),
+                amount: convert_to_ui_amount(event.input_amount, input_decimals),
                 amount_raw: event.input_amount.to_string(),
-                decimals: event.input_mint_decimals,
+                decimals: input_decimals,
                 authority: None,
                 destination: None,
                 destination_owner: None,
                 source: None,
+                decimals_defaulted: input_defaulted,
+                symbol: None,
+                name: None,
             },
             output_token: crate::types::TokenInfo {
                 mint: event.output_mint.clone(),
-                amount: convert_to_ui_amount(event.output_amount, event.output_mint_decimals),
+                amount: convert_to_ui_amount(event.output_amount, output_decimals),
                 amount_raw: event.output_amount.to_string(),
-                decimals: event.output_mint_decimals,
+                decimals: output_decimals,
                 authority: None,
                 destination: None,
                 destination_owner: None,
                 source: None,
+                decimals_defaulted: output_defaulted,
+                symbol: None,
+                name: None,
             },
             slippage_bps: None,
             fee: None,
@@ -133,16 +151,58 @@ impl<'a> JupiterParser<'a> {
             program_id: self.dex_info.program_id.clone(),
             amm: Some(get_program_name(&event.amm).to_string()),
             amms: Some(vec![get_program_name(&event.amm).to_string()]),
-            route: self.dex_info.route.clone().or_else(|| Some("Jupiter".to_string())),
+            route: self
+                .dex_info
+                .route
+                .clone()
+                .or_else(|| Some("Jupiter".to_string())),
             slot: self.adapter.slot(),
             timestamp: self.block_time(),
-            signature: self.adapter.signature(),
+            signature: self.adapter.signature().to_string(),
             idx: event.idx,
-            signer: Some(self.adapter.signers()),
+            signer: Some(self.adapter.signers().to_vec()),
+            valuation: None,
+            legs: None,
+            circular: false,
         })
     }
 }
 
+/// Registry entry for [JupiterParser].
+pub struct JupiterProtocol;
+
+impl ProtocolParser for JupiterProtocol {
+    fn name(&self) -> &str {
+        "Jupiter"
+    }
+
+    fn program_ids(&self) -> Vec<String> {
+        [
+            dex_programs::JUPITER.id,
+            dex_programs::JUPITER_DCA.id,
+            dex_programs::JUPITER_VA.id,
+            dex_programs::JUPITER_LIMIT_ORDER_V2.id,
+        ]
+        .iter()
+        .map(|id| id.to_string())
+        .collect()
+    }
+
+    fn parse(&self, ctx: &ParseContext) -> ParseOutput {
+        JupiterParser::new(
+            ctx.adapter,
+            ctx.dex_info.clone(),
+            ctx.transfer_actions,
+            ctx.instructions,
+        )
+        .process_trades()
+    }
+
+    fn is_route(&self) -> bool {
+        true
+    }
+}
+
 impl<'a> JupiterParser<'a> {
     fn block_time(&self) -> i64 {
         self.adapter.block_time()
@@ -155,7 +215,165 @@ struct JupiterSwapEventData {
     input_amount: u64,
     output_mint: String,
     output_amount: u64,
-    input_mint_decimals: u8,
-    output_mint_decimals: u8,
-    idx: String,
+    /// Decimals and whether they were defaulted, see [TransactionAdapter::resolve_token_decimals].
+    input_mint_decimals: (u8, bool),
+    output_mint_decimals: (u8, bool),
+    idx: InstructionIndex,
+}
+
+/// Decode a Jupiter instruction for pre-execution analysis.
+pub fn decode_instruction(
+    adapter: &TransactionAdapter,
+    ci: &ClassifiedInstruction,
+) -> JupiterInstruction {
+    let data = &ci.instruction.data;
+    match decode_swap_intent(adapter, ci) {
+        Some(intent) if data[0..8] == discriminators::JUPITER_ROUTE => {
+            JupiterInstruction::Route(intent)
+        }
+        Some(intent) if data[0..8] == discriminators::JUPITER_SHARED_ACCOUNTS_ROUTE => {
+            JupiterInstruction::SharedAccountsRoute(intent)
+        }
+        Some(intent) => JupiterInstruction::ExactOutRoute(intent),
+        None => JupiterInstruction::Other {
+            discriminator: get_instruction_discriminator(data),
+        },
+    }
+}
+
+/// Decode a Jupiter `route` / `shared_accounts_route` / `exact_out_route` instruction into its swap intent.
+/// The route plan is variable-length, but the trailing amount, quote, slippage and platform-fee
+/// arguments are fixed-size, so they are read from the end of the data.
+pub fn decode_swap_intent(
+    adapter: &TransactionAdapter,
+    ci: &ClassifiedInstruction,
+) -> Option<SwapIntent> {
+    let data = &ci.instruction.data;
+    let accounts = &ci.instruction.accounts;
+    // in_amount/out_amount (8) + quoted amount (8) + slippage_bps (2) + platform_fee_bps (1)
+    const TRAILING_ARGS_LEN: usize = 19;
+    if ci.program_id != dex_programs::JUPITER.key || data.len() < 8 + TRAILING_ARGS_LEN {
+        return None;
+    }
+    let disc = &data[0..8];
+    // (user, source token account, source mint, destination mint)
+    let (user, source_account, in_mint, out_mint, is_exact_out) =
+        if disc == discriminators::JUPITER_ROUTE && accounts.len() >= 6 {
+            (
+                &accounts[1],
+                &accounts[2],
+                None,
+                Some(accounts[5].to_string()),
+                false,
+            )
+        } else if disc == discriminators::JUPITER_SHARED_ACCOUNTS_ROUTE && accounts.len() >= 9 {
+            (
+                &accounts[2],
+                &accounts[3],
+                Some(accounts[7].to_string()),
+                Some(accounts[8].to_string()),
+                false,
+            )
+        } else if disc == discriminators::JUPITER_EXACT_OUT_ROUTE && accounts.len() >= 7 {
+            (
+                &accounts[1],
+                &accounts[2],
+                Some(accounts[5].to_string()),
+                Some(accounts[6].to_string()),
+                true,
+            )
+        } else {
+            return None;
+        };
+    let mut r = BinaryReader::new(&data[data.len() - TRAILING_ARGS_LEN..]);
+    let amount = r.read_u64_le().ok()?;
+    let quoted_amount = r.read_u64_le().ok()?;
+    let slippage_bps = r.read_u16_le().ok()?;
+    let mut intent = SwapIntent {
+        program_id: dex_programs::JUPITER.id.to_string(),
+        amm: dex_programs::JUPITER.name.to_string(),
+        user: user.to_string(),
+        pool: None,
+        in_mint: in_mint.or_else(|| adapter.get_token_account_mint(source_account)),
+        out_mint,
+        slippage_bps: Some(slippage_bps),
+        idx: ci.idx(),
+        ..Default::default()
+    };
+    let slippage = slippage_bps as u128;
+    if is_exact_out {
+        intent.amount_out = Some(amount);
+        intent.max_in = Some(
+            u64::try_from(quoted_amount as u128 * (10_000 + slippage) / 10_000).unwrap_or(u64::MAX),
+        );
+    } else {
+        intent.amount_in = Some(amount);
+        intent.min_out =
+            Some((quoted_amount as u128 * 10_000u128.saturating_sub(slippage) / 10_000) as u64);
+    }
+    Some(intent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TOKEN_PROGRAM_ID;
+    use crate::instruction_classifier::InstructionClassifier;
+    use crate::test_support::{ix, key, spl_transfer, token_balance, tx};
+    use crate::types::InnerInstructionSet;
+
+    /// A route event swapping 1000 of mint 9 for 400 of mint 8.
+    fn route_event() -> Vec<u8> {
+        let mut data = discriminators::JUPITER_ROUTE_EVENT.to_vec();
+        data.extend([7; 32]);
+        data.extend([9; 32]);
+        data.extend(1_000u64.to_le_bytes());
+        data.extend([8; 32]);
+        data.extend(400u64.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn the_user_owns_the_input_not_the_fee_payer() {
+        // Keys: 1 relayer paying fees, 2 user, 3 user's mint 9 account, 4 pool account.
+        let mut keys: Vec<String> = (1..=4).map(key).collect();
+        keys.push(dex_programs::JUPITER.id.to_string());
+        keys.push(TOKEN_PROGRAM_ID.to_string());
+        let mut input = tx(
+            keys,
+            vec![ix(4, &[1, 2, 3], vec![])],
+            vec![InnerInstructionSet {
+                index: 0,
+                instructions: vec![
+                    ix(5, &[2, 3, 1], spl_transfer(1_000)),
+                    ix(4, &[], route_event()),
+                ],
+            }],
+        );
+        input.signatures.push(vec![2; 64]);
+        input.meta.as_mut().unwrap().post_token_balances = Some(vec![
+            token_balance(2, &key(9), &key(2), 0, 0),
+            token_balance(3, &key(9), &key(7), 1_000, 0),
+        ]);
+
+        let adapter = TransactionAdapter::new(&input, None);
+        let classifier = InstructionClassifier::new(&adapter);
+        let utils = TransactionUtils::new(&adapter);
+        let registry = crate::parsers::ParserRegistry::with_builtins();
+        let transfer_actions = utils.get_transfer_actions(&classifier, &registry, &[]);
+        let instructions = classifier.get_instructions(&dex_programs::JUPITER.key);
+        let output = JupiterParser::new(
+            &adapter,
+            DexInfo::default(),
+            &transfer_actions,
+            instructions,
+        )
+        .process_trades();
+        assert!(output.errors.is_empty(), "{:?}", output.errors);
+        let trade = &output.trades[0];
+        assert_eq!(trade.user, key(2));
+        assert_eq!(trade.input_token.mint, key(9));
+        assert_eq!(trade.output_token.amount_raw, "400");
+        assert_eq!(trade.signer, Some(vec![key(1), key(2)]));
+    }
 }
@@ -1,24 +1,31 @@
 //! Meteora DEX parser (DLMM, DAMM, DAMM V2) — transfer-based swap detection.
 
-use crate::constants::{dex_programs, get_program_name, discriminators};
+use crate::binary_reader::BinaryReader;
+use crate::constants::{dex_programs, discriminators, get_program_name_by_key};
+use crate::instruction_index::InstructionIndex;
+use crate::parsers::{unmatched_swap, ParseContext, ProtocolParser};
 use crate::transaction_adapter::TransactionAdapter;
 use crate::transaction_utils::TransactionUtils;
-use crate::types::{ClassifiedInstruction, DexInfo, TradeInfo, TransferData};
+use crate::types::{
+    ClassifiedInstruction, DexInfo, MeteoraInstruction, ParseOutput, SwapIntent, TransferData,
+};
+use crate::utils::get_instruction_discriminator;
+use solana_sdk::pubkey::Pubkey;
 use std::collections::HashMap;
 
 pub struct MeteoraParser<'a> {
     adapter: &'a TransactionAdapter<'a>,
     dex_info: DexInfo,
-    transfer_actions: HashMap<String, Vec<TransferData>>,
-    classified_instructions: Vec<ClassifiedInstruction>,
+    transfer_actions: &'a HashMap<(Pubkey, InstructionIndex), Vec<TransferData>>,
+    classified_instructions: &'a [ClassifiedInstruction],
 }
 
 impl<'a> MeteoraParser<'a> {
     pub fn new(
         adapter: &'a TransactionAdapter<'a>,
         dex_info: DexInfo,
-        transfer_actions: HashMap<String, Vec<TransferData>>,
-        classified_instructions: Vec<ClassifiedInstruction>,
+        transfer_actions: &'a HashMap<(Pubkey, InstructionIndex), Vec<TransferData>>,
+        classified_instructions: &'a [ClassifiedInstruction],
     ) -> Self {
         Self {
             adapter,
@@ -28,44 +35,52 @@ impl<'a> MeteoraParser<'a> {
         }
     }
 
-    pub fn process_trades(&self) -> Vec<TradeInfo> {
-        let mut trades = Vec::new();
+    pub fn process_trades(&self) -> ParseOutput {
+        let mut output = ParseOutput::default();
         let utils = TransactionUtils::new(self.adapter);
         let meteora_ids = [
-            dex_programs::METEORA.id,
-            dex_programs::METEORA_DAMM.id,
-            dex_programs::METEORA_DAMM_V2.id,
+            dex_programs::METEORA.key,
+            dex_programs::METEORA_DAMM.key,
+            dex_programs::METEORA_DAMM_V2.key,
         ];
-        for ci in &self.classified_instructions {
-            if !meteora_ids.contains(&ci.program_id.as_str()) {
+        for ci in self.classified_instructions {
+            if !meteora_ids.contains(&ci.program_id) {
                 continue;
             }
             if !self.not_liquidity_event(&ci.instruction.data) {
                 continue;
             }
             let mut transfers = TransactionUtils::get_transfers_for_instruction(
-                &self.transfer_actions,
+                self.transfer_actions,
                 &ci.program_id,
                 ci.outer_index,
                 ci.inner_index,
             );
-            if transfers.len() >= 2 {
-                if ci.program_id == dex_programs::METEORA.id {
+            let found = transfers.len();
+            let trade = if found >= 2 {
+                if ci.program_id == dex_programs::METEORA.key {
                     transfers = transfers.into_iter().take(2).collect();
                 }
                 let dex_info = DexInfo {
-                    amm: Some(get_program_name(&ci.program_id).to_string()),
+                    amm: Some(get_program_name_by_key(&ci.program_id).to_string()),
                     ..self.dex_info.clone()
                 };
-                if let Some(mut trade) = utils.process_swap_data(&transfers, &dex_info, true) {
-                    if let Some(pool) = self.get_pool_address(&ci.instruction.accounts, &ci.program_id) {
-                        trade.pool = vec![pool];
-                    }
-                    trades.push(trade);
+                utils.process_swap_data(&transfers, &dex_info, true)
+            } else {
+                None
+            };
+            let Some(mut trade) = trade else {
+                if decode_swap_intent(self.adapter, ci).is_some() {
+                    output.errors.push(unmatched_swap(ci, found));
                 }
+                continue;
+            };
+            if let Some(pool) = self.get_pool_address(&ci.instruction.accounts, &ci.program_id) {
+                trade.pool = vec![pool];
             }
+            output.trades.push(trade);
         }
-        trades
+        output
     }
 
     fn not_liquidity_event(&self, data: &[u8]) -> bool {
@@ -87,16 +102,118 @@ impl<'a> MeteoraParser<'a> {
         true
     }
 
-    fn get_pool_address(&self, accounts: &[String], program_id: &str) -> Option<String> {
+    fn get_pool_address(&self, accounts: &[Pubkey], program_id: &Pubkey) -> Option<String> {
         if accounts.len() <= 5 {
             return None;
         }
-        match program_id {
-            id if id == dex_programs::METEORA.id || id == dex_programs::METEORA_DAMM.id => {
-                accounts.get(0).cloned()
+        let pool = match *program_id {
+            id if id == dex_programs::METEORA.key || id == dex_programs::METEORA_DAMM.key => {
+                accounts.first()
             }
-            id if id == dex_programs::METEORA_DAMM_V2.id => accounts.get(1).cloned(),
+            id if id == dex_programs::METEORA_DAMM_V2.key => accounts.get(1),
             _ => None,
+        };
+        pool.map(Pubkey::to_string)
+    }
+}
+
+/// Registry entry for [MeteoraParser].
+pub struct MeteoraProtocol;
+
+impl ProtocolParser for MeteoraProtocol {
+    fn name(&self) -> &str {
+        "Meteora"
+    }
+
+    fn program_ids(&self) -> Vec<String> {
+        [
+            dex_programs::METEORA.id,
+            dex_programs::METEORA_DAMM.id,
+            dex_programs::METEORA_DAMM_V2.id,
+        ]
+        .iter()
+        .map(|id| id.to_string())
+        .collect()
+    }
+
+    fn parse(&self, ctx: &ParseContext) -> ParseOutput {
+        MeteoraParser::new(
+            ctx.adapter,
+            ctx.dex_info.clone(),
+            ctx.transfer_actions,
+            ctx.instructions,
+        )
+        .process_trades()
+    }
+}
+
+/// Decode a Meteora instruction for pre-execution analysis.
+pub fn decode_instruction(
+    adapter: &TransactionAdapter,
+    ci: &ClassifiedInstruction,
+) -> MeteoraInstruction {
+    let data = &ci.instruction.data;
+    match decode_swap_intent(adapter, ci) {
+        Some(intent) if data[0..8] == discriminators::METEORA_DLMM_SWAP2 => {
+            MeteoraInstruction::Swap2(intent)
         }
+        Some(intent) if data[0..8] == discriminators::METEORA_DLMM_SWAP_EXACT_OUT => {
+            MeteoraInstruction::SwapExactOut(intent)
+        }
+        Some(intent) => MeteoraInstruction::Swap(intent),
+        None => MeteoraInstruction::Other {
+            discriminator: get_instruction_discriminator(data),
+        },
+    }
+}
+
+/// Decode a DLMM `swap` / `swap2` / `swap_exact_out` instruction into its swap intent.
+/// Direction comes from the user's input token account, so mints are set only when
+/// that account can be resolved from this transaction.
+pub fn decode_swap_intent(
+    adapter: &TransactionAdapter,
+    ci: &ClassifiedInstruction,
+) -> Option<SwapIntent> {
+    let data = &ci.instruction.data;
+    let accounts = &ci.instruction.accounts;
+    if ci.program_id != dex_programs::METEORA.key || data.len() < 24 || accounts.len() < 11 {
+        return None;
+    }
+    let disc = &data[0..8];
+    let is_exact_out = disc == discriminators::METEORA_DLMM_SWAP_EXACT_OUT;
+    if !is_exact_out
+        && disc != discriminators::METEORA_DLMM_SWAP
+        && disc != discriminators::METEORA_DLMM_SWAP2
+    {
+        return None;
+    }
+    let mut r = BinaryReader::new(&data[8..]);
+    let first = r.read_u64_le().ok()?;
+    let second = r.read_u64_le().ok()?;
+    let token_x = accounts[6].to_string();
+    let token_y = accounts[7].to_string();
+    let in_mint = adapter.get_token_account_mint(&accounts[4]);
+    let out_mint = match in_mint.as_deref() {
+        Some(m) if m == token_x => Some(token_y),
+        Some(m) if m == token_y => Some(token_x),
+        _ => adapter.get_token_account_mint(&accounts[5]),
+    };
+    let mut intent = SwapIntent {
+        program_id: dex_programs::METEORA.id.to_string(),
+        amm: dex_programs::METEORA.name.to_string(),
+        user: accounts[10].to_string(),
+        pool: Some(accounts[0].to_string()),
+        in_mint,
+        out_mint,
+        idx: ci.idx(),
+        ..Default::default()
+    };
+    if is_exact_out {
+        intent.max_in = Some(first);
+        intent.amount_out = Some(second);
+    } else {
+        intent.amount_in = Some(first);
+        intent.min_out = Some(second);
     }
+    Some(intent)
 }
@@ -1,6 +1,122 @@
+//! Protocol parsers and the registry [crate::DexParser] dispatches through.
+
 pub mod jupiter;
-pub mod raydium;
-pub mod orca;
 pub mod meteora;
+pub mod orca;
 pub mod pumpfun;
 pub mod pumpswap;
+pub mod raydium;
+pub mod unknown;
+
+use crate::constants::get_program_name;
+use crate::error::ParseError;
+use crate::instruction_index::InstructionIndex;
+use crate::transaction_adapter::TransactionAdapter;
+use crate::types::{ClassifiedInstruction, DexInfo, ParseConfig, ParseOutput, TransferData};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Everything a protocol parser gets for one program in one transaction.
+pub struct ParseContext<'a> {
+    pub adapter: &'a TransactionAdapter<'a>,
+    /// Program the instructions below belong to.
+    pub program_id: &'a Pubkey,
+    /// From [ProtocolParser::dex_info], with the transaction-level route filled in.
+    pub dex_info: &'a DexInfo,
+    /// Token transfers keyed by the program and index of the instruction that executed them.
+    pub transfer_actions: &'a HashMap<(Pubkey, InstructionIndex), Vec<TransferData>>,
+    /// This program's instructions, outer and inner.
+    pub instructions: &'a [ClassifiedInstruction],
+    pub config: &'a ParseConfig,
+}
+
+/// A DEX/protocol parser that can be plugged into [crate::DexParser] via
+/// [crate::dex_parser::DexParserBuilder::register].
+pub trait ProtocolParser: Send + Sync {
+    /// Display name, used as `amm` for program ids missing from [crate::constants].
+    fn name(&self) -> &str;
+
+    /// Program ids this parser handles, base58-encoded.
+    fn program_ids(&self) -> Vec<String>;
+
+    fn parse(&self, ctx: &ParseContext) -> ParseOutput;
+
+    /// Aggregators (e.g. Jupiter) emit the user's end-to-end swap. When one is the
+    /// transaction's primary program its trades are used alone and the per-AMM legs skipped.
+    fn is_route(&self) -> bool {
+        false
+    }
+
+    /// How trades of `program_id` are labelled.
+    fn dex_info(&self, program_id: &str) -> DexInfo {
+        let name = match get_program_name(program_id) {
+            "Unknown" => self.name(),
+            known => known,
+        };
+        if self.is_route() {
+            DexInfo {
+                program_id: Some(program_id.to_string()),
+                amm: None,
+                route: Some(name.to_string()),
+            }
+        } else {
+            DexInfo {
+                program_id: Some(program_id.to_string()),
+                amm: Some(name.to_string()),
+                route: None,
+            }
+        }
+    }
+}
+
+/// Program id -> parser lookup. A later registration for the same program id replaces the earlier one.
+#[derive(Clone, Default)]
+pub struct ParserRegistry {
+    parsers: HashMap<Pubkey, Arc<dyn ProtocolParser>>,
+}
+
+impl ParserRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry preloaded with the built-in parsers.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register(Arc::new(jupiter::JupiterProtocol));
+        registry.register(Arc::new(raydium::RaydiumProtocol));
+        registry.register(Arc::new(orca::OrcaProtocol));
+        registry.register(Arc::new(meteora::MeteoraProtocol));
+        registry.register(Arc::new(pumpfun::PumpfunProtocol));
+        registry.register(Arc::new(pumpswap::PumpswapProtocol));
+        registry
+    }
+
+    /// Ids of `parser` that aren't valid base58 keys are skipped.
+    pub fn register(&mut self, parser: Arc<dyn ProtocolParser>) {
+        for program_id in parser.program_ids() {
+            if let Ok(key) = program_id.parse() {
+                self.parsers.insert(key, parser.clone());
+            }
+        }
+    }
+
+    pub fn get(&self, program_id: &Pubkey) -> Option<&Arc<dyn ProtocolParser>> {
+        self.parsers.get(program_id)
+    }
+
+    pub fn contains(&self, program_id: &Pubkey) -> bool {
+        self.parsers.contains_key(program_id)
+    }
+}
+
+/// Error for a recognized swap instruction of a transfer-based parser that yielded no trade.
+pub(crate) fn unmatched_swap(ci: &ClassifiedInstruction, transfers: usize) -> ParseError {
+    let reason = if transfers < 2 {
+        format!("swap has {transfers} token transfer(s), expected at least 2")
+    } else {
+        "swap transfers do not resolve to an input and an output token".to_string()
+    };
+    ParseError::decode(ci.program_id, ci.idx(), reason)
+}
@@ -1,24 +1,31 @@
 //! Orca DEX parser — transfer-based swap detection.
 
-use crate::constants::{dex_programs, get_program_name, discriminators};
+use crate::binary_reader::BinaryReader;
+use crate::constants::{dex_programs, discriminators, get_program_name_by_key};
+use crate::instruction_index::InstructionIndex;
+use crate::parsers::{unmatched_swap, ParseContext, ProtocolParser};
 use crate::transaction_adapter::TransactionAdapter;
 use crate::transaction_utils::TransactionUtils;
-use crate::types::{ClassifiedInstruction, DexInfo, TradeInfo, TransferData};
+use crate::types::{
+    ClassifiedInstruction, DexInfo, OrcaInstruction, ParseOutput, SwapIntent, TransferData,
+};
+use crate::utils::get_instruction_discriminator;
+use solana_sdk::pubkey::Pubkey;
 use std::collections::HashMap;
 
 pub struct OrcaParser<'a> {
     adapter: &'a TransactionAdapter<'a>,
     dex_info: DexInfo,
-    transfer_actions: HashMap<String, Vec<TransferData>>,
-    classified_instructions: Vec<ClassifiedInstruction>,
+    transfer_actions: &'a HashMap<(Pubkey, InstructionIndex), Vec<TransferData>>,
+    classified_instructions: &'a [ClassifiedInstruction],
 }
 
 impl<'a> OrcaParser<'a> {
     pub fn new(
         adapter: &'a TransactionAdapter<'a>,
         dex_info: DexInfo,
-        transfer_actions: HashMap<String, Vec<TransferData>>,
-        classified_instructions: Vec<ClassifiedInstruction>,
+        transfer_actions: &'a HashMap<(Pubkey, InstructionIndex), Vec<TransferData>>,
+        classified_instructions: &'a [ClassifiedInstruction],
     ) -> Self {
         Self {
             adapter,
@@ -28,33 +35,40 @@ impl<'a> OrcaParser<'a> {
         }
     }
 
-    pub fn process_trades(&self) -> Vec<TradeInfo> {
-        let mut trades = Vec::new();
+    pub fn process_trades(&self) -> ParseOutput {
+        let mut output = ParseOutput::default();
         let utils = TransactionUtils::new(self.adapter);
-        for ci in &self.classified_instructions {
-            if ci.program_id != dex_programs::ORCA.id {
+        for ci in self.classified_instructions {
+            if ci.program_id != dex_programs::ORCA.key {
                 continue;
             }
             if !self.not_liquidity_event(&ci.instruction.data) {
                 continue;
             }
             let transfers = TransactionUtils::get_transfers_for_instruction(
-                &self.transfer_actions,
+                self.transfer_actions,
                 &ci.program_id,
                 ci.outer_index,
                 ci.inner_index,
             );
-            if transfers.len() >= 2 {
+            let trade = if transfers.len() >= 2 {
                 let dex_info = DexInfo {
-                    amm: Some(get_program_name(&ci.program_id).to_string()),
+                    amm: Some(get_program_name_by_key(&ci.program_id).to_string()),
                     ..self.dex_info.clone()
                 };
-                if let Some(trade) = utils.process_swap_data(&transfers, &dex_info, true) {
-                    trades.push(trade);
+                utils.process_swap_data(&transfers, &dex_info, true)
+            } else {
+                None
+            };
+            match trade {
+                Some(trade) => output.trades.push(trade),
+                None if decode_swap_intent(self.adapter, ci).is_some() => {
+                    output.errors.push(unmatched_swap(ci, transfers.len()))
                 }
+                None => {}
             }
         }
-        trades
+        output
     }
 
     fn not_liquidity_event(&self, data: &[u8]) -> bool {
@@ -73,3 +87,102 @@ impl<'a> OrcaParser<'a> {
         true
     }
 }
+
+/// Registry entry for [OrcaParser].
+pub struct OrcaProtocol;
+
+impl ProtocolParser for OrcaProtocol {
+    fn name(&self) -> &str {
+        "Orca"
+    }
+
+    fn program_ids(&self) -> Vec<String> {
+        vec![dex_programs::ORCA.id.to_string()]
+    }
+
+    fn parse(&self, ctx: &ParseContext) -> ParseOutput {
+        OrcaParser::new(
+            ctx.adapter,
+            ctx.dex_info.clone(),
+            ctx.transfer_actions,
+            ctx.instructions,
+        )
+        .process_trades()
+    }
+}
+
+/// Decode a Whirlpool instruction for pre-execution analysis.
+pub fn decode_instruction(
+    adapter: &TransactionAdapter,
+    ci: &ClassifiedInstruction,
+) -> OrcaInstruction {
+    let data = &ci.instruction.data;
+    match decode_swap_intent(adapter, ci) {
+        Some(intent) if data[0..8] == discriminators::ORCA_SWAP => OrcaInstruction::Swap(intent),
+        Some(intent) => OrcaInstruction::SwapV2(intent),
+        None => OrcaInstruction::Other {
+            discriminator: get_instruction_discriminator(data),
+        },
+    }
+}
+
+/// Decode a Whirlpool `swap` / `swap_v2` instruction into its swap intent.
+/// `amount_specified_is_input` decides whether `other_amount_threshold` is a minimum output or a maximum input.
+pub fn decode_swap_intent(
+    adapter: &TransactionAdapter,
+    ci: &ClassifiedInstruction,
+) -> Option<SwapIntent> {
+    let data = &ci.instruction.data;
+    let accounts = &ci.instruction.accounts;
+    if data.len() < 8 {
+        return None;
+    }
+    // (token_authority, whirlpool, mint_a, mint_b); v1 only names the owner token accounts
+    let (authority, pool, mint_a, mint_b) =
+        if data[0..8] == discriminators::ORCA_SWAP && accounts.len() >= 7 {
+            (
+                &accounts[1],
+                &accounts[2],
+                adapter.get_token_account_mint(&accounts[3]),
+                adapter.get_token_account_mint(&accounts[5]),
+            )
+        } else if data[0..8] == discriminators::ORCA_SWAP_V2 && accounts.len() >= 11 {
+            (
+                &accounts[3],
+                &accounts[4],
+                Some(accounts[5].to_string()),
+                Some(accounts[6].to_string()),
+            )
+        } else {
+            return None;
+        };
+    let mut r = BinaryReader::new(&data[8..]);
+    let amount = r.read_u64_le().ok()?;
+    let other_amount_threshold = r.read_u64_le().ok()?;
+    let _sqrt_price_limit = r.read_fixed_array(16).ok()?;
+    let amount_specified_is_input = r.read_u8().ok()? == 1;
+    let a_to_b = r.read_u8().ok()? == 1;
+    let (in_mint, out_mint) = if a_to_b {
+        (mint_a, mint_b)
+    } else {
+        (mint_b, mint_a)
+    };
+    let mut intent = SwapIntent {
+        program_id: dex_programs::ORCA.id.to_string(),
+        amm: dex_programs::ORCA.name.to_string(),
+        user: authority.to_string(),
+        pool: Some(pool.to_string()),
+        in_mint,
+        out_mint,
+        idx: ci.idx(),
+        ..Default::default()
+    };
+    if amount_specified_is_input {
+        intent.amount_in = Some(amount);
+        intent.min_out = Some(other_amount_threshold);
+    } else {
+        intent.amount_out = Some(amount);
+        intent.max_in = Some(other_amount_threshold);
+    }
+    Some(intent)
+}
@@ -1,25 +1,33 @@
 //! Pumpfun DEX parser - event-based trade parsing.
 
-use crate::binary_reader::BinaryReader;
-use crate::constants::{dex_programs, discriminators, tokens};
+use crate::binary_reader::{BinaryReader, BinaryReaderError};
+use crate::binary_struct;
+use crate::constants::{dex_programs, discriminators, pumpfun, tokens};
+use crate::error::ParseError;
+use crate::instruction_index::InstructionIndex;
+use crate::parsers::{ParseContext, ProtocolParser};
 use crate::transaction_adapter::TransactionAdapter;
-use crate::types::{ClassifiedInstruction, DexInfo, TradeInfo, TransferData};
-use crate::utils::convert_to_ui_amount;
+use crate::types::{
+    ClassifiedInstruction, DexInfo, MemeEvent, ParseOutput, PumpfunInstruction, SwapIntent,
+    TradeInfo, TransferData,
+};
+use crate::utils::{convert_to_ui_amount, get_instruction_discriminator};
+use solana_sdk::pubkey::Pubkey;
 use std::collections::HashMap;
 
 pub struct PumpfunParser<'a> {
     adapter: &'a TransactionAdapter<'a>,
     dex_info: DexInfo,
-    _transfer_actions: HashMap<String, Vec<TransferData>>,
-    classified_instructions: Vec<ClassifiedInstruction>,
+    _transfer_actions: &'a HashMap<(Pubkey, InstructionIndex), Vec<TransferData>>,
+    classified_instructions: &'a [ClassifiedInstruction],
 }
 
 impl<'a> PumpfunParser<'a> {
     pub fn new(
         adapter: &'a TransactionAdapter<'a>,
         dex_info: DexInfo,
-        transfer_actions: HashMap<String, Vec<TransferData>>,
-        classified_instructions: Vec<ClassifiedInstruction>,
+        transfer_actions: &'a HashMap<(Pubkey, InstructionIndex), Vec<TransferData>>,
+        classified_instructions: &'a [ClassifiedInstruction],
     ) -> Self {
         Self {
             adapter,
@@ -29,10 +37,10 @@ impl<'a> PumpfunParser<'a> {
         }
     }
 
-    pub fn process_trades(&self) -> Vec<TradeInfo> {
-        let mut trades = Vec::new();
-        for ci in &self.classified_instructions {
-            if ci.program_id != dex_programs::PUMP_FUN.id {
+    pub fn process_trades(&self) -> ParseOutput {
+        let mut output = ParseOutput::default();
+        for ci in self.classified_instructions {
+            if ci.program_id != dex_programs::PUMP_FUN.key {
                 continue;
             }
             let data = &ci.instruction.data;
@@ -43,126 +51,321 @@ impl<'a> PumpfunParser<'a> {
                 continue;
             }
             let event_data = &data[16..];
-            if let Some(evt) = decode_trade_event(event_data) {
-                let idx = format!("{}-{}", ci.outer_index, ci.inner_index.unwrap_or(0));
-                let (input_mint, input_amount, input_dec, output_mint, output_amount, output_dec) = if evt.is_buy {
+            let idx = ci.idx();
+            let evt = match decode_trade_event(event_data) {
+                Ok(evt) => evt,
+                Err(e) => {
+                    output
+                        .errors
+                        .push(ParseError::decode(ci.program_id, idx, e));
+                    continue;
+                }
+            };
+            let token_dec = self
+                .adapter
+                .resolve_token_decimals(&evt.mint.to_string(), pumpfun::TOKEN_DECIMALS);
+            let sol_dec = (9u8, false);
+            let (input_mint, input_amount, input_dec, output_mint, output_amount, output_dec) =
+                if evt.is_buy {
                     (
                         tokens::SOL.to_string(),
                         evt.sol_amount,
-                        9u8,
-                        evt.mint.clone(),
+                        sol_dec,
+                        evt.mint.to_string(),
                         evt.token_amount,
-                        6u8,
+                        token_dec,
                     )
                 } else {
                     (
-                        evt.mint.clone(),
+                        evt.mint.to_string(),
                         evt.token_amount,
-                        6u8,
+                        token_dec,
                         tokens::SOL.to_string(),
                         evt.sol_amount,
-                        9u8,
+                        sol_dec,
                     )
                 };
-                let trade_type = if evt.is_buy {
-                    crate::types::TradeType::Buy
-                } else {
-                    crate::types::TradeType::Sell
-                };
-                let mut trade = TradeInfo {
-                    user: evt.user.clone(),
-                    trade_type,
-                    pool: evt.bonding_curve.clone().map(|p| vec![p]).unwrap_or_default(),
-                    input_token: crate::types::TokenInfo {
-                        mint: input_mint.clone(),
-                        amount: convert_to_ui_amount(input_amount, input_dec),
-                        amount_raw: input_amount.to_string(),
-                        decimals: input_dec,
-                        authority: None,
-                        destination: None,
-                        destination_owner: None,
-                        source: None,
-                    },
-                    output_token: crate::types::TokenInfo {
-                        mint: output_mint.clone(),
-                        amount: convert_to_ui_amount(output_amount, output_dec),
-                        amount_raw: output_amount.to_string(),
-                        decimals: output_dec,
-                        authority: None,
-                        destination: None,
-                        destination_owner: None,
-                        source: None,
-                    },
-                    slippage_bps: None,
-                    fee: None,
-                    fees: None,
-                    program_id: Some(dex_programs::PUMP_FUN.id.to_string()),
-                    amm: Some(dex_programs::PUMP_FUN.name.to_string()),
-                    amms: None,
-                    route: self.dex_info.route.clone(),
-                    slot: self.adapter.slot(),
-                    timestamp: evt.timestamp,
-                    signature: self.adapter.signature(),
-                    idx: idx.clone(),
-                    signer: Some(self.adapter.signers()),
+            let trade_type = if evt.is_buy {
+                crate::types::TradeType::Buy
+            } else {
+                crate::types::TradeType::Sell
+            };
+            let bonding_curve = self.find_bonding_curve(ci, &evt.mint);
+            let mut trade = TradeInfo {
+                user: evt.user.to_string(),
+                trade_type,
+                pool: bonding_curve.clone().into_iter().collect(),
+                input_token: crate::types::TokenInfo {
+                    mint: input_mint.clone(),
+                    amount: convert_to_ui_amount(input_amount, input_dec.0),
+                    amount_raw: input_amount.to_string(),
+                    decimals: input_dec.0,
+                    authority: None,
+                    destination: None,
+                    destination_owner: None,
+                    source: None,
+                    decimals_defaulted: input_dec.1,
+                    symbol: None,
+                    name: None,
+                },
+                output_token: crate::types::TokenInfo {
+                    mint: output_mint.clone(),
+                    amount: convert_to_ui_amount(output_amount, output_dec.0),
+                    amount_raw: output_amount.to_string(),
+                    decimals: output_dec.0,
+                    authority: None,
+                    destination: None,
+                    destination_owner: None,
+                    source: None,
+                    decimals_defaulted: output_dec.1,
+                    symbol: None,
+                    name: None,
+                },
+                slippage_bps: None,
+                fee: None,
+                fees: None,
+                program_id: Some(dex_programs::PUMP_FUN.id.to_string()),
+                amm: Some(dex_programs::PUMP_FUN.name.to_string()),
+                amms: None,
+                route: self.dex_info.route.clone(),
+                slot: self.adapter.slot(),
+                timestamp: evt.timestamp,
+                signature: self.adapter.signature().to_string(),
+                idx,
+                signer: Some(self.adapter.signers().to_vec()),
+                valuation: None,
+                legs: None,
+                circular: false,
+            };
+            let sol_fee =
+                |amount: u64, type_: &str, recipient: Option<Pubkey>| crate::types::FeeInfo {
+                    mint: tokens::SOL.to_string(),
+                    amount: convert_to_ui_amount(amount, 9),
+                    amount_raw: amount.to_string(),
+                    decimals: 9,
+                    dex: Some(dex_programs::PUMP_FUN.name.to_string()),
+                    type_: Some(type_.to_string()),
+                    recipient: recipient.map(|r| r.to_string()),
                 };
-                if let Some(fee) = evt.fee {
-                    trade.fee = Some(crate::types::FeeInfo {
-                        mint: tokens::SOL.to_string(),
-                        amount: convert_to_ui_amount(fee, 9),
-                        amount_raw: fee.to_string(),
-                        decimals: 9,
-                        dex: None,
-                        type_: None,
-                        recipient: None,
-                    });
-                }
-                trades.push(trade);
+            if let Some(fee) = evt.fee {
+                trade.fee = Some(sol_fee(fee, "protocol", evt.fee_recipient));
+            }
+            if let Some(creator_fee) = evt.creator_fee {
+                trade.fees = Some(
+                    trade
+                        .fee
+                        .clone()
+                        .into_iter()
+                        .chain(std::iter::once(sol_fee(
+                            creator_fee,
+                            "creator",
+                            evt.creator,
+                        )))
+                        .collect(),
+                );
             }
+            output.meme_events.push(MemeEvent {
+                event_type: trade_type,
+                timestamp: evt.timestamp,
+                idx,
+                slot: self.adapter.slot(),
+                signature: self.adapter.signature().to_string(),
+                user: trade.user.clone(),
+                base_mint: evt.mint.to_string(),
+                quote_mint: tokens::SOL.to_string(),
+                input_token: Some(trade.input_token.clone()),
+                output_token: Some(trade.output_token.clone()),
+                name: None,
+                symbol: None,
+                uri: None,
+                decimals: Some(token_dec.0),
+                total_supply: None,
+                fee: evt.fee.map(|f| convert_to_ui_amount(f, 9)),
+                protocol_fee: None,
+                platform_fee: None,
+                creator: evt.creator.map(|c| c.to_string()),
+                bonding_curve,
+                pool: None,
+                protocol: Some(dex_programs::PUMP_FUN.name.to_string()),
+                virtual_sol_reserves: Some(evt.virtual_sol_reserves),
+                virtual_token_reserves: Some(evt.virtual_token_reserves),
+                real_sol_reserves: evt.real_sol_reserves,
+                real_token_reserves: Some(evt.remaining_token_reserves()),
+                price: Some(evt.price(token_dec.0)),
+                bonding_curve_progress: Some(evt.bonding_curve_progress()),
+                fee_recipient: evt.fee_recipient.map(|r| r.to_string()),
+                fee_basis_points: evt.fee_basis_points,
+                creator_fee_basis_points: evt.creator_fee_basis_points,
+                creator_fee: evt.creator_fee.map(|f| convert_to_ui_amount(f, 9)),
+            });
+            output.trades.push(trade);
         }
-        trades
+        output
+    }
+
+    /// Bonding curve (account 3) of the `buy`/`sell` that emitted the event: the closest
+    /// preceding Pumpfun swap instruction for the same mint within the outer instruction.
+    fn find_bonding_curve(&self, event: &ClassifiedInstruction, mint: &Pubkey) -> Option<String> {
+        self.classified_instructions
+            .iter()
+            .filter(|ci| {
+                ci.outer_index == event.outer_index
+                    && ci.inner_index < event.inner_index
+                    && ci.instruction.data.len() >= 8
+                    && (ci.instruction.data[0..8] == discriminators::PUMPFUN_BUY
+                        || ci.instruction.data[0..8] == discriminators::PUMPFUN_SELL)
+                    && ci.instruction.accounts.get(2) == Some(mint)
+            })
+            .max_by_key(|ci| ci.inner_index)
+            .and_then(|ci| ci.instruction.accounts.get(3))
+            .map(Pubkey::to_string)
     }
 }
 
-struct PumpfunTradeEvent {
-    mint: String,
-    sol_amount: u64,
-    token_amount: u64,
-    is_buy: bool,
-    user: String,
-    timestamp: i64,
-    bonding_curve: Option<String>,
-    fee: Option<u64>,
+/// Registry entry for [PumpfunParser].
+pub struct PumpfunProtocol;
+
+impl ProtocolParser for PumpfunProtocol {
+    fn name(&self) -> &str {
+        "Pumpfun"
+    }
+
+    fn program_ids(&self) -> Vec<String> {
+        vec![dex_programs::PUMP_FUN.id.to_string()]
+    }
+
+    fn parse(&self, ctx: &ParseContext) -> ParseOutput {
+        PumpfunParser::new(
+            ctx.adapter,
+            ctx.dex_info.clone(),
+            ctx.transfer_actions,
+            ctx.instructions,
+        )
+        .process_trades()
+    }
+}
+
+binary_struct! {
+    /// Pumpfun `TradeEvent`. Fields in `trailing` were added by program upgrades
+    /// (real reserves and fees, then creator fees, then volume tracking) and are
+    /// `None` for events emitted before them.
+    #[derive(Debug, Clone)]
+    pub struct PumpfunTradeEvent {
+        pub mint: Pubkey,
+        pub sol_amount: u64,
+        pub token_amount: u64,
+        pub is_buy: bool,
+        pub user: Pubkey,
+        pub timestamp: i64,
+        pub virtual_sol_reserves: u64,
+        pub virtual_token_reserves: u64,
+    }
+    trailing {
+        pub real_sol_reserves: u64,
+        pub real_token_reserves: u64,
+        pub fee_recipient: Pubkey,
+        pub fee_basis_points: u64,
+        pub fee: u64,
+        pub creator: Pubkey,
+        pub creator_fee_basis_points: u64,
+        pub creator_fee: u64,
+        pub track_volume: bool,
+        pub total_unclaimed_tokens: u64,
+        pub total_claimed_tokens: u64,
+        pub current_sol_volume: u64,
+        pub last_update_timestamp: i64,
+        pub ix_name: String,
+    }
 }
 
-fn decode_trade_event(data: &[u8]) -> Option<PumpfunTradeEvent> {
-    let mut reader = BinaryReader::new(data);
-    let mint_slice = reader.read_fixed_array(32).ok()?;
-    let mint = bs58::encode(mint_slice).into_string();
-    let sol_amount = reader.read_u64_le().ok()?;
-    let token_amount = reader.read_u64_le().ok()?;
-    let is_buy = reader.read_u8().ok()? == 1;
-    let user_slice = reader.read_fixed_array(32).ok()?;
-    let user = bs58::encode(user_slice).into_string();
-    let timestamp = reader.read_i64_le().ok()?;
-    let _ = reader.read_u64_le().ok()?;
-    let _ = reader.read_u64_le().ok()?;
-    let mut fee = None;
-    if reader.remaining() >= 58 {
-        let _ = reader.read_u64_le().ok()?;
-        let _ = reader.read_u64_le().ok()?;
-        let _ = reader.read_fixed_array(32).ok()?;
-        let _ = reader.read_u16_le().ok()?;
-        fee = Some(reader.read_u64_le().ok()?);
+impl PumpfunTradeEvent {
+    /// SOL per token implied by the virtual reserves after the trade.
+    pub fn price(&self, token_decimals: u8) -> f64 {
+        if self.virtual_token_reserves == 0 {
+            return 0.0;
+        }
+        convert_to_ui_amount(self.virtual_sol_reserves, 9)
+            / convert_to_ui_amount(self.virtual_token_reserves, token_decimals)
+    }
+
+    /// Tokens still sellable from the curve. Older events lack `real_token_reserves`,
+    /// so it is derived from the virtual reserves (their difference is fixed).
+    pub fn remaining_token_reserves(&self) -> u64 {
+        self.real_token_reserves.unwrap_or_else(|| {
+            self.virtual_token_reserves.saturating_sub(
+                pumpfun::INITIAL_VIRTUAL_TOKEN_RESERVES - pumpfun::INITIAL_REAL_TOKEN_RESERVES,
+            )
+        })
+    }
+
+    /// Bonding curve completion in `[0, 1]`; 1 means the curve can migrate.
+    pub fn bonding_curve_progress(&self) -> f64 {
+        let remaining = self
+            .remaining_token_reserves()
+            .min(pumpfun::INITIAL_REAL_TOKEN_RESERVES);
+        1.0 - remaining as f64 / pumpfun::INITIAL_REAL_TOKEN_RESERVES as f64
+    }
+}
+
+/// Decode a `TradeEvent` payload (the data after the 16-byte event discriminator).
+pub fn decode_trade_event(data: &[u8]) -> Result<PumpfunTradeEvent, BinaryReaderError> {
+    BinaryReader::new(data).read()
+}
+
+/// Decode a Pumpfun instruction for pre-execution analysis.
+pub fn decode_instruction(
+    adapter: &TransactionAdapter,
+    ci: &ClassifiedInstruction,
+) -> PumpfunInstruction {
+    let data = &ci.instruction.data;
+    match decode_swap_intent(adapter, ci) {
+        Some(intent) if data[0..8] == discriminators::PUMPFUN_BUY => {
+            PumpfunInstruction::Buy(intent)
+        }
+        Some(intent) => PumpfunInstruction::Sell(intent),
+        None => PumpfunInstruction::Other {
+            discriminator: get_instruction_discriminator(data),
+        },
+    }
+}
+
+/// Decode a Pumpfun `buy` / `sell` instruction into its swap intent.
+/// Buy: `amount` tokens out for at most `max_sol_cost`; sell: `amount` tokens in for at least `min_sol_output`.
+pub fn decode_swap_intent(
+    _adapter: &TransactionAdapter,
+    ci: &ClassifiedInstruction,
+) -> Option<SwapIntent> {
+    let data = &ci.instruction.data;
+    let accounts = &ci.instruction.accounts;
+    if data.len() < 8 || accounts.len() < 7 {
+        return None;
+    }
+    let is_buy = data[0..8] == discriminators::PUMPFUN_BUY;
+    if !is_buy && data[0..8] != discriminators::PUMPFUN_SELL {
+        return None;
+    }
+    let mut reader = BinaryReader::new(&data[8..]);
+    let amount = reader.read_u64_le().ok()?;
+    let sol_limit = reader.read_u64_le().ok()?;
+    let mint = accounts[2].to_string();
+    let mut intent = SwapIntent {
+        program_id: dex_programs::PUMP_FUN.id.to_string(),
+        amm: dex_programs::PUMP_FUN.name.to_string(),
+        user: accounts[6].to_string(),
+        pool: Some(accounts[3].to_string()),
+        idx: ci.idx(),
+        ..Default::default()
+    };
+    if is_buy {
+        intent.in_mint = Some(tokens::SOL.to_string());
+        intent.out_mint = Some(mint);
+        intent.amount_out = Some(amount);
+        intent.max_in = Some(sol_limit);
+    } else {
+        intent.in_mint = Some(mint);
+        intent.out_mint = Some(tokens::SOL.to_string());
+        intent.amount_in = Some(amount);
+        intent.min_out = Some(sol_limit);
     }
-    Some(PumpfunTradeEvent {
-        mint,
-        sol_amount,
-        token_amount,
-        is_buy,
-        user,
-        timestamp,
-        bonding_curve: None,
-        fee,
-    })
+    Some(intent)
 }
@@ -1,276 +1,425 @@
 //! Pumpswap DEX parser - event-based (buy/sell event discriminators).
 
 use crate::binary_reader::BinaryReader;
-use crate::constants::{dex_programs, discriminators};
+use crate::binary_struct;
+use crate::constants::{dex_programs, discriminators, pumpfun};
+use crate::error::ParseError;
+use crate::instruction_index::InstructionIndex;
+use crate::parsers::{ParseContext, ProtocolParser};
 use crate::transaction_adapter::TransactionAdapter;
-use crate::types::{ClassifiedInstruction, DexInfo, TradeInfo, TransferData};
-use crate::utils::{convert_to_ui_amount, get_trade_type};
-use std::collections::HashMap;
+use crate::types::{
+    ClassifiedInstruction, DexInfo, ParseOutput, PumpswapInstruction, SwapIntent, TradeInfo,
+};
+use crate::utils::{convert_to_ui_amount, get_instruction_discriminator};
+use solana_sdk::pubkey::Pubkey;
 
 pub struct PumpswapParser<'a> {
     adapter: &'a TransactionAdapter<'a>,
     dex_info: DexInfo,
-    _transfer_actions: HashMap<String, Vec<TransferData>>,
-    classified_instructions: Vec<ClassifiedInstruction>,
+    classified_instructions: &'a [ClassifiedInstruction],
 }
 
 impl<'a> PumpswapParser<'a> {
     pub fn new(
         adapter: &'a TransactionAdapter<'a>,
         dex_info: DexInfo,
-        transfer_actions: HashMap<String, Vec<TransferData>>,
-        classified_instructions: Vec<ClassifiedInstruction>,
+        classified_instructions: &'a [ClassifiedInstruction],
     ) -> Self {
         Self {
             adapter,
             dex_info,
-            _transfer_actions: transfer_actions,
             classified_instructions,
         }
     }
 
-    pub fn process_trades(&self) -> Vec<TradeInfo> {
-        let mut trades = Vec::new();
-        for ci in &self.classified_instructions {
-            if ci.program_id != dex_programs::PUMP_SWAP.id {
+    pub fn process_trades(&self) -> ParseOutput {
+        let mut output = ParseOutput::default();
+        for ci in self.classified_instructions {
+            if ci.program_id != dex_programs::PUMP_SWAP.key {
                 continue;
             }
             let data = &ci.instruction.data;
             if data.len() < 16 {
                 continue;
             }
-            let idx = format!("{}-{}", ci.outer_index, ci.inner_index.unwrap_or(0));
+            let idx = ci.idx();
             let event_data = &data[16..];
-            if data[0..16] == discriminators::PUMPSWAP_BUY_EVENT {
-                if let Some(evt) = decode_buy_event(event_data) {
-                    let input_mint = self.adapter.spl_token_map.get(&evt.user_quote_token_account).map(|t| t.mint.clone());
-                    let output_mint = self.adapter.spl_token_map.get(&evt.user_base_token_account).map(|t| t.mint.clone());
-                    let fee_mint = self.adapter.spl_token_map.get(&evt.protocol_fee_recipient_token_account).map(|t| t.mint.clone());
-                    if let (Some(in_m), Some(out_m), Some(fee_m)) = (input_mint, output_mint, fee_mint) {
-                        let in_dec = self.adapter.get_token_decimals(&in_m);
-                        let out_dec = self.adapter.get_token_decimals(&out_m);
-                        let fee_dec = self.adapter.get_token_decimals(&fee_m);
-                        let fee_amt = evt.protocol_fee + evt.coin_creator_fee;
-                        let trade = TradeInfo {
-                            user: evt.user.clone(),
-                            trade_type: get_trade_type(&in_m, &out_m),
-                            pool: vec![evt.pool.clone()],
-                            input_token: crate::types::TokenInfo {
-                                mint: in_m.clone(),
-                                amount: convert_to_ui_amount(evt.quote_amount_in_with_lp_fee, in_dec),
-                                amount_raw: evt.quote_amount_in_with_lp_fee.to_string(),
-                                decimals: in_dec,
-                                authority: None,
-                                destination: None,
-                                destination_owner: None,
-                                source: None,
-                            },
-                            output_token: crate::types::TokenInfo {
-                                mint: out_m.clone(),
-                                amount: convert_to_ui_amount(evt.base_amount_out, out_dec),
-                                amount_raw: evt.base_amount_out.to_string(),
-                                decimals: out_dec,
-                                authority: None,
-                                destination: None,
-                                destination_owner: None,
-                                source: None,
-                            },
-                            slippage_bps: None,
-                            fee: Some(crate::types::FeeInfo {
-                                mint: fee_m.clone(),
-                                amount: convert_to_ui_amount(fee_amt, fee_dec),
-                                amount_raw: fee_amt.to_string(),
-                                decimals: fee_dec,
-                                dex: Some(dex_programs::PUMP_SWAP.name.to_string()),
-                                type_: Some("protocol".to_string()),
-                                recipient: Some(evt.protocol_fee_recipient.clone()),
-                            }),
-                            fees: None,
-                            program_id: Some(dex_programs::PUMP_SWAP.id.to_string()),
-                            amm: Some(dex_programs::PUMP_SWAP.name.to_string()),
-                            amms: None,
-                            route: self.dex_info.route.clone(),
-                            slot: self.adapter.slot(),
-                            timestamp: self.adapter.block_time(),
-                            signature: self.adapter.signature(),
-                            idx: idx.clone(),
-                            signer: Some(self.adapter.signers()),
-                        };
-                        trades.push(trade);
-                    }
-                }
+            let swap = if data[0..16] == discriminators::PUMPSWAP_BUY_EVENT {
+                BinaryReader::new(event_data)
+                    .read::<PumpswapBuyEvent>()
+                    .map(SwapEvent::from)
             } else if data[0..16] == discriminators::PUMPSWAP_SELL_EVENT {
-                if let Some(evt) = decode_sell_event(event_data) {
-                    let input_mint = self.adapter.spl_token_map.get(&evt.user_base_token_account).map(|t| t.mint.clone());
-                    let output_mint = self.adapter.spl_token_map.get(&evt.user_quote_token_account).map(|t| t.mint.clone());
-                    let fee_mint = self.adapter.spl_token_map.get(&evt.protocol_fee_recipient_token_account).map(|t| t.mint.clone());
-                    if let (Some(in_m), Some(out_m), Some(fee_m)) = (input_mint, output_mint, fee_mint) {
-                        let in_dec = self.adapter.get_token_decimals(&in_m);
-                        let out_dec = self.adapter.get_token_decimals(&out_m);
-                        let fee_dec = self.adapter.get_token_decimals(&fee_m);
-                        let fee_amt = evt.protocol_fee + evt.coin_creator_fee;
-                        let trade = TradeInfo {
-                            user: evt.user.clone(),
-                            trade_type: get_trade_type(&in_m, &out_m),
-                            pool: vec![evt.pool.clone()],
-                            input_token: crate::types::TokenInfo {
-                                mint: in_m.clone(),
-                                amount: convert_to_ui_amount(evt.base_amount_in, in_dec),
-                                amount_raw: evt.base_amount_in.to_string(),
-                                decimals: in_dec,
-                                authority: None,
-                                destination: None,
-                                destination_owner: None,
-                                source: None,
-                            },
-                            output_token: crate::types::TokenInfo {
-                                mint: out_m.clone(),
-                                amount: convert_to_ui_amount(evt.user_quote_amount_out, out_dec),
-                                amount_raw: evt.user_quote_amount_out.to_string(),
-                                decimals: out_dec,
-                                authority: None,
-                                destination: None,
-                                destination_owner: None,
-                                source: None,
-                            },
-                            slippage_bps: None,
-                            fee: Some(crate::types::FeeInfo {
-                                mint: fee_m.clone(),
-                                amount: convert_to_ui_amount(fee_amt, fee_dec),
-                                amount_raw: evt.protocol_fee.to_string(),
-                                decimals: fee_dec,
-                                dex: Some(dex_programs::PUMP_SWAP.name.to_string()),
-                                type_: None,
-                                recipient: Some(evt.protocol_fee_recipient.clone()),
-                            }),
-                            fees: None,
-                            program_id: Some(dex_programs::PUMP_SWAP.id.to_string()),
-                            amm: Some(dex_programs::PUMP_SWAP.name.to_string()),
-                            amms: None,
-                            route: self.dex_info.route.clone(),
-                            slot: self.adapter.slot(),
-                            timestamp: self.adapter.block_time(),
-                            signature: self.adapter.signature(),
-                            idx: idx.clone(),
-                            signer: Some(self.adapter.signers()),
-                        };
-                        trades.push(trade);
-                    }
-                }
+                BinaryReader::new(event_data)
+                    .read::<PumpswapSellEvent>()
+                    .map(SwapEvent::from)
+            } else {
+                continue;
+            };
+            match swap
+                .map_err(|e| e.to_string())
+                .and_then(|swap| self.build_trade(&swap, idx))
+            {
+                Ok(trade) => output.trades.push(trade),
+                Err(reason) => output
+                    .errors
+                    .push(ParseError::decode(ci.program_id, idx, reason)),
             }
         }
-        trades
+        output
+    }
+
+    fn build_trade(&self, evt: &SwapEvent, idx: InstructionIndex) -> Result<TradeInfo, String> {
+        let mint = |account: &Pubkey| {
+            self.adapter
+                .spl_token_map
+                .get(account)
+                .map(|t| t.mint.clone())
+                .ok_or_else(|| format!("cannot resolve the mint of token account {account}"))
+        };
+        let in_m = mint(&evt.user_input_token_account)?;
+        let out_m = mint(&evt.user_output_token_account)?;
+        let fee_m = mint(&evt.protocol_fee_recipient_token_account)?;
+        let in_dec = self
+            .adapter
+            .resolve_token_decimals(&in_m, pumpfun::TOKEN_DECIMALS);
+        let out_dec = self
+            .adapter
+            .resolve_token_decimals(&out_m, pumpfun::TOKEN_DECIMALS);
+        let fee_dec = self
+            .adapter
+            .resolve_token_decimals(&fee_m, pumpfun::TOKEN_DECIMALS);
+        let token = |mint: &str, amount: u64, (decimals, decimals_defaulted): (u8, bool)| {
+            crate::types::TokenInfo {
+                mint: mint.to_string(),
+                amount: convert_to_ui_amount(amount, decimals),
+                amount_raw: amount.to_string(),
+                decimals,
+                authority: None,
+                destination: None,
+                destination_owner: None,
+                source: None,
+                decimals_defaulted,
+                symbol: None,
+                name: None,
+            }
+        };
+        let fee = |amount: u64, type_: &str, recipient: &Pubkey| crate::types::FeeInfo {
+            mint: fee_m.clone(),
+            amount: convert_to_ui_amount(amount, fee_dec.0),
+            amount_raw: amount.to_string(),
+            decimals: fee_dec.0,
+            dex: Some(dex_programs::PUMP_SWAP.name.to_string()),
+            type_: Some(type_.to_string()),
+            recipient: Some(recipient.to_string()),
+        };
+        let protocol_fee = fee(evt.protocol_fee, "protocol", &evt.protocol_fee_recipient);
+        let fees = evt
+            .coin_creator_fee
+            .map(|(creator, amount)| vec![protocol_fee.clone(), fee(amount, "creator", &creator)]);
+        Ok(TradeInfo {
+            user: evt.user.to_string(),
+            trade_type: self.adapter.quote_assets().trade_type(&in_m, &out_m),
+            pool: vec![evt.pool.to_string()],
+            input_token: token(&in_m, evt.amount_in, in_dec),
+            output_token: token(&out_m, evt.amount_out, out_dec),
+            slippage_bps: None,
+            fee: Some(protocol_fee),
+            fees,
+            program_id: Some(dex_programs::PUMP_SWAP.id.to_string()),
+            amm: Some(dex_programs::PUMP_SWAP.name.to_string()),
+            amms: None,
+            route: self.dex_info.route.clone(),
+            slot: self.adapter.slot(),
+            timestamp: self.adapter.block_time(),
+            signature: self.adapter.signature().to_string(),
+            idx,
+            signer: Some(self.adapter.signers().to_vec()),
+            valuation: None,
+            legs: None,
+            circular: false,
+        })
     }
 }
 
-struct PumpswapBuyEvent {
-    base_amount_out: u64,
-    quote_amount_in_with_lp_fee: u64,
+/// The fields of a buy or sell event a trade is built from, oriented from the user's side.
+struct SwapEvent {
+    pool: Pubkey,
+    user: Pubkey,
+    user_input_token_account: Pubkey,
+    user_output_token_account: Pubkey,
+    amount_in: u64,
+    amount_out: u64,
     protocol_fee: u64,
-    coin_creator_fee: u64,
-    pool: String,
-    user: String,
-    user_base_token_account: String,
-    user_quote_token_account: String,
-    protocol_fee_recipient: String,
-    protocol_fee_recipient_token_account: String,
+    protocol_fee_recipient: Pubkey,
+    protocol_fee_recipient_token_account: Pubkey,
+    /// Coin creator and its fee, on events that carry them.
+    coin_creator_fee: Option<(Pubkey, u64)>,
 }
 
-struct PumpswapSellEvent {
-    base_amount_in: u64,
-    user_quote_amount_out: u64,
-    protocol_fee: u64,
-    coin_creator_fee: u64,
-    pool: String,
-    user: String,
-    user_base_token_account: String,
-    user_quote_token_account: String,
-    protocol_fee_recipient: String,
-    protocol_fee_recipient_token_account: String,
+impl From<PumpswapBuyEvent> for SwapEvent {
+    fn from(evt: PumpswapBuyEvent) -> Self {
+        Self {
+            pool: evt.pool,
+            user: evt.user,
+            user_input_token_account: evt.user_quote_token_account,
+            user_output_token_account: evt.user_base_token_account,
+            amount_in: evt.quote_amount_in_with_lp_fee,
+            amount_out: evt.base_amount_out,
+            protocol_fee: evt.protocol_fee,
+            protocol_fee_recipient: evt.protocol_fee_recipient,
+            protocol_fee_recipient_token_account: evt.protocol_fee_recipient_token_account,
+            coin_creator_fee: evt.coin_creator.zip(evt.coin_creator_fee),
+        }
+    }
 }
 
-fn decode_buy_event(data: &[u8]) -> Option<PumpswapBuyEvent> {
-    let mut r = BinaryReader::new(data);
-    let _timestamp = r.read_i64_le().ok()?;
-    let base_amount_out = r.read_u64_le().ok()?;
-    let _max_quote = r.read_u64_le().ok()?;
-    let _ub = r.read_u64_le().ok()?;
-    let _uq = r.read_u64_le().ok()?;
-    let _pb = r.read_u64_le().ok()?;
-    let _pq = r.read_u64_le().ok()?;
-    let _quote_in = r.read_u64_le().ok()?;
-    let _lp_bps = r.read_u64_le().ok()?;
-    let _lp_fee = r.read_u64_le().ok()?;
-    let _proto_bps = r.read_u64_le().ok()?;
-    let protocol_fee = r.read_u64_le().ok()?;
-    let quote_amount_in_with_lp_fee = r.read_u64_le().ok()?;
-    let _user_quote_in = r.read_u64_le().ok()?;
-    let pool = r.read_pubkey().ok()?;
-    let user = r.read_pubkey().ok()?;
-    let user_base_token_account = r.read_pubkey().ok()?;
-    let user_quote_token_account = r.read_pubkey().ok()?;
-    let protocol_fee_recipient = r.read_pubkey().ok()?;
-    let protocol_fee_recipient_token_account = r.read_pubkey().ok()?;
-    let (coin_creator_fee, _) = if r.remaining() >= 48 {
-        let _creator = r.read_pubkey().ok()?;
-        let _bps = r.read_u64_le().ok()?;
-        (r.read_u64_le().ok()?, ())
-    } else {
-        (0u64, ())
-    };
-    Some(PumpswapBuyEvent {
-        base_amount_out,
-        quote_amount_in_with_lp_fee,
-        protocol_fee,
-        coin_creator_fee,
-        pool,
-        user,
-        user_base_token_account,
-        user_quote_token_account,
-        protocol_fee_recipient,
-        protocol_fee_recipient_token_account,
-    })
+impl From<PumpswapSellEvent> for SwapEvent {
+    fn from(evt: PumpswapSellEvent) -> Self {
+        Self {
+            pool: evt.pool,
+            user: evt.user,
+            user_input_token_account: evt.user_base_token_account,
+            user_output_token_account: evt.user_quote_token_account,
+            amount_in: evt.base_amount_in,
+            amount_out: evt.user_quote_amount_out,
+            protocol_fee: evt.protocol_fee,
+            protocol_fee_recipient: evt.protocol_fee_recipient,
+            protocol_fee_recipient_token_account: evt.protocol_fee_recipient_token_account,
+            coin_creator_fee: evt.coin_creator.zip(evt.coin_creator_fee),
+        }
+    }
 }
 
-fn decode_sell_event(data: &[u8]) -> Option<PumpswapSellEvent> {
-    let mut r = BinaryReader::new(data);
-    let _timestamp = r.read_i64_le().ok()?;
-    let base_amount_in = r.read_u64_le().ok()?;
-    let _min_quote = r.read_u64_le().ok()?;
-    let _ub = r.read_u64_le().ok()?;
-    let _uq = r.read_u64_le().ok()?;
-    let _pb = r.read_u64_le().ok()?;
-    let _pq = r.read_u64_le().ok()?;
-    let _quote_out = r.read_u64_le().ok()?;
-    let _lp_bps = r.read_u64_le().ok()?;
-    let _lp_fee = r.read_u64_le().ok()?;
-    let _proto_bps = r.read_u64_le().ok()?;
-    let protocol_fee = r.read_u64_le().ok()?;
-    let _quote_out_no_lp = r.read_u64_le().ok()?;
-    let user_quote_amount_out = r.read_u64_le().ok()?;
-    let pool = r.read_pubkey().ok()?;
-    let user = r.read_pubkey().ok()?;
-    let user_base_token_account = r.read_pubkey().ok()?;
-    let user_quote_token_account = r.read_pubkey().ok()?;
-    let protocol_fee_recipient = r.read_pubkey().ok()?;
-    let protocol_fee_recipient_token_account = r.read_pubkey().ok()?;
-    let coin_creator_fee = if r.remaining() >= 48 {
-        let _creator = r.read_pubkey().ok()?;
-        let _bps = r.read_u64_le().ok()?;
-        r.read_u64_le().ok().unwrap_or(0)
-    } else {
-        0
+/// Registry entry for [PumpswapParser].
+pub struct PumpswapProtocol;
+
+impl ProtocolParser for PumpswapProtocol {
+    fn name(&self) -> &str {
+        "Pumpswap"
+    }
+
+    fn program_ids(&self) -> Vec<String> {
+        vec![dex_programs::PUMP_SWAP.id.to_string()]
+    }
+
+    fn parse(&self, ctx: &ParseContext) -> ParseOutput {
+        PumpswapParser::new(ctx.adapter, ctx.dex_info.clone(), ctx.instructions).process_trades()
+    }
+}
+
+binary_struct! {
+    struct PumpswapBuyEvent {
+        _timestamp: i64,
+        base_amount_out: u64,
+        _max_quote_amount_in: u64,
+        _user_base_token_reserves: u64,
+        _user_quote_token_reserves: u64,
+        _pool_base_token_reserves: u64,
+        _pool_quote_token_reserves: u64,
+        _quote_amount_in: u64,
+        _lp_fee_basis_points: u64,
+        _lp_fee: u64,
+        _protocol_fee_basis_points: u64,
+        protocol_fee: u64,
+        quote_amount_in_with_lp_fee: u64,
+        _user_quote_amount_in: u64,
+        pool: Pubkey,
+        user: Pubkey,
+        user_base_token_account: Pubkey,
+        user_quote_token_account: Pubkey,
+        protocol_fee_recipient: Pubkey,
+        protocol_fee_recipient_token_account: Pubkey,
+    }
+    trailing {
+        coin_creator: Pubkey,
+        _coin_creator_fee_basis_points: u64,
+        coin_creator_fee: u64,
+    }
+}
+
+binary_struct! {
+    struct PumpswapSellEvent {
+        _timestamp: i64,
+        base_amount_in: u64,
+        _min_quote_amount_out: u64,
+        _user_base_token_reserves: u64,
+        _user_quote_token_reserves: u64,
+        _pool_base_token_reserves: u64,
+        _pool_quote_token_reserves: u64,
+        _quote_amount_out: u64,
+        _lp_fee_basis_points: u64,
+        _lp_fee: u64,
+        _protocol_fee_basis_points: u64,
+        protocol_fee: u64,
+        _quote_amount_out_without_lp_fee: u64,
+        user_quote_amount_out: u64,
+        pool: Pubkey,
+        user: Pubkey,
+        user_base_token_account: Pubkey,
+        user_quote_token_account: Pubkey,
+        protocol_fee_recipient: Pubkey,
+        protocol_fee_recipient_token_account: Pubkey,
+    }
+    trailing {
+        coin_creator: Pubkey,
+        _coin_creator_fee_basis_points: u64,
+        coin_creator_fee: u64,
+    }
+}
+
+/// Decode a Pumpswap instruction for pre-execution analysis.
+pub fn decode_instruction(
+    adapter: &TransactionAdapter,
+    ci: &ClassifiedInstruction,
+) -> PumpswapInstruction {
+    let data = &ci.instruction.data;
+    match decode_swap_intent(adapter, ci) {
+        Some(intent) if data[0..8] == discriminators::PUMPSWAP_BUY => {
+            PumpswapInstruction::Buy(intent)
+        }
+        Some(intent) => PumpswapInstruction::Sell(intent),
+        None => PumpswapInstruction::Other {
+            discriminator: get_instruction_discriminator(data),
+        },
+    }
+}
+
+/// Decode a Pumpswap `buy` / `sell` instruction into its swap intent.
+/// Buy: `base_amount_out` for at most `max_quote_amount_in`; sell: `base_amount_in` for at least `min_quote_amount_out`.
+pub fn decode_swap_intent(
+    _adapter: &TransactionAdapter,
+    ci: &ClassifiedInstruction,
+) -> Option<SwapIntent> {
+    let data = &ci.instruction.data;
+    let accounts = &ci.instruction.accounts;
+    if data.len() < 8 || accounts.len() < 7 {
+        return None;
+    }
+    let is_buy = data[0..8] == discriminators::PUMPSWAP_BUY;
+    if !is_buy && data[0..8] != discriminators::PUMPSWAP_SELL {
+        return None;
+    }
+    let mut r = BinaryReader::new(&data[8..]);
+    let base_amount = r.read_u64_le().ok()?;
+    let quote_limit = r.read_u64_le().ok()?;
+    let base_mint = accounts[3].to_string();
+    let quote_mint = accounts[4].to_string();
+    let mut intent = SwapIntent {
+        program_id: dex_programs::PUMP_SWAP.id.to_string(),
+        amm: dex_programs::PUMP_SWAP.name.to_string(),
+        user: accounts[1].to_string(),
+        pool: Some(accounts[0].to_string()),
+        idx: ci.idx(),
+        ..Default::default()
     };
-    Some(PumpswapSellEvent {
-        base_amount_in,
-        user_quote_amount_out,
-        protocol_fee,
-        coin_creator_fee,
-        pool,
-        user,
-        user_base_token_account,
-        user_quote_token_account,
-        protocol_fee_recipient,
-        protocol_fee_recipient_token_account,
-    })
+    if is_buy {
+        intent.in_mint = Some(quote_mint);
+        intent.out_mint = Some(base_mint);
+        intent.amount_out = Some(base_amount);
+        intent.max_in = Some(quote_limit);
+    } else {
+        intent.in_mint = Some(base_mint);
+        intent.out_mint = Some(quote_mint);
+        intent.amount_in = Some(base_amount);
+        intent.min_out = Some(quote_limit);
+    }
+    Some(intent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::tokens;
+    use crate::instruction_classifier::InstructionClassifier;
+    use crate::test_support::{ix, key, token_balance, tx};
+    use crate::types::{InnerInstructionSet, SolanaTransactionInput};
+
+    /// A sell event of 1000 base for 500 quote with a protocol fee of 3 and a creator fee of 2.
+    /// Keys: 2 pool, 1 user, 3 base account, 4 quote account, 5 fee recipient, 6 its token
+    /// account, 7 coin creator.
+    fn sell_event() -> Vec<u8> {
+        let mut data = discriminators::PUMPSWAP_SELL_EVENT.to_vec();
+        let amounts = [0, 1_000, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 0, 500];
+        data.extend(amounts.iter().flat_map(|v: &u64| v.to_le_bytes()));
+        for n in [2, 1, 3, 4, 5, 6] {
+            data.extend([n; 32]);
+        }
+        data.extend([7; 32]);
+        data.extend(20u64.to_le_bytes());
+        data.extend(2u64.to_le_bytes());
+        data
+    }
+
+    fn sell_tx(resolve_quote: bool) -> SolanaTransactionInput {
+        let mut keys: Vec<String> = (1..=7).map(key).collect();
+        keys.push(dex_programs::PUMP_SWAP.id.to_string());
+        let mut tx = tx(
+            keys,
+            vec![ix(7, &[], vec![])],
+            vec![InnerInstructionSet {
+                index: 0,
+                instructions: vec![ix(7, &[], sell_event())],
+            }],
+        );
+        let mut balances = vec![token_balance(2, &key(9), &key(1), 0, 6)];
+        if resolve_quote {
+            balances.push(token_balance(3, tokens::SOL, &key(1), 0, 9));
+            balances.push(token_balance(5, tokens::SOL, &key(5), 0, 9));
+        }
+        tx.meta.as_mut().unwrap().post_token_balances = Some(balances);
+        tx
+    }
+
+    fn parse(tx: &SolanaTransactionInput) -> ParseOutput {
+        let adapter = TransactionAdapter::new(tx, None);
+        let classifier = InstructionClassifier::new(&adapter);
+        let instructions = classifier.get_instructions(&dex_programs::PUMP_SWAP.key);
+        PumpswapParser::new(&adapter, DexInfo::default(), instructions).process_trades()
+    }
+
+    #[test]
+    fn sell_reports_protocol_and_creator_fees_separately() {
+        let output = parse(&sell_tx(true));
+        assert!(output.errors.is_empty(), "{:?}", output.errors);
+        let trade = &output.trades[0];
+        assert_eq!(trade.user, key(1));
+        assert_eq!(trade.input_token.mint, key(9));
+        assert_eq!(trade.input_token.amount_raw, "1000");
+        assert_eq!(trade.output_token.mint, tokens::SOL);
+        assert_eq!(trade.output_token.amount_raw, "500");
+        let fee = trade.fee.as_ref().unwrap();
+        assert_eq!(
+            (fee.amount_raw.as_str(), fee.amount, fee.type_.as_deref()),
+            ("3", 3e-9, Some("protocol"))
+        );
+        let fees: Vec<_> = trade
+            .fees
+            .iter()
+            .flatten()
+            .map(|f| {
+                (
+                    f.amount_raw.as_str(),
+                    f.type_.as_deref(),
+                    f.recipient.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            fees,
+            [
+                ("3", Some("protocol"), Some(key(5))),
+                ("2", Some("creator"), Some(key(7)))
+            ]
+        );
+    }
+
+    #[test]
+    fn unresolved_mint_is_reported() {
+        let output = parse(&sell_tx(false));
+        assert!(output.trades.is_empty());
+        let [ParseError::Decode { reason, .. }] = &output.errors[..] else {
+            panic!("expected one decode error, got {:?}", output.errors);
+        };
+        assert!(reason.contains(&key(4)), "{reason}");
+    }
 }
@@ -1,24 +1,31 @@
 //! Raydium DEX parser (V4, AMM, CPMM, CL) - transfer-based swap detection.
 
-use crate::constants::{dex_programs, discriminators, get_program_name};
+use crate::binary_reader::BinaryReader;
+use crate::constants::{dex_programs, discriminators, get_program_name_by_key};
+use crate::instruction_index::InstructionIndex;
+use crate::parsers::{unmatched_swap, ParseContext, ProtocolParser};
 use crate::transaction_adapter::TransactionAdapter;
 use crate::transaction_utils::TransactionUtils;
-use crate::types::{ClassifiedInstruction, DexInfo, TradeInfo, TransferData};
+use crate::types::{
+    ClassifiedInstruction, DexInfo, ParseOutput, RaydiumInstruction, SwapIntent, TransferData,
+};
+use crate::utils::get_instruction_discriminator;
+use solana_sdk::pubkey::Pubkey;
 use std::collections::HashMap;
 
 pub struct RaydiumParser<'a> {
     adapter: &'a TransactionAdapter<'a>,
     dex_info: DexInfo,
-    transfer_actions: HashMap<String, Vec<TransferData>>,
-    classified_instructions: Vec<ClassifiedInstruction>,
+    transfer_actions: &'a HashMap<(Pubkey, InstructionIndex), Vec<TransferData>>,
+    classified_instructions: &'a [ClassifiedInstruction],
 }
 
 impl<'a> RaydiumParser<'a> {
     pub fn new(
         adapter: &'a TransactionAdapter<'a>,
         dex_info: DexInfo,
-        transfer_actions: HashMap<String, Vec<TransferData>>,
-        classified_instructions: Vec<ClassifiedInstruction>,
+        transfer_actions: &'a HashMap<(Pubkey, InstructionIndex), Vec<TransferData>>,
+        classified_instructions: &'a [ClassifiedInstruction],
     ) -> Self {
         Self {
             adapter,
@@ -28,59 +35,64 @@ impl<'a> RaydiumParser<'a> {
         }
     }
 
-    pub fn process_trades(&self) -> Vec<TradeInfo> {
-        let mut trades = Vec::new();
+    pub fn process_trades(&self) -> ParseOutput {
+        let mut output = ParseOutput::default();
         let utils = TransactionUtils::new(self.adapter);
-        for ci in &self.classified_instructions {
+        for ci in self.classified_instructions {
             if !self.not_liquidity_event(&ci.instruction.data) {
                 continue;
             }
             let transfers = TransactionUtils::get_transfers_for_instruction(
-                &self.transfer_actions,
+                self.transfer_actions,
                 &ci.program_id,
                 ci.outer_index,
                 ci.inner_index,
             );
-            if transfers.len() >= 2 {
+            let trade = if transfers.len() >= 2 {
                 let dex_info = DexInfo {
-                    amm: Some(get_program_name(&ci.program_id).to_string()),
+                    amm: Some(get_program_name_by_key(&ci.program_id).to_string()),
                     ..self.dex_info.clone()
                 };
                 let take = 2.min(transfers.len());
-                if let Some(mut trade) = utils.process_swap_data(&transfers[..take], &dex_info, true) {
-                    if let Some(pool) = self.get_pool_address(&ci.instruction.accounts, &ci.program_id) {
-                        trade.pool = vec![pool];
-                    }
-                    if transfers.len() > 2 {
-                        let fee_transfer = &transfers[2];
-                        trade.fee = Some(crate::types::FeeInfo {
-                            mint: fee_transfer.info.mint.clone(),
-                            amount: fee_transfer.info.token_amount.ui_amount.unwrap_or(0.0),
-                            amount_raw: fee_transfer.info.token_amount.amount.clone(),
-                            decimals: fee_transfer.info.token_amount.decimals,
-                            dex: None,
-                            type_: None,
-                            recipient: None,
-                        });
-                    }
-                    trades.push(trade);
+                utils.process_swap_data(&transfers[..take], &dex_info, true)
+            } else {
+                None
+            };
+            let Some(mut trade) = trade else {
+                if decode_swap_intent(self.adapter, ci).is_some() {
+                    output.errors.push(unmatched_swap(ci, transfers.len()));
                 }
+                continue;
+            };
+            if let Some(pool) = self.get_pool_address(&ci.instruction.accounts, &ci.program_id) {
+                trade.pool = vec![pool];
+            }
+            if transfers.len() > 2 {
+                let fee_transfer = &transfers[2];
+                trade.fee = Some(crate::types::FeeInfo {
+                    mint: fee_transfer.info.mint.clone(),
+                    amount: fee_transfer.info.token_amount.ui_amount.unwrap_or(0.0),
+                    amount_raw: fee_transfer.info.token_amount.amount.clone(),
+                    decimals: fee_transfer.info.token_amount.decimals,
+                    dex: None,
+                    type_: None,
+                    recipient: None,
+                });
             }
+            output.trades.push(trade);
         }
-        trades
+        output
     }
 
     fn not_liquidity_event(&self, data: &[u8]) -> bool {
         if data.is_empty() {
             return true;
         }
-        if data.len() >= 1 {
-            if data[0] == discriminators::RAYDIUM_CREATE[0]
-                || data[0] == discriminators::RAYDIUM_ADD_LIQUIDITY[0]
-                || data[0] == discriminators::RAYDIUM_REMOVE_LIQUIDITY[0]
-            {
-                return false;
-            }
+        if data[0] == discriminators::RAYDIUM_CREATE[0]
+            || data[0] == discriminators::RAYDIUM_ADD_LIQUIDITY[0]
+            || data[0] == discriminators::RAYDIUM_REMOVE_LIQUIDITY[0]
+        {
+            return false;
         }
         if data.len() >= 8 {
             let slice = &data[0..8];
@@ -94,17 +106,138 @@ impl<'a> RaydiumParser<'a> {
         true
     }
 
-    fn get_pool_address(&self, accounts: &[String], program_id: &str) -> Option<String> {
+    fn get_pool_address(&self, accounts: &[Pubkey], program_id: &Pubkey) -> Option<String> {
         if accounts.len() <= 5 {
             return None;
         }
-        match program_id {
-            id if id == dex_programs::RAYDIUM_V4.id || id == dex_programs::RAYDIUM_AMM.id => {
-                accounts.get(1).cloned()
+        let pool = match *program_id {
+            id if id == dex_programs::RAYDIUM_V4.key || id == dex_programs::RAYDIUM_AMM.key => {
+                accounts.get(1)
             }
-            id if id == dex_programs::RAYDIUM_CL.id => accounts.get(2).cloned(),
-            id if id == dex_programs::RAYDIUM_CPMM.id => accounts.get(3).cloned(),
+            id if id == dex_programs::RAYDIUM_CL.key => accounts.get(2),
+            id if id == dex_programs::RAYDIUM_CPMM.key => accounts.get(3),
             _ => None,
+        };
+        pool.map(Pubkey::to_string)
+    }
+}
+
+/// Registry entry for [RaydiumParser].
+pub struct RaydiumProtocol;
+
+impl ProtocolParser for RaydiumProtocol {
+    fn name(&self) -> &str {
+        "Raydium"
+    }
+
+    fn program_ids(&self) -> Vec<String> {
+        [
+            dex_programs::RAYDIUM_V4.id,
+            dex_programs::RAYDIUM_AMM.id,
+            dex_programs::RAYDIUM_CPMM.id,
+            dex_programs::RAYDIUM_CL.id,
+            dex_programs::RAYDIUM_ROUTE.id,
+        ]
+        .iter()
+        .map(|id| id.to_string())
+        .collect()
+    }
+
+    fn parse(&self, ctx: &ParseContext) -> ParseOutput {
+        RaydiumParser::new(
+            ctx.adapter,
+            ctx.dex_info.clone(),
+            ctx.transfer_actions,
+            ctx.instructions,
+        )
+        .process_trades()
+    }
+}
+
+/// Decode a Raydium instruction for pre-execution analysis.
+pub fn decode_instruction(
+    adapter: &TransactionAdapter,
+    ci: &ClassifiedInstruction,
+) -> RaydiumInstruction {
+    let data = &ci.instruction.data;
+    match decode_swap_intent(adapter, ci) {
+        Some(intent)
+            if data[0] == discriminators::RAYDIUM_SWAP_BASE_IN[0]
+                || data[0..8] == discriminators::RAYDIUM_CPMM_SWAP_BASE_INPUT =>
+        {
+            RaydiumInstruction::SwapBaseIn(intent)
+        }
+        Some(intent) => RaydiumInstruction::SwapBaseOut(intent),
+        None => RaydiumInstruction::Other {
+            discriminator: get_instruction_discriminator(data),
+        },
+    }
+}
+
+/// Decode a Raydium V4/AMM `swap_base_in` / `swap_base_out` or CPMM `swap_base_input` /
+/// `swap_base_output` instruction into its swap intent.
+/// V4 names only token accounts, so mints are resolved from this transaction when possible.
+pub fn decode_swap_intent(
+    adapter: &TransactionAdapter,
+    ci: &ClassifiedInstruction,
+) -> Option<SwapIntent> {
+    let data = &ci.instruction.data;
+    let accounts = &ci.instruction.accounts;
+    let program_id = ci.program_id;
+    let mut intent = SwapIntent {
+        program_id: program_id.to_string(),
+        amm: get_program_name_by_key(&program_id).to_string(),
+        idx: ci.idx(),
+        ..Default::default()
+    };
+    if program_id == dex_programs::RAYDIUM_V4.key || program_id == dex_programs::RAYDIUM_AMM.key {
+        if data.len() < 17 || accounts.len() < 17 {
+            return None;
+        }
+        let is_base_in = data[0] == discriminators::RAYDIUM_SWAP_BASE_IN[0];
+        if !is_base_in && data[0] != discriminators::RAYDIUM_SWAP_BASE_OUT[0] {
+            return None;
+        }
+        let mut r = BinaryReader::new(&data[1..]);
+        let first = r.read_u64_le().ok()?;
+        let second = r.read_u64_le().ok()?;
+        let n = accounts.len();
+        intent.user = accounts[n - 1].to_string();
+        intent.pool = Some(accounts[1].to_string());
+        intent.in_mint = adapter.get_token_account_mint(&accounts[n - 3]);
+        intent.out_mint = adapter.get_token_account_mint(&accounts[n - 2]);
+        if is_base_in {
+            intent.amount_in = Some(first);
+            intent.min_out = Some(second);
+        } else {
+            intent.max_in = Some(first);
+            intent.amount_out = Some(second);
+        }
+        return Some(intent);
+    }
+    if program_id == dex_programs::RAYDIUM_CPMM.key {
+        if data.len() < 24 || accounts.len() < 12 {
+            return None;
+        }
+        let is_base_input = data[0..8] == discriminators::RAYDIUM_CPMM_SWAP_BASE_INPUT;
+        if !is_base_input && data[0..8] != discriminators::RAYDIUM_CPMM_SWAP_BASE_OUTPUT {
+            return None;
+        }
+        let mut r = BinaryReader::new(&data[8..]);
+        let first = r.read_u64_le().ok()?;
+        let second = r.read_u64_le().ok()?;
+        intent.user = accounts[0].to_string();
+        intent.pool = Some(accounts[3].to_string());
+        intent.in_mint = Some(accounts[10].to_string());
+        intent.out_mint = Some(accounts[11].to_string());
+        if is_base_input {
+            intent.amount_in = Some(first);
+            intent.min_out = Some(second);
+        } else {
+            intent.max_in = Some(first);
+            intent.amount_out = Some(second);
         }
+        return Some(intent);
     }
+    None
 }
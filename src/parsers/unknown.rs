@@ -0,0 +1,116 @@
+//! Fallback parser for unrecognized programs (`ParseConfig::try_unknown_dex`) — transfer-based swap inference.
+
+use crate::constants::get_program_name_by_key;
+use crate::instruction_index::InstructionIndex;
+use crate::transaction_adapter::TransactionAdapter;
+use crate::transaction_utils::TransactionUtils;
+use crate::types::{ClassifiedInstruction, DexInfo, ParseOutput, TransferData};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+pub struct UnknownParser<'a> {
+    adapter: &'a TransactionAdapter<'a>,
+    dex_info: DexInfo,
+    transfer_actions: &'a HashMap<(Pubkey, InstructionIndex), Vec<TransferData>>,
+    classified_instructions: &'a [ClassifiedInstruction],
+}
+
+impl<'a> UnknownParser<'a> {
+    pub fn new(
+        adapter: &'a TransactionAdapter<'a>,
+        dex_info: DexInfo,
+        transfer_actions: &'a HashMap<(Pubkey, InstructionIndex), Vec<TransferData>>,
+        classified_instructions: &'a [ClassifiedInstruction],
+    ) -> Self {
+        Self {
+            adapter,
+            dex_info,
+            transfer_actions,
+            classified_instructions,
+        }
+    }
+
+    /// A top-level instruction is treated as a swap when, across the transfers it executes,
+    /// the signer sends one mint and receives a different one.
+    pub fn process_trades(&self) -> ParseOutput {
+        let mut trades = Vec::new();
+        let utils = TransactionUtils::new(self.adapter);
+        let signers = self.adapter.signers();
+        for ci in self.classified_instructions {
+            // Inner calls of an unknown program can't be told apart from their caller's transfers.
+            if ci.inner_index.is_some() {
+                continue;
+            }
+            let transfers = TransactionUtils::get_transfers_for_outer_instruction(
+                self.transfer_actions,
+                ci.outer_index,
+            );
+            let transfers: Vec<TransferData> = transfers
+                .into_iter()
+                .filter(|t| t.is_fee != Some(true))
+                .collect();
+            // With several signers (e.g. a bot paying fees for a user), the user is the one
+            // whose token accounts fund the swap.
+            let Some(signer) = signers
+                .iter()
+                .find(|s| transfers.iter().any(|t| self.is_sent_by(t, s)))
+            else {
+                continue;
+            };
+            let sent: Vec<&TransferData> = transfers
+                .iter()
+                .filter(|t| self.is_sent_by(t, signer))
+                .collect();
+            let input_mint = match sent.first() {
+                Some(t) => t.info.mint.clone(),
+                None => continue,
+            };
+            let output_mint = match transfers.iter().find(|t| {
+                t.info.mint != input_mint
+                    && t.info.destination_owner.as_deref() == Some(signer.as_str())
+            }) {
+                Some(t) => t.info.mint.clone(),
+                None => continue,
+            };
+            let swap_transfers: Vec<TransferData> = sent
+                .iter()
+                .filter(|t| t.info.mint == input_mint)
+                .copied()
+                .chain(transfers.iter().filter(|t| {
+                    t.info.mint == output_mint
+                        && t.info.destination_owner.as_deref() == Some(signer.as_str())
+                }))
+                .cloned()
+                .collect();
+            let dex_info = DexInfo {
+                program_id: Some(ci.program_id.to_string()),
+                amm: self
+                    .dex_info
+                    .amm
+                    .clone()
+                    .or_else(|| Some(get_program_name_by_key(&ci.program_id).to_string())),
+                route: self.dex_info.route.clone(),
+            };
+            if let Some(mut trade) = utils.process_swap_data(&swap_transfers, &dex_info, false) {
+                trade.idx = ci.idx();
+                trades.push(trade);
+            }
+        }
+        ParseOutput {
+            trades,
+            ..Default::default()
+        }
+    }
+
+    fn is_sent_by(&self, transfer: &TransferData, signer: &str) -> bool {
+        transfer.info.authority.as_deref() == Some(signer)
+            || transfer
+                .info
+                .source
+                .parse()
+                .ok()
+                .and_then(|source| self.adapter.get_token_account_owner(&source))
+                .as_deref()
+                == Some(signer)
+    }
+}
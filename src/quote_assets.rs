@@ -0,0 +1,166 @@
+//! Ranked quote assets: which side of a pair is the price denominator.
+
+use crate::constants::tokens;
+use crate::types::TradeType;
+use std::sync::LazyLock;
+
+/// Mints that act as quote currency, strongest first. In a pair of two quote assets
+/// the higher-ranked one is the quote; a mint not in the list is always the base.
+///
+/// Drives Buy/Sell classification (spending quote for base is a buy), base/quote
+/// orientation of valuations not involving a USD stable, and which mint a transfer
+/// between accounts of different mints is attributed to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuoteAssets {
+    mints: Vec<String>,
+}
+
+impl QuoteAssets {
+    pub fn new<I, S>(mints: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            mints: mints.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// The [Default] ranking, built once and shared.
+    pub fn shared() -> &'static QuoteAssets {
+        static DEFAULT: LazyLock<QuoteAssets> = LazyLock::new(QuoteAssets::default);
+        &DEFAULT
+    }
+
+    /// Append `mint` with the lowest rank, if not already present.
+    pub fn with(mut self, mint: impl Into<String>) -> Self {
+        let mint = mint.into();
+        if !self.mints.contains(&mint) {
+            self.mints.push(mint);
+        }
+        self
+    }
+
+    pub fn mints(&self) -> &[String] {
+        &self.mints
+    }
+
+    /// Position in the ranking, 0 being the strongest quote.
+    pub fn rank(&self, mint: &str) -> Option<usize> {
+        self.mints.iter().position(|m| m == mint)
+    }
+
+    pub fn is_quote(&self, mint: &str) -> bool {
+        self.rank(mint).is_some()
+    }
+
+    /// Whether `a` is a stronger quote than `b`.
+    pub fn outranks(&self, a: &str, b: &str) -> bool {
+        match (self.rank(a), self.rank(b)) {
+            (Some(a), Some(b)) => a < b,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+
+    /// `(base, quote)` of a pair; `b` is the quote unless `a` outranks it.
+    pub fn orient<'m>(&self, a: &'m str, b: &'m str) -> (&'m str, &'m str) {
+        if self.outranks(a, b) {
+            (b, a)
+        } else {
+            (a, b)
+        }
+    }
+
+    /// Buy when the input is the quote side, Sell otherwise.
+    pub fn trade_type(&self, in_mint: &str, out_mint: &str) -> TradeType {
+        if self.outranks(in_mint, out_mint) {
+            TradeType::Buy
+        } else {
+            TradeType::Sell
+        }
+    }
+
+    /// Mint of a transfer whose source and destination accounts may disagree:
+    /// the base side if one of them is a quote asset, else `token1`.
+    pub fn transfer_token_mint(
+        &self,
+        token1: Option<&str>,
+        token2: Option<&str>,
+    ) -> Option<String> {
+        match (token1, token2) {
+            (Some(a), Some(b)) if self.outranks(a, b) => Some(b.to_string()),
+            (a, b) => a.or(b).map(String::from),
+        }
+    }
+}
+
+impl Default for QuoteAssets {
+    /// SOL, then the well-known USD/EUR stables.
+    fn default() -> Self {
+        Self::new([
+            tokens::SOL,
+            tokens::USDC,
+            tokens::USDT,
+            tokens::USD1,
+            tokens::USDG,
+            tokens::PYUSD,
+            tokens::EURC,
+            tokens::USDY,
+            tokens::FDUSD,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spending_the_higher_ranked_quote_is_a_buy() {
+        let quotes = QuoteAssets::default();
+        assert_eq!(quotes.trade_type(tokens::SOL, "A"), TradeType::Buy);
+        assert_eq!(quotes.trade_type("A", tokens::SOL), TradeType::Sell);
+        assert_eq!(quotes.trade_type(tokens::SOL, tokens::USDC), TradeType::Buy);
+        assert_eq!(
+            quotes.trade_type(tokens::USDC, tokens::SOL),
+            TradeType::Sell
+        );
+        assert_eq!(quotes.trade_type("A", "B"), TradeType::Sell);
+    }
+
+    #[test]
+    fn added_mints_rank_last() {
+        let quotes = QuoteAssets::new([tokens::USDC])
+            .with("LST")
+            .with(tokens::USDC);
+        assert_eq!(quotes.mints(), [tokens::USDC, "LST"]);
+        assert_eq!(quotes.orient("LST", tokens::USDC), ("LST", tokens::USDC));
+        assert_eq!(quotes.orient(tokens::USDC, "LST"), ("LST", tokens::USDC));
+        assert_eq!(quotes.orient("A", "LST"), ("A", "LST"));
+        assert!(!quotes.is_quote(tokens::SOL));
+        assert_eq!(quotes.trade_type("LST", "A"), TradeType::Buy);
+    }
+
+    #[test]
+    fn attributes_transfers_to_the_base_side() {
+        let quotes = QuoteAssets::shared();
+        assert_eq!(
+            quotes
+                .transfer_token_mint(Some(tokens::SOL), Some("A"))
+                .as_deref(),
+            Some("A")
+        );
+        assert_eq!(
+            quotes
+                .transfer_token_mint(Some("A"), Some(tokens::SOL))
+                .as_deref(),
+            Some("A")
+        );
+        assert_eq!(
+            quotes.transfer_token_mint(None, Some("B")).as_deref(),
+            Some("B")
+        );
+        assert_eq!(quotes.transfer_token_mint(None, None), None);
+    }
+}
@@ -0,0 +1,283 @@
+//! Sandwich detection across the ordered transactions of one block.
+
+use crate::instruction_index::InstructionIndex;
+use crate::types::{ParseResult, TradeInfo, TransactionStatus};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// A trade taking part in a sandwich.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SandwichTrade {
+    /// Position of the transaction in the analyzed slice.
+    pub tx_position: usize,
+    pub signature: String,
+    /// Owner of the swapped tokens ([TradeInfo::user]).
+    pub user: String,
+    pub idx: InstructionIndex,
+    pub input_mint: String,
+    pub output_mint: String,
+    pub input_amount: f64,
+    pub output_amount: f64,
+}
+
+/// A victim trade between a front-run and a back-run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SandwichVictim {
+    pub trade: SandwichTrade,
+    /// Output the victim missed, in its output mint: what its input would have bought at
+    /// the front-run's rate minus what it got.
+    pub loss: f64,
+}
+
+/// Front-run and back-run by one user around other users' trades in the same pool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sandwich {
+    pub slot: u64,
+    pub pool: String,
+    pub attacker: String,
+    pub front_run: SandwichTrade,
+    pub back_run: SandwichTrade,
+    pub victims: Vec<SandwichVictim>,
+    /// Mint the attacker put in and got back.
+    pub profit_mint: String,
+    /// Back-run output minus the front-run input spent on the amount sold back.
+    pub attacker_profit: f64,
+}
+
+/// Find sandwiches in `results`, parsed transactions in block order. Trades are grouped
+/// by slot and first pool, so a slice spanning several slots never pairs trades across
+/// them, and attributed to their `user`, the owner of the swapped tokens, not the fee
+/// payer; failed transactions are ignored.
+pub fn detect_sandwiches(results: &[ParseResult]) -> Vec<Sandwich> {
+    let mut by_pool: HashMap<(u64, &str), Vec<SandwichTrade>> = HashMap::new();
+    let mut pools: Vec<(u64, &str)> = Vec::new();
+    for (position, result) in results.iter().enumerate() {
+        if result.tx_status == TransactionStatus::Failed {
+            continue;
+        }
+        let trades: Vec<&TradeInfo> = if result.trades.is_empty() {
+            result.aggregate_trade.iter().collect()
+        } else {
+            result.trades.iter().collect()
+        };
+        for trade in trades {
+            let Some(pool) = trade.pool.first() else {
+                continue;
+            };
+            let key = (result.slot, pool.as_str());
+            if !by_pool.contains_key(&key) {
+                pools.push(key);
+            }
+            by_pool.entry(key).or_default().push(SandwichTrade {
+                tx_position: position,
+                signature: result.signature.clone(),
+                user: trade.user.clone(),
+                idx: trade.idx,
+                input_mint: trade.input_token.mint.clone(),
+                output_mint: trade.output_token.mint.clone(),
+                input_amount: trade.input_token.amount,
+                output_amount: trade.output_token.amount,
+            });
+        }
+    }
+
+    let mut sandwiches = Vec::new();
+    for (slot, pool) in pools {
+        let trades = &by_pool[&(slot, pool)];
+        let mut used = HashSet::new();
+        for (f, front) in trades.iter().enumerate() {
+            if used.contains(&f) {
+                continue;
+            }
+            // The first back-run candidate with victims before it.
+            let found = trades
+                .iter()
+                .enumerate()
+                .skip(f + 1)
+                .filter(|(b, t)| {
+                    !used.contains(b)
+                        && t.tx_position > front.tx_position
+                        && t.user == front.user
+                        && t.input_mint == front.output_mint
+                        && t.output_mint == front.input_mint
+                })
+                .find_map(|(b, back)| {
+                    let victims = victims_between(front, back, &trades[f + 1..b]);
+                    (!victims.is_empty()).then_some((b, back, victims))
+                });
+            let Some((b, back, victims)) = found else {
+                continue;
+            };
+            used.insert(f);
+            used.insert(b);
+            let sold_share = if front.output_amount > 0.0 {
+                (back.input_amount / front.output_amount).min(1.0)
+            } else {
+                1.0
+            };
+            sandwiches.push(Sandwich {
+                slot,
+                pool: pool.to_string(),
+                attacker: front.user.clone(),
+                front_run: front.clone(),
+                back_run: back.clone(),
+                victims,
+                profit_mint: front.input_mint.clone(),
+                attacker_profit: back.output_amount - front.input_amount * sold_share,
+            });
+        }
+    }
+    sandwiches.sort_by_key(|s| s.front_run.tx_position);
+    sandwiches
+}
+
+/// Other users' trades in the front-run's direction between `front` and `back`.
+fn victims_between(
+    front: &SandwichTrade,
+    back: &SandwichTrade,
+    between: &[SandwichTrade],
+) -> Vec<SandwichVictim> {
+    between
+        .iter()
+        .filter(|t| {
+            t.tx_position > front.tx_position
+                && t.tx_position < back.tx_position
+                && t.user != front.user
+                && t.input_mint == front.input_mint
+                && t.output_mint == front.output_mint
+        })
+        .map(|t| SandwichVictim {
+            loss: victim_loss(front, t),
+            trade: t.clone(),
+        })
+        .collect()
+}
+
+fn victim_loss(front: &SandwichTrade, victim: &SandwichTrade) -> f64 {
+    if front.input_amount <= 0.0 {
+        return 0.0;
+    }
+    let front_rate = front.output_amount / front.input_amount;
+    (victim.input_amount * front_rate - victim.output_amount).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{result, trade};
+
+    const SOL: &str = crate::constants::tokens::SOL;
+
+    const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+
+    /// `sol` whole SOL for `tokens` of a 0-decimal token.
+    fn buy(user: &str, sol: u64, tokens: u64) -> TradeInfo {
+        trade(
+            user,
+            "0",
+            "pool",
+            (SOL, sol * LAMPORTS_PER_SOL),
+            ("A", tokens),
+        )
+    }
+
+    fn sell(user: &str, tokens: u64, sol: u64) -> TradeInfo {
+        trade(
+            user,
+            "0",
+            "pool",
+            ("A", tokens),
+            (SOL, sol * LAMPORTS_PER_SOL),
+        )
+    }
+
+    #[test]
+    fn finds_front_victim_back() {
+        let results = [
+            result(1, "bot", 0, vec![buy("bot", 100, 1_000)]),
+            result(1, "alice", 0, vec![buy("alice", 100, 800)]),
+            result(1, "bot", 0, vec![sell("bot", 1_000, 120)]),
+        ];
+        let found = detect_sandwiches(&results);
+        assert_eq!(found.len(), 1);
+        let s = &found[0];
+        assert_eq!(s.attacker, "bot");
+        assert_eq!((s.front_run.tx_position, s.back_run.tx_position), (0, 2));
+        assert_eq!(s.victims.len(), 1);
+        assert_eq!(s.victims[0].loss, 200.0);
+        assert_eq!(s.attacker_profit, 20.0);
+    }
+
+    #[test]
+    fn keys_on_trade_user_not_fee_payer() {
+        // One relayer pays fees for everyone; the token owners tell the parties apart.
+        let results = [
+            result(1, "relayer", 0, vec![buy("bot", 100, 1_000)]),
+            result(1, "relayer", 0, vec![buy("alice", 100, 800)]),
+            result(1, "relayer", 0, vec![sell("bot", 1_000, 120)]),
+        ];
+        let found = detect_sandwiches(&results);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].attacker, "bot");
+        assert_eq!(found[0].victims[0].trade.user, "alice");
+    }
+
+    #[test]
+    fn skips_a_back_run_without_victims() {
+        let results = [
+            result(1, "bot", 0, vec![buy("bot", 100, 1_000)]),
+            result(1, "bot", 0, vec![sell("bot", 500, 60)]),
+            result(1, "alice", 0, vec![buy("alice", 100, 800)]),
+            result(1, "bot", 0, vec![sell("bot", 500, 70)]),
+        ];
+        let found = detect_sandwiches(&results);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].back_run.tx_position, 3);
+        assert_eq!(found[0].victims[0].trade.user, "alice");
+    }
+
+    #[test]
+    fn pairs_trades_within_one_slot_only() {
+        let results = [
+            result(1, "bot", 0, vec![buy("bot", 100, 1_000)]),
+            result(2, "alice", 0, vec![buy("alice", 100, 800)]),
+            result(2, "bot", 0, vec![sell("bot", 1_000, 120)]),
+            result(2, "bot", 0, vec![buy("bot", 100, 1_000)]),
+            result(2, "carol", 0, vec![buy("carol", 100, 800)]),
+            result(3, "bot", 0, vec![sell("bot", 1_000, 120)]),
+            result(3, "dave", 0, vec![buy("dave", 100, 800)]),
+            result(3, "bot", 0, vec![sell("bot", 1_000, 120)]),
+        ];
+        let found = detect_sandwiches(&results);
+        assert!(found.is_empty(), "{found:?}");
+
+        let results = [
+            result(1, "bot", 0, vec![buy("bot", 100, 1_000)]),
+            result(2, "bot", 0, vec![buy("bot", 100, 1_000)]),
+            result(2, "alice", 0, vec![buy("alice", 100, 800)]),
+            result(2, "bot", 0, vec![sell("bot", 1_000, 120)]),
+        ];
+        let found = detect_sandwiches(&results);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].slot, 2);
+        assert_eq!(
+            (
+                found[0].front_run.tx_position,
+                found[0].back_run.tx_position
+            ),
+            (1, 3)
+        );
+    }
+
+    #[test]
+    fn ignores_failed_transactions() {
+        let mut victim = result(1, "alice", 0, vec![buy("alice", 100, 800)]);
+        victim.tx_status = TransactionStatus::Failed;
+        let results = [
+            result(1, "bot", 0, vec![buy("bot", 100, 1_000)]),
+            victim,
+            result(1, "bot", 0, vec![sell("bot", 1_000, 120)]),
+        ];
+        assert!(detect_sandwiches(&results).is_empty());
+    }
+}
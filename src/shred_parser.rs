@@ -1,11 +1,15 @@
 //! Parser for shred-stream / pre-execution instruction analysis.
 //! Parses instructions by DEX without full transaction meta.
 
-use crate::constants::get_program_name;
+use crate::constants::dex_programs;
 use crate::instruction_classifier::InstructionClassifier;
+use crate::parsers::{jupiter, meteora, orca, pumpfun, pumpswap, raydium};
 use crate::transaction_adapter::TransactionAdapter;
-use crate::types::{ParseConfig, ParseShredResult};
-use std::collections::HashMap;
+use crate::types::{
+    ClassifiedInstruction, DecodedInstruction, ParseConfig, ParseShredResult, ProgramInstructions,
+    ShredInstruction,
+};
+use crate::utils::get_instruction_discriminator;
 
 pub struct ShredParser;
 
@@ -14,7 +18,8 @@ impl ShredParser {
         Self
     }
 
-    /// Parse instructions from transaction (e.g. from shred stream) grouped by DEX name.
+    /// Parse instructions from transaction (e.g. from shred stream), keyed by program id in
+    /// order of first appearance, decoding swap instructions into [crate::types::SwapIntent]s.
     pub fn parse_all(
         &self,
         tx: &crate::types::SolanaTransactionInput,
@@ -24,41 +29,240 @@ impl ShredParser {
         let mut result = ParseShredResult {
             state: true,
             signature: String::new(),
-            instructions: HashMap::new(),
+            instructions: ProgramInstructions::new(),
+            swap_intents: Vec::new(),
             msg: None,
         };
-        let adapter = TransactionAdapter::new(tx, Some(config.clone()));
-        result.signature = adapter.signature();
+        let adapter = TransactionAdapter::new(tx, Some(&config));
+        result.signature = adapter.signature().to_string();
         let classifier = InstructionClassifier::new(&adapter);
         let all_program_ids = classifier.get_all_program_ids();
         if let Some(ref filter) = config.program_ids {
-            if !all_program_ids.iter().any(|id| filter.contains(id)) {
+            if !all_program_ids
+                .iter()
+                .any(|id| filter.contains(&id.to_string()))
+            {
                 return result;
             }
         }
-        for program_id in &all_program_ids {
-            if config.program_ids.as_ref().map(|p| !p.contains(program_id)).unwrap_or(false) {
+        let mut instructions = Vec::new();
+        for key in &all_program_ids {
+            let program_id = &key.to_string();
+            if config
+                .program_ids
+                .as_ref()
+                .map(|p| !p.contains(program_id))
+                .unwrap_or(false)
+            {
                 continue;
             }
-            if config.ignore_program_ids.as_ref().map(|p| p.contains(program_id)).unwrap_or(false) {
+            if config
+                .ignore_program_ids
+                .as_ref()
+                .map(|p| p.contains(program_id))
+                .unwrap_or(false)
+            {
                 continue;
             }
-            let instructions = classifier.get_instructions(program_id);
-            let name = get_program_name(program_id);
-            result.instructions.insert(
-                name.to_string(),
-                instructions
-                    .into_iter()
-                    .map(|ci| serde_json::json!({ "programId": ci.program_id, "outerIndex": ci.outer_index, "innerIndex": ci.inner_index }))
-                    .collect(),
-            );
+            instructions.extend(classifier.get_instructions(key).iter());
+        }
+        instructions.sort_by_key(|ci| (ci.outer_index, ci.inner_index));
+        for ci in &instructions {
+            let decoded = decode_instruction(&adapter, ci);
+            if let Some(intent) = decoded.swap_intent() {
+                result.swap_intents.push(intent.clone());
+            }
+            result.instructions.push(ShredInstruction {
+                program_id: ci.program_id.to_string(),
+                outer_index: ci.outer_index,
+                inner_index: ci.inner_index,
+                decoded,
+            });
         }
         result
     }
 }
 
+/// Decode a classified instruction with its protocol's instruction decoder.
+pub fn decode_instruction(
+    adapter: &TransactionAdapter,
+    ci: &ClassifiedInstruction,
+) -> DecodedInstruction {
+    match ci.program_id {
+        id if id == dex_programs::JUPITER.key => {
+            DecodedInstruction::Jupiter(jupiter::decode_instruction(adapter, ci))
+        }
+        id if id == dex_programs::RAYDIUM_V4.key
+            || id == dex_programs::RAYDIUM_AMM.key
+            || id == dex_programs::RAYDIUM_CPMM.key =>
+        {
+            DecodedInstruction::Raydium(raydium::decode_instruction(adapter, ci))
+        }
+        id if id == dex_programs::ORCA.key => {
+            DecodedInstruction::Orca(orca::decode_instruction(adapter, ci))
+        }
+        id if id == dex_programs::METEORA.key => {
+            DecodedInstruction::Meteora(meteora::decode_instruction(adapter, ci))
+        }
+        id if id == dex_programs::PUMP_FUN.key => {
+            DecodedInstruction::Pumpfun(pumpfun::decode_instruction(adapter, ci))
+        }
+        id if id == dex_programs::PUMP_SWAP.key => {
+            DecodedInstruction::Pumpswap(pumpswap::decode_instruction(adapter, ci))
+        }
+        _ => DecodedInstruction::Unknown {
+            discriminator: get_instruction_discriminator(&ci.instruction.data),
+        },
+    }
+}
+
 impl Default for ShredParser {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{discriminators, tokens};
+    use crate::test_support::{ix, key, tx};
+    use crate::types::{
+        JupiterInstruction, PumpfunInstruction, RawInstruction, RaydiumInstruction,
+        SolanaTransactionInput,
+    };
+
+    fn pre_execution(
+        account_keys: Vec<String>,
+        instructions: Vec<RawInstruction>,
+    ) -> SolanaTransactionInput {
+        SolanaTransactionInput {
+            meta: None,
+            ..tx(account_keys, instructions, Vec::new())
+        }
+    }
+
+    fn args(disc: &[u8], values: &[u64]) -> Vec<u8> {
+        let mut data = disc.to_vec();
+        for v in values {
+            data.extend(v.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn decodes_pumpfun_buy_and_sell_limits() {
+        let mut keys: Vec<String> = (1..=7).map(key).collect();
+        keys.push(dex_programs::PUMP_FUN.id.to_string());
+        let accounts = [1, 2, 3, 4, 5, 6, 0];
+        let tx = pre_execution(
+            keys.clone(),
+            vec![
+                ix(
+                    7,
+                    &accounts,
+                    args(&discriminators::PUMPFUN_BUY, &[1_000, 50]),
+                ),
+                ix(
+                    7,
+                    &accounts,
+                    args(&discriminators::PUMPFUN_SELL, &[400, 20]),
+                ),
+            ],
+        );
+        let result = ShredParser::new().parse_all(&tx, None);
+        let [buy, sell] = &result.swap_intents[..] else {
+            panic!("expected two intents, got {:?}", result.swap_intents);
+        };
+        assert_eq!(buy.user, keys[0]);
+        assert_eq!(buy.pool.as_deref(), Some(keys[4].as_str()));
+        assert_eq!(buy.in_mint.as_deref(), Some(tokens::SOL));
+        assert_eq!(buy.out_mint.as_deref(), Some(keys[3].as_str()));
+        assert_eq!((buy.amount_out, buy.max_in), (Some(1_000), Some(50)));
+        assert_eq!(sell.in_mint.as_deref(), Some(keys[3].as_str()));
+        assert_eq!((sell.amount_in, sell.min_out), (Some(400), Some(20)));
+        assert_eq!(sell.idx.to_string(), "1");
+        let decoded = result.instructions.get(&keys[7]).unwrap();
+        assert!(matches!(
+            decoded[0].decoded,
+            DecodedInstruction::Pumpfun(PumpfunInstruction::Buy(_))
+        ));
+        assert!(matches!(
+            decoded[1].decoded,
+            DecodedInstruction::Pumpfun(PumpfunInstruction::Sell(_))
+        ));
+    }
+
+    #[test]
+    fn decodes_raydium_swap_base_in_without_meta() {
+        let mut keys: Vec<String> = (1..=17).map(key).collect();
+        keys.push(dex_programs::RAYDIUM_V4.id.to_string());
+        let accounts: Vec<u8> = (1..=16).chain([0]).collect();
+        let tx = pre_execution(
+            keys.clone(),
+            vec![ix(
+                17,
+                &accounts,
+                args(&discriminators::RAYDIUM_SWAP_BASE_IN, &[5_000, 4_900]),
+            )],
+        );
+        let result = ShredParser::new().parse_all(&tx, None);
+        let DecodedInstruction::Raydium(RaydiumInstruction::SwapBaseIn(intent)) =
+            &result.instructions.get(&keys[17]).unwrap()[0].decoded
+        else {
+            panic!("expected swap_base_in");
+        };
+        assert_eq!(intent.user, keys[0]);
+        assert_eq!(intent.pool.as_deref(), Some(keys[2].as_str()));
+        assert_eq!(
+            (intent.amount_in, intent.min_out),
+            (Some(5_000), Some(4_900))
+        );
+        // V4 names only token accounts, which can't be resolved to mints before execution
+        assert_eq!(
+            (intent.in_mint.as_ref(), intent.out_mint.as_ref()),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn applies_jupiter_slippage_to_the_quoted_amount() {
+        let mut keys: Vec<String> = (1..=9).map(key).collect();
+        keys.push(dex_programs::JUPITER.id.to_string());
+        // route plan bytes, then in_amount, quoted_out_amount, slippage_bps, platform_fee_bps
+        let mut data = args(&discriminators::JUPITER_SHARED_ACCOUNTS_ROUTE, &[]);
+        data.extend([0xAA; 5]);
+        data.extend(1_000u64.to_le_bytes());
+        data.extend(2_000u64.to_le_bytes());
+        data.extend(50u16.to_le_bytes());
+        data.push(0);
+        let tx = pre_execution(
+            keys.clone(),
+            vec![ix(9, &[1, 2, 0, 3, 4, 5, 6, 7, 8], data)],
+        );
+        let result = ShredParser::new().parse_all(&tx, None);
+        let DecodedInstruction::Jupiter(JupiterInstruction::SharedAccountsRoute(intent)) =
+            &result.instructions.get(&keys[9]).unwrap()[0].decoded
+        else {
+            panic!("expected shared_accounts_route");
+        };
+        assert_eq!(intent.user, keys[0]);
+        assert_eq!(intent.in_mint.as_deref(), Some(keys[7].as_str()));
+        assert_eq!(intent.out_mint.as_deref(), Some(keys[8].as_str()));
+        assert_eq!(intent.amount_in, Some(1_000));
+        assert_eq!(intent.min_out, Some(1_990));
+        assert_eq!(intent.slippage_bps, Some(50));
+    }
+
+    #[test]
+    fn leaves_non_swap_instructions_opaque() {
+        let keys = vec![key(1), dex_programs::PUMP_FUN.id.to_string()];
+        let tx = pre_execution(keys.clone(), vec![ix(1, &[0], vec![1, 2, 3])]);
+        let result = ShredParser::new().parse_all(&tx, None);
+        assert!(result.swap_intents.is_empty());
+        assert!(matches!(
+            &result.instructions.get(&keys[1]).unwrap()[0].decoded,
+            DecodedInstruction::Pumpfun(PumpfunInstruction::Other { discriminator }) if discriminator == &[1, 2, 3]
+        ));
+    }
+}
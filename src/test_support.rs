@@ -0,0 +1,191 @@
+//! Builders shared by unit tests.
+
+use crate::types::{
+    FeeInfo, InnerInstructionSet, ParseResult, RawInstruction, SolanaTransactionInput, TokenAmount,
+    TokenBalanceInput, TokenInfo, TradeInfo, TradeType, TransactionMetaInput, TransactionStatus,
+    UiTokenAmountInput,
+};
+
+/// Account key `n`: 32 bytes of `n`, base58-encoded.
+pub(crate) fn key(n: u8) -> String {
+    bs58::encode([n; 32]).into_string()
+}
+
+/// Outer instruction of the program at account `program` over `accounts`.
+pub(crate) fn ix(program: u8, accounts: &[u8], data: Vec<u8>) -> RawInstruction {
+    RawInstruction {
+        program_id_index: program,
+        data,
+        account_key_indexes: accounts.to_vec(),
+        stack_height: None,
+    }
+}
+
+/// Inner instruction like [ix], invoked at `stack_height`.
+pub(crate) fn cpi(
+    program: u8,
+    accounts: &[u8],
+    data: Vec<u8>,
+    stack_height: u32,
+) -> RawInstruction {
+    RawInstruction {
+        stack_height: Some(stack_height),
+        ..ix(program, accounts, data)
+    }
+}
+
+/// SPL Token `transfer` of `amount`.
+pub(crate) fn spl_transfer(amount: u64) -> Vec<u8> {
+    let mut data = vec![3];
+    data.extend(amount.to_le_bytes());
+    data
+}
+
+pub(crate) fn token_balance(
+    account_index: u32,
+    mint: &str,
+    owner: &str,
+    amount: u64,
+    decimals: u8,
+) -> TokenBalanceInput {
+    TokenBalanceInput {
+        account_index,
+        mint: Some(mint.to_string()),
+        owner: Some(owner.to_string()),
+        ui_token_amount: UiTokenAmountInput {
+            amount: amount.to_string(),
+            decimals,
+            ui_amount: None,
+            ui_amount_string: None,
+        },
+    }
+}
+
+/// A successful transaction signed by `account_keys[0]` with a 5000 lamport fee and no
+/// balances.
+pub(crate) fn tx(
+    account_keys: Vec<String>,
+    instructions: Vec<RawInstruction>,
+    inner_instructions: Vec<InnerInstructionSet>,
+) -> SolanaTransactionInput {
+    SolanaTransactionInput {
+        slot: 1,
+        block_time: Some(1_700_000_000),
+        version: None,
+        signatures: vec![vec![1; 64]],
+        header: None,
+        account_keys,
+        address_table_lookups: None,
+        instructions,
+        inner_instructions: Some(inner_instructions),
+        meta: Some(TransactionMetaInput {
+            err: None,
+            fee: Some(5000),
+            pre_balances: None,
+            post_balances: None,
+            pre_token_balances: None,
+            post_token_balances: None,
+            inner_instructions: None,
+            loaded_addresses: None,
+            compute_units_consumed: None,
+        }),
+    }
+}
+
+/// `amount_raw` of `mint`, with the decimals of well-known mints and 0 for others.
+pub(crate) fn token(mint: &str, amount_raw: u64) -> TokenInfo {
+    let decimals = crate::constants::token_decimals(mint).unwrap_or(0);
+    TokenInfo {
+        mint: mint.to_string(),
+        amount: crate::utils::convert_to_ui_amount(amount_raw, decimals),
+        amount_raw: amount_raw.to_string(),
+        decimals,
+        authority: None,
+        destination: None,
+        destination_owner: None,
+        source: None,
+        decimals_defaulted: false,
+        symbol: None,
+        name: None,
+    }
+}
+
+/// A `user` swap of `amount_in` of `input` for `amount_out` of `output` at `idx` in `pool`.
+pub(crate) fn trade(
+    user: &str,
+    idx: &str,
+    pool: &str,
+    (input, amount_in): (&str, u64),
+    (output, amount_out): (&str, u64),
+) -> TradeInfo {
+    TradeInfo {
+        user: user.to_string(),
+        trade_type: TradeType::Buy,
+        pool: vec![pool.to_string()],
+        input_token: token(input, amount_in),
+        output_token: token(output, amount_out),
+        slippage_bps: None,
+        fee: None,
+        fees: None,
+        program_id: None,
+        amm: None,
+        amms: None,
+        route: None,
+        slot: 0,
+        timestamp: 0,
+        signature: String::new(),
+        idx: idx.parse().unwrap(),
+        signer: None,
+        valuation: None,
+        legs: None,
+        circular: false,
+    }
+}
+
+pub(crate) fn fee(mint: &str, amount_raw: u64) -> FeeInfo {
+    FeeInfo {
+        mint: mint.to_string(),
+        amount: amount_raw as f64,
+        amount_raw: amount_raw.to_string(),
+        decimals: 0,
+        dex: None,
+        type_: None,
+        recipient: None,
+    }
+}
+
+/// A successful transaction of `signer` at `slot` with `trades` and a fee of `fee_lamports`.
+pub(crate) fn result(
+    slot: u64,
+    signer: &str,
+    fee_lamports: u64,
+    trades: Vec<TradeInfo>,
+) -> ParseResult {
+    ParseResult {
+        state: true,
+        fee: TokenAmount {
+            amount: fee_lamports.to_string(),
+            ui_amount: Some(fee_lamports as f64 / 1e9),
+            decimals: 9,
+        },
+        aggregate_trade: None,
+        trades,
+        liquidities: Vec::new(),
+        transfers: Vec::new(),
+        meme_events: Vec::new(),
+        slot,
+        timestamp: 0,
+        signature: format!("{signer}-{slot}"),
+        signer: vec![signer.to_string()],
+        compute_units: 0,
+        tx_status: TransactionStatus::Success,
+        msg: None,
+        sol_balance_change: None,
+        token_balance_change: None,
+        diagnostics: Vec::new(),
+        fee_breakdown: Default::default(),
+        tips: Vec::new(),
+        arbitrage: None,
+        block: None,
+    }
+}
@@ -0,0 +1,163 @@
+//! Tips: SOL transfers from a signer to block engine and relayer tip accounts.
+
+use crate::binary_reader::BinaryReader;
+use crate::constants::{system_instruction, tip_accounts, SYSTEM_PROGRAM};
+use crate::instruction_index::InstructionIndex;
+use crate::transaction_adapter::TransactionAdapter;
+use crate::types::{RawInstruction, TokenAmount};
+use crate::utils::convert_to_ui_amount;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// Built-in tip accounts by account, built once and shared by every [TipAccounts::default].
+static DEFAULT_TIP_ACCOUNTS: LazyLock<HashMap<&'static str, &'static str>> = LazyLock::new(|| {
+    [
+        ("Jito", tip_accounts::JITO),
+        ("Nozomi", tip_accounts::NOZOMI),
+        ("bloXroute", tip_accounts::BLOXROUTE),
+        ("0slot", tip_accounts::ZERO_SLOT),
+        ("NextBlock", tip_accounts::NEXTBLOCK),
+    ]
+    .into_iter()
+    .flat_map(|(provider, accounts)| accounts.iter().map(move |&a| (a, provider)))
+    .collect()
+});
+
+/// Tip accounts and the provider each belongs to. Only accounts added with
+/// [TipAccounts::with] are owned; the built-in ones are shared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TipAccounts {
+    defaults: bool,
+    accounts: HashMap<String, String>,
+}
+
+impl TipAccounts {
+    /// No tip accounts; add them with [TipAccounts::with].
+    pub fn empty() -> Self {
+        Self {
+            defaults: false,
+            accounts: HashMap::new(),
+        }
+    }
+
+    pub fn with(mut self, provider: impl Into<String>, account: impl Into<String>) -> Self {
+        self.accounts.insert(account.into(), provider.into());
+        self
+    }
+
+    pub fn with_all<I, S>(mut self, provider: &str, accounts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        for account in accounts {
+            self.accounts.insert(account.into(), provider.to_string());
+        }
+        self
+    }
+
+    /// Provider of a tip account; added accounts take precedence over the built-in ones.
+    pub fn provider(&self, account: &str) -> Option<&str> {
+        self.accounts.get(account).map(String::as_str).or_else(|| {
+            self.defaults
+                .then(|| DEFAULT_TIP_ACCOUNTS.get(account).copied())
+                .flatten()
+        })
+    }
+}
+
+impl Default for TipAccounts {
+    /// Jito, Nozomi, bloXroute, 0slot and NextBlock.
+    fn default() -> Self {
+        Self {
+            defaults: true,
+            accounts: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tip {
+    pub idx: InstructionIndex,
+    pub from: String,
+    pub recipient: String,
+    pub provider: String,
+    pub amount: TokenAmount,
+}
+
+/// System Program transfers, outer or inner, from one of the transaction's signers to
+/// an account of `tip_accounts`.
+pub fn detect_tips(adapter: &TransactionAdapter, tip_accounts: &TipAccounts) -> Vec<Tip> {
+    let signers = adapter.signer_keys();
+    let mut tips = Vec::new();
+    let mut check = |raw: &RawInstruction, idx: InstructionIndex| {
+        if adapter.get_instruction_program_id(raw) != Some(SYSTEM_PROGRAM) {
+            return;
+        }
+        let Some(lamports) = decode_transfer(&raw.data) else {
+            return;
+        };
+        let account = |i: usize| {
+            raw.account_key_indexes
+                .get(i)
+                .and_then(|&k| adapter.account_key(k as usize))
+        };
+        let (Some(from), Some(recipient)) = (account(0), account(1)) else {
+            return;
+        };
+        if !signers.contains(from) {
+            return;
+        }
+        let recipient = recipient.to_string();
+        let Some(provider) = tip_accounts.provider(&recipient) else {
+            return;
+        };
+        tips.push(Tip {
+            idx,
+            from: from.to_string(),
+            provider: provider.to_string(),
+            recipient,
+            amount: TokenAmount {
+                amount: lamports.to_string(),
+                ui_amount: Some(convert_to_ui_amount(lamports, 9)),
+                decimals: 9,
+            },
+        });
+    };
+    for (outer, raw) in adapter.raw_instructions().iter().enumerate() {
+        check(raw, InstructionIndex::outer(outer));
+    }
+    for set in adapter.raw_inner_instructions().unwrap_or_default() {
+        for (inner, raw) in set.instructions.iter().enumerate() {
+            check(raw, InstructionIndex::inner(set.index as usize, inner));
+        }
+    }
+    tips
+}
+
+/// Lamports of a System Program `Transfer`.
+fn decode_transfer(data: &[u8]) -> Option<u64> {
+    let mut reader = BinaryReader::new(data);
+    (reader.read_u32_le().ok()? == system_instruction::TRANSFER as u32)
+        .then(|| reader.read_u64_le().ok())
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn additions_extend_and_override_the_defaults() {
+        let jito = tip_accounts::JITO[0];
+        let tips = TipAccounts::default()
+            .with("MyRelay", "relay")
+            .with("Other", jito);
+        assert_eq!(tips.provider("relay"), Some("MyRelay"));
+        assert_eq!(tips.provider(jito), Some("Other"));
+        assert_eq!(tips.provider(tip_accounts::NOZOMI[0]), Some("Nozomi"));
+        assert_eq!(TipAccounts::empty().provider(jito), None);
+        assert_eq!(tips.provider("unknown"), None);
+    }
+}
@@ -0,0 +1,55 @@
+//! Token metadata (decimals, symbol, name) for mints a transaction doesn't describe.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    pub decimals: Option<u8>,
+    pub symbol: Option<String>,
+    pub name: Option<String>,
+}
+
+/// Mint metadata source, e.g. a cache in front of on-chain mint and metadata accounts.
+/// Set it on [crate::ParseConfig::token_metadata] or [crate::DexParserBuilder::token_metadata].
+/// Consulted only for mints whose decimals the transaction's token balances don't reveal,
+/// and for symbol/name when [crate::ParseConfig::enrich_token_metadata] is set.
+pub trait TokenMetadataProvider: Debug + Send + Sync {
+    fn get(&self, mint: &str) -> Option<TokenMetadata>;
+}
+
+/// [TokenMetadataProvider] backed by a map.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryTokenMetadata {
+    entries: HashMap<String, TokenMetadata>,
+}
+
+impl InMemoryTokenMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, mint: impl Into<String>, metadata: TokenMetadata) {
+        self.entries.insert(mint.into(), metadata);
+    }
+
+    pub fn with(mut self, mint: impl Into<String>, metadata: TokenMetadata) -> Self {
+        self.insert(mint, metadata);
+        self
+    }
+}
+
+impl FromIterator<(String, TokenMetadata)> for InMemoryTokenMetadata {
+    fn from_iter<I: IntoIterator<Item = (String, TokenMetadata)>>(iter: I) -> Self {
+        Self {
+            entries: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl TokenMetadataProvider for InMemoryTokenMetadata {
+    fn get(&self, mint: &str) -> Option<TokenMetadata> {
+        self.entries.get(mint).cloned()
+    }
+}
@@ -1,46 +1,129 @@
 //! Adapter for unified transaction data access.
 
-use crate::constants::{
-    spl_token_instruction, tokens, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID,
-};
+use crate::constants::{spl_token_instruction, tokens, TOKEN_2022_PROGRAM, TOKEN_PROGRAM};
+use crate::error::ParseError;
+use crate::quote_assets::QuoteAssets;
+use crate::token_metadata::TokenMetadata;
 use crate::types::{
-    BalanceChange, ParseConfig, PoolEventType, TokenAmount, TokenInfo, TransactionStatus,
-    InnerInstructionSet, RawInstruction, SolanaTransactionInput, TokenBalanceInput,
+    BalanceChange, InnerInstructionSet, ParseConfig, PoolEventType, RawInstruction,
+    SolanaTransactionInput, TokenAmount, TokenBalanceInput, TokenInfo, TransactionStatus,
 };
-use crate::utils::{convert_to_ui_amount, get_transfer_token_mint};
+use crate::utils::convert_to_ui_amount;
+use solana_sdk::pubkey::Pubkey;
 use std::collections::HashMap;
 
+/// Account keys are decoded once on construction and handled as [Pubkey]s; they are
+/// encoded to base58 only when they end up in an output type.
 pub struct TransactionAdapter<'a> {
     tx: &'a SolanaTransactionInput,
-    pub config: Option<ParseConfig>,
-    pub account_keys: Vec<String>,
-    pub spl_token_map: HashMap<String, TokenInfo>,
+    pub config: Option<&'a ParseConfig>,
+    pub account_keys: Vec<Pubkey>,
+    /// Position of each account key, for [Self::get_account_index].
+    key_index: HashMap<Pubkey, usize>,
+    /// First account key that isn't valid base58, kept as [Pubkey::default] in `account_keys`.
+    invalid_key: Option<usize>,
+    pub spl_token_map: HashMap<Pubkey, TokenInfo>,
     pub spl_decimals_map: HashMap<String, u8>,
+    /// First signature and signers, encoded once for every trade and transfer to copy.
+    signature: String,
+    signers: Vec<String>,
 }
 
 impl<'a> TransactionAdapter<'a> {
-    pub fn new(tx: &'a SolanaTransactionInput, config: Option<ParseConfig>) -> Self {
-        let account_keys = Self::extract_account_keys(tx);
+    pub fn new(tx: &'a SolanaTransactionInput, config: Option<&'a ParseConfig>) -> Self {
+        let mut invalid_key = None;
+        let account_keys: Vec<Pubkey> = Self::input_account_keys(tx)
+            .enumerate()
+            .map(|(index, key)| {
+                key.parse().unwrap_or_else(|_| {
+                    invalid_key.get_or_insert(index);
+                    Pubkey::default()
+                })
+            })
+            .collect();
+        let mut key_index = HashMap::with_capacity(account_keys.len());
+        for (index, key) in account_keys.iter().enumerate() {
+            key_index.entry(*key).or_insert(index);
+        }
         let mut adapter = Self {
             tx,
             config,
-            account_keys: account_keys.clone(),
+            account_keys,
+            key_index,
+            invalid_key,
             spl_token_map: HashMap::new(),
             spl_decimals_map: HashMap::new(),
+            signature: tx
+                .signatures
+                .first()
+                .map(|sig| bs58::encode(sig).into_string())
+                .unwrap_or_default(),
+            signers: Vec::new(),
         };
+        adapter.signers = adapter
+            .signer_keys()
+            .iter()
+            .map(Pubkey::to_string)
+            .collect();
         adapter.extract_token_info();
         adapter
     }
 
-    fn extract_account_keys(tx: &SolanaTransactionInput) -> Vec<String> {
-        let mut keys = tx.account_keys.clone();
-        if let Some(ref meta) = tx.meta {
-            if let Some(ref loaded) = meta.loaded_addresses {
-                keys.extend(loaded.writable.clone());
-                keys.extend(loaded.readonly.clone());
+    /// Static then loaded account keys, in the order instructions index them.
+    fn input_account_keys(tx: &'a SolanaTransactionInput) -> impl Iterator<Item = &'a str> {
+        let loaded = tx.meta.as_ref().and_then(|m| m.loaded_addresses.as_ref());
+        tx.account_keys
+            .iter()
+            .chain(
+                loaded
+                    .into_iter()
+                    .flat_map(|l| l.writable.iter().chain(&l.readonly)),
+            )
+            .map(String::as_str)
+    }
+
+    /// Check that meta is present, the first signature is well-formed, every account key
+    /// decodes and every instruction only references accounts that exist.
+    pub fn validate(&self) -> Result<(), ParseError> {
+        if self.tx.meta.is_none() {
+            return Err(ParseError::MissingMeta);
+        }
+        match self.tx.signatures.first() {
+            Some(sig) if sig.len() == 64 => {}
+            Some(sig) => {
+                return Err(ParseError::MalformedData(format!(
+                    "signature is {} bytes, expected 64",
+                    sig.len()
+                )))
+            }
+            None => {
+                return Err(ParseError::MalformedData(
+                    "transaction has no signatures".to_string(),
+                ))
+            }
+        }
+        if let Some(index) = self.invalid_key {
+            return Err(ParseError::MalformedData(format!(
+                "account key {index} is not a valid public key"
+            )));
+        }
+        let len = self.account_keys.len();
+        let inner = self
+            .tx
+            .inner_instructions
+            .iter()
+            .flatten()
+            .flat_map(|set| set.instructions.iter());
+        for raw in self.tx.instructions.iter().chain(inner) {
+            let indexes = std::iter::once(&raw.program_id_index).chain(&raw.account_key_indexes);
+            if let Some(&index) = indexes.into_iter().find(|&&i| i as usize >= len) {
+                return Err(ParseError::UnknownAccountIndex {
+                    index: index as usize,
+                    len,
+                });
             }
         }
-        keys
+        Ok(())
     }
 
     pub fn slot(&self) -> u64 {
@@ -51,21 +134,119 @@ impl<'a> TransactionAdapter<'a> {
         self.tx.block_time.unwrap_or(0)
     }
 
-    pub fn signature(&self) -> String {
-        if let Some(sig) = self.tx.signatures.first() {
-            bs58::encode(sig).into_string()
-        } else {
-            String::new()
-        }
+    pub fn signature(&self) -> &str {
+        &self.signature
     }
 
     pub fn signer(&self) -> String {
-        self.get_account_key(0).unwrap_or_else(|| String::new())
+        self.get_account_key(0).unwrap_or_default()
     }
 
-    pub fn signers(&self) -> Vec<String> {
-        let n = 1.min(self.account_keys.len());
-        self.account_keys.iter().take(n).cloned().collect()
+    /// Number of leading account keys that signed: from the message header when the input
+    /// carries one, else one per signature (at least the fee payer).
+    pub fn num_required_signatures(&self) -> usize {
+        let n = match self.tx.header {
+            Some(header) => header.num_required_signatures as usize,
+            None => self.tx.signatures.len().max(1),
+        };
+        n.min(self.tx.account_keys.len())
+    }
+
+    /// Every required signer, fee payer first.
+    pub fn signers(&self) -> &[String] {
+        &self.signers
+    }
+
+    /// Keys of [Self::signers].
+    pub fn signer_keys(&self) -> &[Pubkey] {
+        &self.account_keys[..self.num_required_signatures()]
+    }
+
+    pub fn is_signer(&self, index: usize) -> bool {
+        index < self.num_required_signatures()
+    }
+
+    /// Whether the account at `index` is write-locked. Static keys follow the message
+    /// header; without one, every static key except invoked programs counts as writable.
+    pub fn is_writable(&self, index: usize) -> bool {
+        let static_len = self.tx.account_keys.len();
+        if index >= static_len {
+            let writable_len = self
+                .tx
+                .meta
+                .as_ref()
+                .and_then(|m| m.loaded_addresses.as_ref())
+                .map(|l| l.writable.len())
+                .unwrap_or(0);
+            return index < static_len + writable_len;
+        }
+        match self.tx.header {
+            Some(header) => {
+                let signed = self.num_required_signatures();
+                if index < signed {
+                    index < signed.saturating_sub(header.num_readonly_signed_accounts as usize)
+                } else {
+                    index
+                        < static_len.saturating_sub(header.num_readonly_unsigned_accounts as usize)
+                }
+            }
+            None => !self
+                .tx
+                .instructions
+                .iter()
+                .any(|ix| ix.program_id_index as usize == index),
+        }
+    }
+
+    /// Where the account key at `index` comes from: the message itself or an address
+    /// lookup table. `None` for an out-of-range index, or a loaded address whose table
+    /// isn't in [SolanaTransactionInput::address_table_lookups].
+    pub fn account_key_source(&self, index: usize) -> Option<AccountKeySource> {
+        let static_len = self.tx.account_keys.len();
+        if index < static_len {
+            return Some(AccountKeySource::Static);
+        }
+        let loaded = self.tx.meta.as_ref()?.loaded_addresses.as_ref()?;
+        let lookups = self.tx.address_table_lookups.as_deref()?;
+        let (mut position, writable) = match index - static_len {
+            i if i < loaded.writable.len() => (i, true),
+            i => (i - loaded.writable.len(), false),
+        };
+        for lookup in lookups {
+            let indexes = if writable {
+                &lookup.writable_indexes
+            } else {
+                &lookup.readonly_indexes
+            };
+            if let Some(&table_index) = indexes.get(position) {
+                return Some(AccountKeySource::LookupTable {
+                    table: lookup.account_key.clone(),
+                    index: table_index,
+                    writable,
+                });
+            }
+            position -= indexes.len();
+        }
+        None
+    }
+
+    /// The signer that owns (or is the authority of) `source_token_account`, falling back to
+    /// the fee payer. Picks the user of a trade when a bot pays fees for a user's wallet.
+    /// Takes the base58 keys of a [crate::types::TransferData].
+    pub fn source_owner_signer(
+        &self,
+        source_token_account: &str,
+        authority: Option<&str>,
+    ) -> String {
+        let is_signer = |key: &str| self.signers.iter().any(|signer| signer == key);
+        source_token_account
+            .parse::<Pubkey>()
+            .ok()
+            .and_then(|s| self.get_token_account_owner(&s))
+            .filter(|owner| is_signer(owner))
+            .or_else(|| authority.filter(|a| is_signer(a)).map(String::from))
+            .or_else(|| is_signer(source_token_account).then(|| source_token_account.to_string()))
+            .unwrap_or_else(|| self.signer())
     }
 
     pub fn fee(&self) -> TokenAmount {
@@ -98,7 +279,7 @@ impl<'a> TransactionAdapter<'a> {
         }
     }
 
-    pub fn instructions(&self) -> Vec<ParsedInstructionRef> {
+    pub fn instructions(&self) -> Vec<ParsedInstructionRef<'a>> {
         self.tx
             .instructions
             .iter()
@@ -119,74 +300,102 @@ impl<'a> TransactionAdapter<'a> {
         self.tx.inner_instructions.as_deref()
     }
 
-    fn raw_to_parsed(&self, raw: &RawInstruction, outer_index: usize) -> ParsedInstructionRef {
-        let program_id = self
-            .get_account_key(raw.program_id_index as usize)
-            .unwrap_or_default();
-        let accounts: Vec<String> = raw
-            .account_key_indexes
-            .iter()
-            .filter_map(|&i| self.get_account_key(i as usize))
-            .collect();
+    fn raw_to_parsed(
+        &self,
+        raw: &'a RawInstruction,
+        outer_index: usize,
+    ) -> ParsedInstructionRef<'a> {
         ParsedInstructionRef {
-            program_id,
-            accounts,
-            data: raw.data.clone(),
+            program_id: self.get_instruction_program_id(raw).unwrap_or_default(),
+            accounts: self.instruction_accounts(raw),
+            data: &raw.data,
             outer_index,
         }
     }
 
-    pub fn get_instruction(
-        &self,
-        raw: &RawInstruction,
-        program_id_index: u8,
-    ) -> crate::types::ParsedInstruction {
-        let program_id = self
-            .get_account_key(program_id_index as usize)
-            .unwrap_or_default();
-        let accounts: Vec<String> = raw
-            .account_key_indexes
-            .iter()
-            .filter_map(|&i| self.get_account_key(i as usize))
-            .collect();
-        crate::types::ParsedInstruction {
-            program_id,
-            accounts,
+    /// `raw` with its keys resolved; `None` when its program index is out of range.
+    pub fn get_instruction(&self, raw: &RawInstruction) -> Option<crate::types::ParsedInstruction> {
+        Some(crate::types::ParsedInstruction {
+            program_id: self.get_instruction_program_id(raw)?,
+            accounts: self.instruction_accounts(raw),
             data: raw.data.clone(),
             parsed: None,
-        }
+        })
+    }
+
+    /// Keys of the accounts `raw` references, skipping out-of-range indexes.
+    pub fn instruction_accounts(&self, raw: &RawInstruction) -> Vec<Pubkey> {
+        raw.account_key_indexes
+            .iter()
+            .filter_map(|&i| self.account_key(i as usize).copied())
+            .collect()
     }
 
-    pub fn get_instruction_program_id(&self, raw: &RawInstruction) -> String {
-        self.get_account_key(raw.program_id_index as usize)
-            .unwrap_or_default()
+    /// Program `raw` invokes; `None` when its program index is out of range.
+    pub fn get_instruction_program_id(&self, raw: &RawInstruction) -> Option<Pubkey> {
+        self.account_key(raw.program_id_index as usize).copied()
     }
 
+    /// Account key at `index`, base58-encoded.
     pub fn get_account_key(&self, index: usize) -> Option<String> {
-        self.account_keys.get(index).cloned()
+        self.account_key(index).map(Pubkey::to_string)
     }
 
-    pub fn get_account_index(&self, address: &str) -> Option<usize> {
-        self.account_keys.iter().position(|k| k == address)
+    pub fn account_key(&self, index: usize) -> Option<&Pubkey> {
+        self.account_keys.get(index)
     }
 
+    /// First position of `address` among the account keys.
+    pub fn get_account_index(&self, address: &Pubkey) -> Option<usize> {
+        self.key_index.get(address).copied()
+    }
+
+    pub fn contains_account(&self, address: &Pubkey) -> bool {
+        self.key_index.contains_key(address)
+    }
+
+    /// Mint of a token account, if this transaction's balances or transfers reveal it.
+    pub fn get_token_account_mint(&self, account_key: &Pubkey) -> Option<String> {
+        self.spl_token_map.get(account_key).map(|t| t.mint.clone())
+    }
+
+    /// Whether `mint` is one of the configured [ParseConfig::quote_assets].
     pub fn is_supported_token(&self, mint: &str) -> bool {
-        matches!(
-            mint,
-            tokens::SOL
-                | tokens::USDC
-                | tokens::USDT
-                | tokens::USD1
-                | tokens::USDG
-                | tokens::PYUSD
-                | tokens::EURC
-                | tokens::USDY
-                | tokens::FDUSD
-        )
+        self.quote_assets().is_quote(mint)
+    }
+
+    pub fn quote_assets(&self) -> &QuoteAssets {
+        self.config
+            .map(|c| &c.quote_assets)
+            .unwrap_or(QuoteAssets::shared())
     }
 
+    /// Metadata for `mint` from [ParseConfig::token_metadata], if configured.
+    pub fn token_metadata(&self, mint: &str) -> Option<TokenMetadata> {
+        self.config?.token_metadata.as_ref()?.get(mint)
+    }
+
+    /// Decimals for `mint`, or 0 if nothing knows them. See [Self::resolve_token_decimals].
     pub fn get_token_decimals(&self, mint: &str) -> u8 {
-        *self.spl_decimals_map.get(mint).unwrap_or(&0)
+        self.resolve_token_decimals(mint, 0).0
+    }
+
+    /// Decimals for `mint` from the transaction's token balances and transfers, then
+    /// [ParseConfig::mint_decimals], then [ParseConfig::token_metadata], then well-known
+    /// mints; otherwise `default`. The flag is `true` when `default` was used.
+    pub fn resolve_token_decimals(&self, mint: &str, default: u8) -> (u8, bool) {
+        self.spl_decimals_map
+            .get(mint)
+            .copied()
+            .or_else(|| {
+                self.config
+                    .and_then(|c| c.mint_decimals.as_ref())
+                    .and_then(|m| m.get(mint).copied())
+            })
+            .or_else(|| self.token_metadata(mint).and_then(|m| m.decimals))
+            .or_else(|| crate::constants::token_decimals(mint))
+            .map(|d| (d, false))
+            .unwrap_or((default, true))
     }
 
     pub fn get_pool_event_base(
@@ -201,7 +410,7 @@ impl<'a> TransactionAdapter<'a> {
             amm: crate::constants::get_program_name(program_id).to_string(),
             slot: self.slot(),
             timestamp: self.block_time(),
-            signature: self.signature(),
+            signature: self.signature().to_string(),
         }
     }
 
@@ -221,7 +430,7 @@ impl<'a> TransactionAdapter<'a> {
         self.tx.meta.as_ref()?.post_token_balances.as_deref()
     }
 
-    pub fn get_token_account_owner(&self, account_key: &str) -> Option<String> {
+    pub fn get_token_account_owner(&self, account_key: &Pubkey) -> Option<String> {
         let post = self.post_token_balances()?;
         let index = self.get_account_index(account_key)?;
         post.iter()
@@ -229,13 +438,10 @@ impl<'a> TransactionAdapter<'a> {
             .and_then(|b| b.owner.clone())
     }
 
-    pub fn get_token_account_balance(&self, account_keys: &[String]) -> Vec<Option<TokenAmount>> {
+    pub fn get_token_account_balance(&self, account_keys: &[Pubkey]) -> Vec<Option<TokenAmount>> {
         account_keys
             .iter()
             .map(|key| {
-                if key.is_empty() {
-                    return None;
-                }
                 let post = self.post_token_balances()?;
                 let index = self.get_account_index(key)?;
                 let bal = post.iter().find(|b| b.account_index == index as u32)?;
@@ -250,14 +456,11 @@ impl<'a> TransactionAdapter<'a> {
 
     pub fn get_token_account_pre_balance(
         &self,
-        account_keys: &[String],
+        account_keys: &[Pubkey],
     ) -> Vec<Option<TokenAmount>> {
         account_keys
             .iter()
             .map(|key| {
-                if key.is_empty() {
-                    return None;
-                }
                 let pre = self.pre_token_balances()?;
                 let index = self.get_account_index(key)?;
                 let bal = pre.iter().find(|b| b.account_index == index as u32)?;
@@ -270,13 +473,10 @@ impl<'a> TransactionAdapter<'a> {
             .collect()
     }
 
-    pub fn get_account_balance(&self, account_keys: &[String]) -> Vec<Option<TokenAmount>> {
+    pub fn get_account_balance(&self, account_keys: &[Pubkey]) -> Vec<Option<TokenAmount>> {
         account_keys
             .iter()
             .map(|key| {
-                if key.is_empty() {
-                    return None;
-                }
                 let index = self.get_account_index(key)?;
                 let post = self.post_balances()?;
                 let amount = *post.get(index)?;
@@ -289,13 +489,10 @@ impl<'a> TransactionAdapter<'a> {
             .collect()
     }
 
-    pub fn get_account_pre_balance(&self, account_keys: &[String]) -> Vec<Option<TokenAmount>> {
+    pub fn get_account_pre_balance(&self, account_keys: &[Pubkey]) -> Vec<Option<TokenAmount>> {
         account_keys
             .iter()
             .map(|key| {
-                if key.is_empty() {
-                    return None;
-                }
                 let index = self.get_account_index(key)?;
                 let pre = self.pre_balances()?;
                 let amount = *pre.get(index)?;
@@ -311,52 +508,55 @@ impl<'a> TransactionAdapter<'a> {
     fn extract_token_info(&mut self) {
         self.extract_token_balances();
         self.extract_token_from_instructions();
-        if !self.spl_token_map.contains_key(tokens::SOL) {
-            self.spl_token_map.insert(
-                tokens::SOL.to_string(),
-                TokenInfo {
-                    mint: tokens::SOL.to_string(),
-                    amount: 0.0,
-                    amount_raw: "0".to_string(),
-                    decimals: 9,
-                    authority: None,
-                    destination: None,
-                    destination_owner: None,
-                    source: None,
-                },
-            );
-        }
+        const SOL: Pubkey = Pubkey::from_str_const(tokens::SOL);
+        self.spl_token_map.entry(SOL).or_insert_with(|| TokenInfo {
+            mint: tokens::SOL.to_string(),
+            amount: 0.0,
+            amount_raw: "0".to_string(),
+            decimals: 9,
+            authority: None,
+            destination: None,
+            destination_owner: None,
+            source: None,
+            decimals_defaulted: false,
+            symbol: None,
+            name: None,
+        });
         self.spl_decimals_map.insert(tokens::SOL.to_string(), 9);
     }
 
     fn extract_token_balances(&mut self) {
-        let post: Vec<_> = match self.post_token_balances() {
-            Some(p) => p.to_vec(),
-            None => return,
+        let tx = self.tx;
+        let Some(post) = tx
+            .meta
+            .as_ref()
+            .and_then(|m| m.post_token_balances.as_deref())
+        else {
+            return;
         };
         for balance in post {
             let mint = match &balance.mint {
                 Some(m) => m.clone(),
                 None => continue,
             };
-            let account_key = self
-                .get_account_key(balance.account_index as usize)
-                .unwrap_or_default();
-            if !self.spl_token_map.contains_key(&account_key) {
-                self.spl_token_map.insert(
-                    account_key,
-                    TokenInfo {
-                        mint: mint.clone(),
-                        amount: balance.ui_token_amount.ui_amount.unwrap_or(0.0),
-                        amount_raw: balance.ui_token_amount.amount.clone(),
-                        decimals: balance.ui_token_amount.decimals,
-                        authority: None,
-                        destination: None,
-                        destination_owner: balance.owner.clone(),
-                        source: None,
-                    },
-                );
-            }
+            let Some(&account_key) = self.account_key(balance.account_index as usize) else {
+                continue;
+            };
+            self.spl_token_map
+                .entry(account_key)
+                .or_insert_with(|| TokenInfo {
+                    mint: mint.clone(),
+                    amount: balance.ui_token_amount.ui_amount.unwrap_or(0.0),
+                    amount_raw: balance.ui_token_amount.amount.clone(),
+                    decimals: balance.ui_token_amount.decimals,
+                    authority: None,
+                    destination: None,
+                    destination_owner: balance.owner.clone(),
+                    source: None,
+                    decimals_defaulted: false,
+                    symbol: None,
+                    name: None,
+                });
             self.spl_decimals_map
                 .insert(mint, balance.ui_token_amount.decimals);
         }
@@ -380,39 +580,39 @@ impl<'a> TransactionAdapter<'a> {
         if data.is_empty() {
             return;
         }
-        let program_id = self
-            .get_account_key(ix.program_id_index as usize)
-            .unwrap_or_default();
-        if program_id != TOKEN_PROGRAM_ID && program_id != TOKEN_2022_PROGRAM_ID {
+        if !matches!(
+            self.get_instruction_program_id(ix),
+            Some(TOKEN_PROGRAM | TOKEN_2022_PROGRAM)
+        ) {
             return;
         }
-        let accounts: Vec<String> = ix
-            .account_key_indexes
-            .iter()
-            .filter_map(|&i| self.get_account_key(i as usize))
-            .collect();
+        let accounts = self.instruction_accounts(ix);
         let (source, destination, mint, decimals) = match data[0] {
             spl_token_instruction::TRANSFER => {
                 if accounts.len() < 2 {
                     return;
                 }
-                let source = accounts[0].clone();
-                let dest = accounts[1].clone();
+                let (source, dest) = (accounts[0], accounts[1]);
                 let token1 = self.spl_token_map.get(&dest).map(|t| t.mint.clone());
                 let token2 = self.spl_token_map.get(&source).map(|t| t.mint.clone());
-                let mint =
-                    get_transfer_token_mint(token1.as_deref(), token2.as_deref());
+                let mint = self
+                    .quote_assets()
+                    .transfer_token_mint(token1.as_deref(), token2.as_deref());
                 (Some(source), Some(dest), mint, None)
             }
             spl_token_instruction::TRANSFER_CHECKED => {
                 if accounts.len() < 3 {
                     return;
                 }
-                let dec = if data.len() >= 10 { Some(data[9]) } else { None };
+                let dec = if data.len() >= 10 {
+                    Some(data[9])
+                } else {
+                    None
+                };
                 (
-                    Some(accounts[0].clone()),
-                    Some(accounts[2].clone()),
-                    Some(accounts[1].clone()),
+                    Some(accounts[0]),
+                    Some(accounts[2]),
+                    Some(accounts[1].to_string()),
                     dec,
                 )
             }
@@ -420,25 +620,23 @@ impl<'a> TransactionAdapter<'a> {
                 if accounts.len() < 2 {
                     return;
                 }
-                let dec = if data.len() >= 10 { Some(data[9]) } else { None };
-                (
-                    None,
-                    Some(accounts[1].clone()),
-                    Some(accounts[0].clone()),
-                    dec,
-                )
+                let dec = if data.len() >= 10 {
+                    Some(data[9])
+                } else {
+                    None
+                };
+                (None, Some(accounts[1]), Some(accounts[0].to_string()), dec)
             }
             spl_token_instruction::BURN | spl_token_instruction::BURN_CHECKED => {
                 if accounts.len() < 2 {
                     return;
                 }
-                let dec = if data.len() >= 10 { Some(data[9]) } else { None };
-                (
-                    Some(accounts[0].clone()),
-                    None,
-                    Some(accounts[1].clone()),
-                    dec,
-                )
+                let dec = if data.len() >= 10 {
+                    Some(data[9])
+                } else {
+                    None
+                };
+                (Some(accounts[0]), None, Some(accounts[1].to_string()), dec)
             }
             _ => return,
         };
@@ -448,21 +646,19 @@ impl<'a> TransactionAdapter<'a> {
             }
         }
         for acc in [source, destination].into_iter().flatten() {
-            if !self.spl_token_map.contains_key(&acc) {
-                self.spl_token_map.insert(
-                    acc,
-                    TokenInfo {
-                        mint: mint.clone().unwrap_or_else(|| tokens::SOL.to_string()),
-                        amount: 0.0,
-                        amount_raw: "0".to_string(),
-                        decimals: decimals.unwrap_or(9),
-                        authority: None,
-                        destination: None,
-                        destination_owner: None,
-                        source: None,
-                    },
-                );
-            }
+            self.spl_token_map.entry(acc).or_insert_with(|| TokenInfo {
+                mint: mint.clone().unwrap_or_else(|| tokens::SOL.to_string()),
+                amount: 0.0,
+                amount_raw: "0".to_string(),
+                decimals: decimals.unwrap_or(9),
+                authority: None,
+                destination: None,
+                destination_owner: None,
+                source: None,
+                decimals_defaulted: decimals.is_none(),
+                symbol: None,
+                name: None,
+            });
         }
     }
 
@@ -479,7 +675,7 @@ impl<'a> TransactionAdapter<'a> {
             let change = post_bal as i64 - pre_bal as i64;
             if change != 0 {
                 changes.insert(
-                    key.clone(),
+                    key.to_string(),
                     BalanceChange {
                         pre: TokenAmount {
                             amount: pre_bal.to_string(),
@@ -595,10 +791,23 @@ impl<'a> TransactionAdapter<'a> {
     }
 }
 
-pub struct ParsedInstructionRef {
-    pub program_id: String,
-    pub accounts: Vec<String>,
-    pub data: Vec<u8>,
+/// Origin of an account key in a (possibly versioned) message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountKeySource {
+    Static,
+    /// Loaded from the lookup table at `table`, entry `index`.
+    LookupTable {
+        table: String,
+        index: u8,
+        writable: bool,
+    },
+}
+
+/// An outer instruction with its keys resolved and its data borrowed from the input.
+pub struct ParsedInstructionRef<'a> {
+    pub program_id: Pubkey,
+    pub accounts: Vec<Pubkey>,
+    pub data: &'a [u8],
     pub outer_index: usize,
 }
 
@@ -611,3 +820,106 @@ pub struct PoolEventBase {
     pub timestamp: i64,
     pub signature: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{ix, key, tx};
+    use crate::types::{AddressTableLookupInput, LoadedAddressesInput, MessageHeader};
+
+    fn header(signed: u8, readonly_signed: u8, readonly_unsigned: u8) -> Option<MessageHeader> {
+        Some(MessageHeader {
+            num_required_signatures: signed,
+            num_readonly_signed_accounts: readonly_signed,
+            num_readonly_unsigned_accounts: readonly_unsigned,
+        })
+    }
+
+    fn writable(input: &SolanaTransactionInput) -> Vec<bool> {
+        let adapter = TransactionAdapter::new(input, None);
+        (0..adapter.account_keys.len())
+            .map(|i| adapter.is_writable(i))
+            .collect()
+    }
+
+    #[test]
+    fn legacy_message_without_header_locks_all_but_programs() {
+        let input = tx(
+            (1..=4).map(key).collect(),
+            vec![ix(3, &[0, 1, 2], vec![])],
+            vec![],
+        );
+        assert_eq!(writable(&input), [true, true, true, false]);
+        let adapter = TransactionAdapter::new(&input, None);
+        assert_eq!(
+            adapter.account_key_source(3),
+            Some(AccountKeySource::Static)
+        );
+        assert_eq!(adapter.account_key_source(4), None);
+    }
+
+    #[test]
+    fn header_splits_signed_and_unsigned_ranges() {
+        // 0 signer-writable, 1 signer-readonly, 2-3 unsigned-writable, 4-5 unsigned-readonly
+        let mut input = tx((1..=6).map(key).collect(), vec![], vec![]);
+        input.header = header(2, 1, 2);
+        assert_eq!(writable(&input), [true, false, true, true, false, false]);
+        let adapter = TransactionAdapter::new(&input, None);
+        assert_eq!(
+            (0..6).map(|i| adapter.is_signer(i)).collect::<Vec<_>>(),
+            [true, true, false, false, false, false]
+        );
+    }
+
+    #[test]
+    fn header_claiming_more_signers_than_keys_is_clamped() {
+        let mut input = tx((1..=3).map(key).collect(), vec![], vec![]);
+        input.header = header(5, 1, 0);
+        let adapter = TransactionAdapter::new(&input, None);
+        assert_eq!(adapter.num_required_signatures(), 3);
+        assert_eq!(adapter.signer_keys().len(), 3);
+        assert_eq!(writable(&input), [true, true, false]);
+    }
+
+    #[test]
+    fn v0_message_resolves_lookup_table_ranges() {
+        let mut input = tx((1..=2).map(key).collect(), vec![], vec![]);
+        input.version = Some(0);
+        input.header = header(1, 0, 0);
+        input.address_table_lookups = Some(vec![
+            AddressTableLookupInput {
+                account_key: key(20),
+                writable_indexes: vec![4],
+                readonly_indexes: vec![7, 8],
+            },
+            AddressTableLookupInput {
+                account_key: key(21),
+                writable_indexes: vec![1],
+                readonly_indexes: vec![],
+            },
+        ]);
+        input.meta.as_mut().unwrap().loaded_addresses = Some(LoadedAddressesInput {
+            writable: vec![key(10), key(11)],
+            readonly: vec![key(12), key(13)],
+        });
+        let adapter = TransactionAdapter::new(&input, None);
+        assert_eq!(adapter.account_keys.len(), 6);
+        assert_eq!(writable(&input), [true, true, true, true, false, false]);
+        let lookup = |table: u8, index: u8, writable: bool| {
+            Some(AccountKeySource::LookupTable {
+                table: key(table),
+                index,
+                writable,
+            })
+        };
+        assert_eq!(
+            adapter.account_key_source(1),
+            Some(AccountKeySource::Static)
+        );
+        assert_eq!(adapter.account_key_source(2), lookup(20, 4, true));
+        assert_eq!(adapter.account_key_source(3), lookup(21, 1, true));
+        assert_eq!(adapter.account_key_source(4), lookup(20, 7, false));
+        assert_eq!(adapter.account_key_source(5), lookup(20, 8, false));
+        assert_eq!(adapter.account_key_source(6), None);
+    }
+}
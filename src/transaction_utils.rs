@@ -1,10 +1,16 @@
 //! Transaction utilities: DEX info, transfer actions, swap data.
 
-use crate::constants::{dex_programs, FEE_ACCOUNTS, SYSTEM_PROGRAMS};
+use crate::constants::{
+    dex_programs, spl_token_instruction, FEE_ACCOUNTS, SYSTEM_PROGRAMS, TOKEN_2022_PROGRAM,
+    TOKEN_PROGRAM,
+};
 use crate::instruction_classifier::InstructionClassifier;
+use crate::instruction_index::InstructionIndex;
+use crate::parsers::ParserRegistry;
 use crate::transaction_adapter::TransactionAdapter;
 use crate::types::{DexInfo, TransferData, TransferInfoInner};
 use crate::utils::convert_to_ui_amount;
+use solana_sdk::pubkey::Pubkey;
 use std::collections::HashMap;
 
 pub struct TransactionUtils<'a> {
@@ -16,85 +22,58 @@ impl<'a> TransactionUtils<'a> {
         Self { adapter }
     }
 
-    pub fn get_dex_info(&self, classifier: &InstructionClassifier<'a>) -> DexInfo {
+    /// Label the transaction by its first program that has a registered parser.
+    pub fn get_dex_info(
+        &self,
+        classifier: &InstructionClassifier<'a>,
+        registry: &ParserRegistry,
+    ) -> DexInfo {
         let program_ids = classifier.get_all_program_ids();
         if program_ids.is_empty() {
             return DexInfo::default();
         }
         for program_id in &program_ids {
-            let id = program_id.as_str();
-            if id == dex_programs::JUPITER.id {
-                return DexInfo {
-                    program_id: Some(program_id.clone()),
-                    route: Some(dex_programs::JUPITER.name.to_string()),
-                    amm: None,
-                };
-            }
-            if id == dex_programs::JUPITER_DCA.id {
-                return DexInfo {
-                    program_id: Some(program_id.clone()),
-                    route: Some(dex_programs::JUPITER_DCA.name.to_string()),
-                    amm: None,
-                };
-            }
-            if id == dex_programs::RAYDIUM_V4.id {
-                return DexInfo {
-                    program_id: Some(program_id.clone()),
-                    route: None,
-                    amm: Some(dex_programs::RAYDIUM_V4.name.to_string()),
-                };
-            }
-            if id == dex_programs::METEORA.id {
-                return DexInfo {
-                    program_id: Some(program_id.clone()),
-                    route: None,
-                    amm: Some(dex_programs::METEORA.name.to_string()),
-                };
-            }
-            if id == dex_programs::ORCA.id {
-                return DexInfo {
-                    program_id: Some(program_id.clone()),
-                    route: None,
-                    amm: Some(dex_programs::ORCA.name.to_string()),
-                };
-            }
-            if id == dex_programs::PUMP_FUN.id {
-                return DexInfo {
-                    program_id: Some(program_id.clone()),
-                    route: None,
-                    amm: Some(dex_programs::PUMP_FUN.name.to_string()),
-                };
-            }
-            if id == dex_programs::PUMP_SWAP.id {
-                return DexInfo {
-                    program_id: Some(program_id.clone()),
-                    route: None,
-                    amm: Some(dex_programs::PUMP_SWAP.name.to_string()),
-                };
+            if let Some(parser) = registry.get(program_id) {
+                return parser.dex_info(&program_id.to_string());
             }
         }
         DexInfo {
-            program_id: program_ids.first().cloned(),
+            program_id: program_ids.first().map(Pubkey::to_string),
             ..Default::default()
         }
     }
 
     /// Build transfer actions from inner and outer instructions (compiled SPL transfer/transferChecked only).
+    ///
+    /// Outer transfers are keyed by their own program and index. Inner transfers are keyed
+    /// by the DEX instruction that executed them (see [TransactionUtils::get_transfer_owner]),
+    /// so an AMM swap routed through an aggregator finds its own transfers.
     pub fn get_transfer_actions(
         &self,
+        classifier: &InstructionClassifier<'a>,
+        registry: &ParserRegistry,
         extra_types: &[&str],
-    ) -> HashMap<String, Vec<TransferData>> {
-        let mut actions: HashMap<String, Vec<TransferData>> = HashMap::new();
+    ) -> HashMap<(Pubkey, InstructionIndex), Vec<TransferData>> {
+        let mut actions: HashMap<(Pubkey, InstructionIndex), Vec<TransferData>> = HashMap::new();
         // Outer instructions
         for (outer_index, raw) in self.adapter.raw_instructions().iter().enumerate() {
-            let program_id = self.adapter.get_instruction_program_id(raw);
-            if SYSTEM_PROGRAMS.contains(&program_id.as_str()) {
+            let Some(program_id) = self.adapter.get_instruction_program_id(raw) else {
+                continue;
+            };
+            if SYSTEM_PROGRAMS.contains(&program_id) {
                 continue;
             }
-            let group_key = format!("{}:{}", program_id, outer_index);
-            if let Some(transfer) = self.parse_compiled_action(raw, &outer_index.to_string(), extra_types) {
+            let group_key = (program_id, InstructionIndex::outer(outer_index));
+            if let Some(transfer) =
+                self.parse_compiled_action(raw, InstructionIndex::outer(outer_index), extra_types)
+            {
                 let is_fee = FEE_ACCOUNTS.contains(&transfer.info.destination.as_str())
-                    || transfer.info.destination_owner.as_ref().map(|o| FEE_ACCOUNTS.contains(&o.as_str())).unwrap_or(false);
+                    || transfer
+                        .info
+                        .destination_owner
+                        .as_ref()
+                        .map(|o| FEE_ACCOUNTS.contains(&o.as_str()))
+                        .unwrap_or(false);
                 let mut t = transfer;
                 if is_fee {
                     t.is_fee = Some(true);
@@ -112,15 +91,18 @@ impl<'a> TransactionUtils<'a> {
                 .adapter
                 .raw_instructions()
                 .get(outer_index)
-                .map(|r| self.adapter.get_instruction_program_id(r))
+                .and_then(|r| self.adapter.get_instruction_program_id(r))
                 .unwrap_or_default();
             for (inner_index, raw) in set.instructions.iter().enumerate() {
-                let group_key = format!("{}:{}-{}", outer_program_id, outer_index, inner_index);
-                if let Some(transfer) = self.parse_compiled_action(
-                    raw,
-                    &format!("{}-{}", outer_index, inner_index),
-                    extra_types,
-                ) {
+                let idx = InstructionIndex::inner(outer_index, inner_index);
+                let group_key = Self::get_transfer_owner(classifier, registry, &idx)
+                    .and_then(|owner| {
+                        classifier
+                            .get_program_id(&owner)
+                            .map(|&program| (program, owner))
+                    })
+                    .unwrap_or((outer_program_id, idx));
+                if let Some(transfer) = self.parse_compiled_action(raw, idx, extra_types) {
                     let is_fee = FEE_ACCOUNTS.contains(&transfer.info.destination.as_str())
                         || transfer
                             .info
@@ -142,21 +124,17 @@ impl<'a> TransactionUtils<'a> {
     fn parse_compiled_action(
         &self,
         raw: &crate::types::RawInstruction,
-        idx: &str,
+        idx: InstructionIndex,
         _extra_types: &[&str],
     ) -> Option<TransferData> {
         let data = &raw.data;
         if data.is_empty() {
             return None;
         }
-        let program_id = self.adapter.get_instruction_program_id(raw);
-        let accounts: Vec<String> = raw
-            .account_key_indexes
-            .iter()
-            .filter_map(|&i| self.adapter.get_account_key(i as usize))
-            .collect();
-        match (program_id.as_str(), data[0]) {
-            (crate::constants::TOKEN_PROGRAM_ID, crate::constants::spl_token_instruction::TRANSFER) => {
+        let program_id = self.adapter.get_instruction_program_id(raw)?;
+        let accounts = self.adapter.instruction_accounts(raw);
+        match (program_id, data[0]) {
+            (TOKEN_PROGRAM, spl_token_instruction::TRANSFER) => {
                 if accounts.len() < 2 {
                     return None;
                 }
@@ -165,20 +143,35 @@ impl<'a> TransactionUtils<'a> {
                 } else {
                     return None;
                 };
-                let source = accounts[0].clone();
-                let destination = accounts[1].clone();
-                let token1 = self.adapter.spl_token_map.get(&destination).map(|t| t.mint.clone());
-                let token2 = self.adapter.spl_token_map.get(&source).map(|t| t.mint.clone());
-                let mint = crate::utils::get_transfer_token_mint(
-                    token1.as_deref(),
-                    token2.as_deref(),
-                )?;
+                let (source, destination) = (accounts[0], accounts[1]);
+                let token1 = self
+                    .adapter
+                    .spl_token_map
+                    .get(&destination)
+                    .map(|t| t.mint.clone());
+                let token2 = self
+                    .adapter
+                    .spl_token_map
+                    .get(&source)
+                    .map(|t| t.mint.clone());
+                let mint = self
+                    .adapter
+                    .quote_assets()
+                    .transfer_token_mint(token1.as_deref(), token2.as_deref())?;
                 let decimals = self.adapter.get_token_decimals(&mint);
                 let (sb, db, spb, dpb) = {
-                    let sb = self.adapter.get_token_account_balance(&[source.clone()]);
-                    let db = self.adapter.get_token_account_balance(&[destination.clone()]);
-                    let spb = self.adapter.get_token_account_pre_balance(&[source.clone()]);
-                    let dpb = self.adapter.get_token_account_pre_balance(&[destination.clone()]);
+                    let sb = self
+                        .adapter
+                        .get_token_account_balance(std::slice::from_ref(&source));
+                    let db = self
+                        .adapter
+                        .get_token_account_balance(std::slice::from_ref(&destination));
+                    let spb = self
+                        .adapter
+                        .get_token_account_pre_balance(std::slice::from_ref(&source));
+                    let dpb = self
+                        .adapter
+                        .get_token_account_pre_balance(std::slice::from_ref(&destination));
                     (
                         sb.into_iter().next().flatten(),
                         db.into_iter().next().flatten(),
@@ -188,13 +181,13 @@ impl<'a> TransactionUtils<'a> {
                 };
                 Some(TransferData {
                     transfer_type: "transfer".to_string(),
-                    program_id: program_id.clone(),
+                    program_id: program_id.to_string(),
                     info: TransferInfoInner {
-                        authority: accounts.get(2).cloned(),
-                        destination,
-                        destination_owner: self.adapter.get_token_account_owner(&accounts[1]),
-                        mint: mint.clone(),
-                        source: source.clone(),
+                        authority: accounts.get(2).map(Pubkey::to_string),
+                        destination: destination.to_string(),
+                        destination_owner: self.adapter.get_token_account_owner(&destination),
+                        mint,
+                        source: source.to_string(),
                         token_amount: crate::types::TokenAmount {
                             amount: amount.to_string(),
                             ui_amount: Some(convert_to_ui_amount(amount, decimals)),
@@ -205,27 +198,32 @@ impl<'a> TransactionUtils<'a> {
                         destination_balance: db,
                         destination_pre_balance: dpb,
                     },
-                    idx: idx.to_string(),
+                    idx,
                     timestamp: self.adapter.block_time(),
-                    signature: self.adapter.signature(),
+                    signature: self.adapter.signature().to_string(),
                     is_fee: None,
                 })
             }
-            (crate::constants::TOKEN_PROGRAM_ID, crate::constants::spl_token_instruction::TRANSFER_CHECKED)
-            | (crate::constants::TOKEN_2022_PROGRAM_ID, crate::constants::spl_token_instruction::TRANSFER_CHECKED) => {
+            (TOKEN_PROGRAM | TOKEN_2022_PROGRAM, spl_token_instruction::TRANSFER_CHECKED) => {
                 if accounts.len() < 3 || data.len() < 10 {
                     return None;
                 }
                 let amount = u64::from_le_bytes(data[1..9].try_into().ok()?);
                 let decimals = data[9];
-                let source = accounts[0].clone();
-                let mint = accounts[1].clone();
-                let destination = accounts[2].clone();
+                let (source, mint, destination) = (accounts[0], accounts[1], accounts[2]);
                 let (sb, db, spb, dpb) = {
-                    let sb = self.adapter.get_token_account_balance(&[source.clone()]);
-                    let db = self.adapter.get_token_account_balance(&[destination.clone()]);
-                    let spb = self.adapter.get_token_account_pre_balance(&[source.clone()]);
-                    let dpb = self.adapter.get_token_account_pre_balance(&[destination.clone()]);
+                    let sb = self
+                        .adapter
+                        .get_token_account_balance(std::slice::from_ref(&source));
+                    let db = self
+                        .adapter
+                        .get_token_account_balance(std::slice::from_ref(&destination));
+                    let spb = self
+                        .adapter
+                        .get_token_account_pre_balance(std::slice::from_ref(&source));
+                    let dpb = self
+                        .adapter
+                        .get_token_account_pre_balance(std::slice::from_ref(&destination));
                     (
                         sb.into_iter().next().flatten(),
                         db.into_iter().next().flatten(),
@@ -235,13 +233,13 @@ impl<'a> TransactionUtils<'a> {
                 };
                 Some(TransferData {
                     transfer_type: "transferChecked".to_string(),
-                    program_id,
+                    program_id: program_id.to_string(),
                     info: TransferInfoInner {
-                        authority: accounts.get(3).cloned(),
-                        destination,
-                        destination_owner: self.adapter.get_token_account_owner(&accounts[2]),
-                        mint,
-                        source,
+                        authority: accounts.get(3).map(Pubkey::to_string),
+                        destination: destination.to_string(),
+                        destination_owner: self.adapter.get_token_account_owner(&destination),
+                        mint: mint.to_string(),
+                        source: source.to_string(),
                         token_amount: crate::types::TokenAmount {
                             amount: amount.to_string(),
                             ui_amount: Some(convert_to_ui_amount(amount, decimals)),
@@ -252,9 +250,9 @@ impl<'a> TransactionUtils<'a> {
                         destination_balance: db,
                         destination_pre_balance: dpb,
                     },
-                    idx: idx.to_string(),
+                    idx,
                     timestamp: self.adapter.block_time(),
-                    signature: self.adapter.signature(),
+                    signature: self.adapter.signature().to_string(),
                     is_fee: None,
                 })
             }
@@ -262,24 +260,71 @@ impl<'a> TransactionUtils<'a> {
         }
     }
 
+    /// Instruction a transfer at `idx` is attributed to: its nearest ancestor handled by a
+    /// (non-route) registered parser, else its direct invoker, when stack heights give the
+    /// call tree. Without them, the closest preceding DEX instruction among its siblings,
+    /// else the outer instruction.
+    pub fn get_transfer_owner(
+        classifier: &InstructionClassifier<'a>,
+        registry: &ParserRegistry,
+        idx: &InstructionIndex,
+    ) -> Option<InstructionIndex> {
+        let is_dex = |i: &InstructionIndex| {
+            classifier
+                .get_program_id(i)
+                .and_then(|program| registry.get(program))
+                .is_some_and(|parser| !parser.is_route())
+        };
+        if let Some(invoker) = classifier.get_invoker(idx) {
+            let mut ancestor = Some(invoker);
+            while let Some(current) = ancestor {
+                if is_dex(&current) {
+                    return Some(current);
+                }
+                ancestor = classifier.get_invoker(&current);
+            }
+            return Some(invoker);
+        }
+        let inner = idx.inner?;
+        (0..inner)
+            .rev()
+            .map(|i| InstructionIndex::inner(idx.outer, i))
+            .find(is_dex)
+            .or(Some(InstructionIndex::outer(idx.outer)))
+    }
+
     /// Get transfers for a specific instruction (by program_id, outer_index, optional inner_index).
     pub fn get_transfers_for_instruction(
-        transfer_actions: &HashMap<String, Vec<TransferData>>,
-        program_id: &str,
+        transfer_actions: &HashMap<(Pubkey, InstructionIndex), Vec<TransferData>>,
+        program_id: &Pubkey,
         outer_index: usize,
         inner_index: Option<usize>,
     ) -> Vec<TransferData> {
-        let key = match inner_index {
-            Some(i) => format!("{}:{}-{}", program_id, outer_index, i),
-            None => format!("{}:{}", program_id, outer_index),
-        };
-        let transfers = transfer_actions.get(&key).cloned().unwrap_or_default();
-        transfers
+        let key = (*program_id, InstructionIndex::new(outer_index, inner_index));
+        transfer_actions
+            .get(&key)
             .into_iter()
+            .flatten()
             .filter(|t| matches!(t.transfer_type.as_str(), "transfer" | "transferChecked"))
+            .cloned()
             .collect()
     }
 
+    /// Get every transfer executed by an outer instruction, including those from its inner instructions, in idx order.
+    pub fn get_transfers_for_outer_instruction(
+        transfer_actions: &HashMap<(Pubkey, InstructionIndex), Vec<TransferData>>,
+        outer_index: usize,
+    ) -> Vec<TransferData> {
+        let transfers: Vec<(TransferData, InstructionIndex)> = transfer_actions
+            .values()
+            .flatten()
+            .filter(|t| t.idx.outer == outer_index)
+            .filter(|t| matches!(t.transfer_type.as_str(), "transfer" | "transferChecked"))
+            .map(|t| (t.clone(), t.idx))
+            .collect();
+        crate::utils::sort_by_idx(&transfers)
+    }
+
     /// Build TradeInfo from transfer list (swap: 2+ tokens, determine in/out by signer).
     pub fn process_swap_data(
         &self,
@@ -305,12 +350,19 @@ impl<'a> TransactionUtils<'a> {
         if unique_mints.len() < 2 {
             return None;
         }
-        let signer = self.get_swap_signer();
-        let (input_mint, output_mint, input_raw, output_raw, fee) =
-            self.sum_token_amounts(transfers, &unique_mints[0], &unique_mints[unique_mints.len() - 1], &signer)?;
-        let in_dec = self.adapter.get_token_decimals(&input_mint);
-        let out_dec = self.adapter.get_token_decimals(&output_mint);
-        let trade_type = crate::utils::get_trade_type(&input_mint, &output_mint);
+        let signer = self.get_swap_signer(transfers, &unique_mints[0]);
+        let (input_mint, output_mint, input_raw, output_raw, fee) = self.sum_token_amounts(
+            transfers,
+            &unique_mints[0],
+            &unique_mints[unique_mints.len() - 1],
+            &signer,
+        )?;
+        let (in_dec, in_defaulted) = self.adapter.resolve_token_decimals(&input_mint, 0);
+        let (out_dec, out_defaulted) = self.adapter.resolve_token_decimals(&output_mint, 0);
+        let trade_type = self
+            .adapter
+            .quote_assets()
+            .trade_type(&input_mint, &output_mint);
         let mut trade = crate::types::TradeInfo {
             user: signer.clone(),
             trade_type,
@@ -324,6 +376,9 @@ impl<'a> TransactionUtils<'a> {
                 destination: None,
                 destination_owner: None,
                 source: None,
+                decimals_defaulted: in_defaulted,
+                symbol: None,
+                name: None,
             },
             output_token: crate::types::TokenInfo {
                 mint: output_mint.clone(),
@@ -334,6 +389,9 @@ impl<'a> TransactionUtils<'a> {
                 destination: None,
                 destination_owner: None,
                 source: None,
+                decimals_defaulted: out_defaulted,
+                symbol: None,
+                name: None,
             },
             slippage_bps: None,
             fee: None,
@@ -344,9 +402,12 @@ impl<'a> TransactionUtils<'a> {
             route: dex_info.route.clone(),
             slot: self.adapter.slot(),
             timestamp: self.adapter.block_time(),
-            signature: self.adapter.signature(),
-            idx: transfers.first().map(|t| t.idx.clone()).unwrap_or_default(),
-            signer: Some(self.adapter.signers()),
+            signature: self.adapter.signature().to_string(),
+            idx: transfers.first().map(|t| t.idx).unwrap_or_default(),
+            signer: Some(self.adapter.signers().to_vec()),
+            valuation: None,
+            legs: None,
+            circular: false,
         };
         if let Some(fee_transfer) = fee {
             trade.fee = Some(crate::types::FeeInfo {
@@ -362,11 +423,22 @@ impl<'a> TransactionUtils<'a> {
         Some(trade)
     }
 
-    fn get_swap_signer(&self) -> String {
-        if self.adapter.account_keys.contains(&dex_programs::JUPITER_DCA.id.to_string()) {
-            self.adapter.get_account_key(2).unwrap_or_else(|| self.adapter.signer())
-        } else {
-            self.adapter.signer()
+    /// The DCA owner for Jupiter DCA fills, else the signer that sent the input token.
+    pub(crate) fn get_swap_signer(&self, transfers: &[TransferData], input_mint: &str) -> String {
+        if self
+            .adapter
+            .contains_account(&dex_programs::JUPITER_DCA.key)
+        {
+            return self
+                .adapter
+                .get_account_key(2)
+                .unwrap_or_else(|| self.adapter.signer());
+        }
+        match transfers.iter().find(|t| t.info.mint == input_mint) {
+            Some(t) => self
+                .adapter
+                .source_owner_signer(&t.info.source, t.info.authority.as_deref()),
+            None => self.adapter.signer(),
         }
     }
 
@@ -383,7 +455,9 @@ impl<'a> TransactionUtils<'a> {
         let mut seen = std::collections::HashSet::new();
         for t in transfers {
             let dest_owner = t.info.destination_owner.as_deref().unwrap_or("");
-            if FEE_ACCOUNTS.contains(&t.info.destination.as_str()) || FEE_ACCOUNTS.contains(&dest_owner) {
+            if FEE_ACCOUNTS.contains(&t.info.destination.as_str())
+                || FEE_ACCOUNTS.contains(&dest_owner)
+            {
                 fee_transfer = Some(t.clone());
                 continue;
             }
@@ -408,3 +482,105 @@ impl<'a> TransactionUtils<'a> {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TOKEN_PROGRAM_ID;
+    use crate::test_support::{cpi, ix, key, spl_transfer, token_balance, tx};
+    use crate::types::{InnerInstructionSet, RawInstruction, SolanaTransactionInput};
+
+    /// Keys: 0 payer, 1-2 token accounts, 3 Jupiter, 4 Raydium V4, 5 Token, 6 Orca.
+    fn routed_tx(inner: Vec<RawInstruction>) -> SolanaTransactionInput {
+        let mut keys: Vec<String> = (1..=3).map(key).collect();
+        keys.extend(
+            [
+                dex_programs::JUPITER.id,
+                dex_programs::RAYDIUM_V4.id,
+                TOKEN_PROGRAM_ID,
+                dex_programs::ORCA.id,
+            ]
+            .map(String::from),
+        );
+        let mut input = tx(
+            keys,
+            vec![ix(3, &[], vec![])],
+            vec![InnerInstructionSet {
+                index: 0,
+                instructions: inner,
+            }],
+        );
+        input.meta.as_mut().unwrap().post_token_balances = Some(vec![
+            token_balance(1, &key(9), &key(1), 0, 0),
+            token_balance(2, &key(9), &key(8), 0, 0),
+        ]);
+        input
+    }
+
+    fn owners(input: &SolanaTransactionInput, idxs: &[&str]) -> Vec<Option<String>> {
+        let adapter = TransactionAdapter::new(input, None);
+        let classifier = InstructionClassifier::new(&adapter);
+        let registry = ParserRegistry::with_builtins();
+        idxs.iter()
+            .map(|idx| {
+                TransactionUtils::get_transfer_owner(&classifier, &registry, &idx.parse().unwrap())
+                    .map(|owner| owner.to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn routed_transfers_go_to_the_nearest_dex_ancestor() {
+        // Jupiter (0) invokes Raydium (0-0), whose vault transfers sit a level deeper
+        // (0-1 directly, 0-3 under a nested token CPI 0-2); 0-4 is Jupiter's own transfer.
+        let input = routed_tx(vec![
+            cpi(4, &[], vec![], 2),
+            cpi(5, &[1, 2, 0], spl_transfer(10), 3),
+            cpi(5, &[], vec![], 3),
+            cpi(5, &[2, 1, 0], spl_transfer(20), 4),
+            cpi(5, &[1, 2, 0], spl_transfer(30), 2),
+        ]);
+        let owners = owners(&input, &["0-1", "0-3", "0-4"]);
+        assert_eq!(
+            owners,
+            [Some("0-0".into()), Some("0-0".into()), Some("0".into())]
+        );
+
+        let adapter = TransactionAdapter::new(&input, None);
+        let classifier = InstructionClassifier::new(&adapter);
+        let registry = ParserRegistry::with_builtins();
+        let utils = TransactionUtils::new(&adapter);
+        let actions = utils.get_transfer_actions(&classifier, &registry, &[]);
+        let amounts = |program: Pubkey, idx: &str| -> Vec<String> {
+            actions
+                .get(&(program, idx.parse().unwrap()))
+                .into_iter()
+                .flatten()
+                .map(|t| t.info.token_amount.amount.clone())
+                .collect()
+        };
+        assert_eq!(amounts(dex_programs::RAYDIUM_V4.key, "0-0"), ["10", "20"]);
+        assert_eq!(amounts(dex_programs::JUPITER.key, "0"), ["30"]);
+    }
+
+    #[test]
+    fn without_stack_heights_transfers_go_to_the_preceding_dex_sibling() {
+        let input = routed_tx(vec![
+            ix(5, &[1, 2, 0], spl_transfer(5)),
+            ix(4, &[], vec![]),
+            ix(5, &[1, 2, 0], spl_transfer(10)),
+            ix(6, &[], vec![]),
+            ix(5, &[2, 1, 0], spl_transfer(20)),
+        ]);
+        let owners = owners(&input, &["0-0", "0-2", "0-4", "0"]);
+        assert_eq!(
+            owners,
+            [
+                Some("0".into()),
+                Some("0-1".into()),
+                Some("0-3".into()),
+                None
+            ]
+        );
+    }
+}
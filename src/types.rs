@@ -1,6 +1,8 @@
 //! Types for trades, pools, transfers, and parse results.
 
+use crate::instruction_index::InstructionIndex;
 use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenAmount {
@@ -19,6 +21,15 @@ pub struct TokenInfo {
     pub destination: Option<String>,
     pub destination_owner: Option<String>,
     pub source: Option<String>,
+    /// `decimals` is an assumed default: the mint appeared in no token balance or
+    /// transfer and is not in [ParseConfig::mint_decimals].
+    #[serde(default)]
+    pub decimals_defaulted: bool,
+    /// From [ParseConfig::token_metadata] when [ParseConfig::enrich_token_metadata] is set.
+    #[serde(default)]
+    pub symbol: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,7 +89,7 @@ pub struct TransferData {
     pub transfer_type: String, // "transfer" | "transferChecked" | etc
     pub program_id: String,
     pub info: TransferInfoInner,
-    pub idx: String,
+    pub idx: InstructionIndex,
     pub timestamp: i64,
     pub signature: String,
     pub is_fee: Option<bool>,
@@ -112,8 +123,28 @@ pub struct TradeInfo {
     pub slot: u64,
     pub timestamp: i64,
     pub signature: String,
-    pub idx: String,
+    pub idx: InstructionIndex,
     pub signer: Option<Vec<String>>,
+    /// Base/quote orientation, execution price and USD value.
+    #[serde(default)]
+    pub valuation: Option<crate::valuation::TradeValuation>,
+    /// Hops of an aggregated multi-hop swap, in execution order.
+    #[serde(default)]
+    pub legs: Option<Vec<RouteLeg>>,
+    /// The route starts and ends in the same mint (arbitrage).
+    #[serde(default)]
+    pub circular: bool,
+}
+
+/// One hop of a multi-hop swap.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteLeg {
+    pub idx: InstructionIndex,
+    pub program_id: Option<String>,
+    pub amm: Option<String>,
+    pub pool: Vec<String>,
+    pub input_token: TokenInfo,
+    pub output_token: TokenInfo,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -125,7 +156,7 @@ pub struct PoolEvent {
     pub slot: u64,
     pub timestamp: i64,
     pub signature: String,
-    pub idx: String,
+    pub idx: InstructionIndex,
     pub signer: Option<Vec<String>>,
     pub pool_id: String,
     pub config: Option<String>,
@@ -148,7 +179,7 @@ pub struct PoolEvent {
 pub struct MemeEvent {
     pub event_type: TradeType,
     pub timestamp: i64,
-    pub idx: String,
+    pub idx: InstructionIndex,
     pub slot: u64,
     pub signature: String,
     pub user: String,
@@ -168,20 +199,52 @@ pub struct MemeEvent {
     pub bonding_curve: Option<String>,
     pub pool: Option<String>,
     pub protocol: Option<String>,
+    /// Bonding curve state after the trade (raw units).
+    #[serde(default)]
+    pub virtual_sol_reserves: Option<u64>,
+    #[serde(default)]
+    pub virtual_token_reserves: Option<u64>,
+    #[serde(default)]
+    pub real_sol_reserves: Option<u64>,
+    #[serde(default)]
+    pub real_token_reserves: Option<u64>,
+    /// Quote per base token implied by the virtual reserves after the trade.
+    #[serde(default)]
+    pub price: Option<f64>,
+    /// Bonding curve completion in `[0, 1]`.
+    #[serde(default)]
+    pub bonding_curve_progress: Option<f64>,
+    #[serde(default)]
+    pub fee_recipient: Option<String>,
+    #[serde(default)]
+    pub fee_basis_points: Option<u64>,
+    #[serde(default)]
+    pub creator_fee_basis_points: Option<u64>,
+    #[serde(default)]
+    pub creator_fee: Option<f64>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ClassifiedInstruction {
     pub instruction: ParsedInstruction,
-    pub program_id: String,
+    pub program_id: Pubkey,
     pub outer_index: usize,
     pub inner_index: Option<usize>,
+    /// 1 for outer instructions; for inner ones as reported by the input, if at all.
+    pub stack_height: Option<u32>,
+}
+
+impl ClassifiedInstruction {
+    pub fn idx(&self) -> InstructionIndex {
+        InstructionIndex::new(self.outer_index, self.inner_index)
+            .with_stack_height(self.stack_height)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ParsedInstruction {
-    pub program_id: String,
-    pub accounts: Vec<String>,
+    pub program_id: Pubkey,
+    pub accounts: Vec<Pubkey>,
     pub data: Vec<u8>,
     pub parsed: Option<serde_json::Value>,
 }
@@ -210,23 +273,318 @@ pub struct ParseResult {
     pub tx_status: TransactionStatus,
     pub msg: Option<String>,
     pub sol_balance_change: Option<BalanceChange>,
-    pub token_balance_change: Option<std::collections::HashMap<String, BalanceChange>>,
+    /// Signer's token balance changes by mint, in mint order.
+    pub token_balance_change: Option<std::collections::BTreeMap<String, BalanceChange>>,
+    #[serde(default)]
+    pub diagnostics: Vec<ParseDiagnostic>,
+    /// Base vs priority fee and requested vs consumed compute units.
+    #[serde(default)]
+    pub fee_breakdown: crate::compute_budget::FeeBreakdown,
+    /// SOL tips paid by the signers to block engines and relayers.
+    #[serde(default)]
+    pub tips: Vec<crate::tips::Tip>,
+    /// Set when the swaps form a circular route (see [crate::arbitrage]).
+    #[serde(default)]
+    pub arbitrage: Option<crate::arbitrage::Arbitrage>,
+    /// Set by [crate::DexParser::parse_block].
+    #[serde(default)]
+    pub block: Option<BlockMeta>,
+}
+
+/// The block a batch of transactions belongs to, see [crate::DexParser::parse_block].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockInfo {
+    pub slot: u64,
+    pub blockhash: String,
+    pub parent_slot: u64,
+}
+
+/// A parsed transaction's block and its position in it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockMeta {
+    pub slot: u64,
+    pub blockhash: String,
+    pub parent_slot: u64,
+    /// Index of the transaction within the block.
+    pub tx_position: usize,
+}
+
+impl BlockInfo {
+    pub fn at(&self, tx_position: usize) -> BlockMeta {
+        BlockMeta {
+            slot: self.slot,
+            blockhash: self.blockhash.clone(),
+            parent_slot: self.parent_slot,
+            tx_position,
+        }
+    }
+}
+
+/// Non-fatal error recorded while parsing (see [ParseConfig::throw_error]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseDiagnostic {
+    pub program_id: Option<String>,
+    pub idx: Option<InstructionIndex>,
+    pub message: String,
+}
+
+/// What a protocol parser produced for its instructions: trades plus decode errors
+/// that did not stop the rest of the transaction from being parsed.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOutput {
+    pub trades: Vec<TradeInfo>,
+    pub meme_events: Vec<MemeEvent>,
+    pub errors: Vec<crate::error::ParseError>,
+}
+
+/// Swap parameters decoded from instruction data alone (pre-execution, no meta).
+/// Mints are only known when the instruction names them or the token account
+/// can be resolved from this transaction.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SwapIntent {
+    pub program_id: String,
+    pub amm: String,
+    pub user: String,
+    pub pool: Option<String>,
+    pub in_mint: Option<String>,
+    pub out_mint: Option<String>,
+    pub amount_in: Option<u64>,
+    pub amount_out: Option<u64>,
+    pub min_out: Option<u64>,
+    pub max_in: Option<u64>,
+    pub slippage_bps: Option<u16>,
+    pub idx: InstructionIndex,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum JupiterInstruction {
+    Route(SwapIntent),
+    SharedAccountsRoute(SwapIntent),
+    ExactOutRoute(SwapIntent),
+    Other { discriminator: Vec<u8> },
+}
+
+/// Raydium V4/AMM `swap_base_in`/`swap_base_out` and CPMM `swap_base_input`/`swap_base_output`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RaydiumInstruction {
+    SwapBaseIn(SwapIntent),
+    SwapBaseOut(SwapIntent),
+    Other { discriminator: Vec<u8> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrcaInstruction {
+    Swap(SwapIntent),
+    SwapV2(SwapIntent),
+    Other { discriminator: Vec<u8> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MeteoraInstruction {
+    Swap(SwapIntent),
+    Swap2(SwapIntent),
+    SwapExactOut(SwapIntent),
+    Other { discriminator: Vec<u8> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PumpfunInstruction {
+    Buy(SwapIntent),
+    Sell(SwapIntent),
+    Other { discriminator: Vec<u8> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PumpswapInstruction {
+    Buy(SwapIntent),
+    Sell(SwapIntent),
+    Other { discriminator: Vec<u8> },
+}
+
+/// Instruction decoded by protocol. Serialized as `{ "protocol": ..., "instruction": ... }`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "protocol", content = "instruction")]
+pub enum DecodedInstruction {
+    Jupiter(JupiterInstruction),
+    Raydium(RaydiumInstruction),
+    Orca(OrcaInstruction),
+    Meteora(MeteoraInstruction),
+    Pumpfun(PumpfunInstruction),
+    Pumpswap(PumpswapInstruction),
+    Unknown { discriminator: Vec<u8> },
+}
+
+impl DecodedInstruction {
+    /// The swap intent carried by this instruction, if it is a swap.
+    pub fn swap_intent(&self) -> Option<&SwapIntent> {
+        match self {
+            DecodedInstruction::Jupiter(
+                JupiterInstruction::Route(i)
+                | JupiterInstruction::SharedAccountsRoute(i)
+                | JupiterInstruction::ExactOutRoute(i),
+            )
+            | DecodedInstruction::Raydium(
+                RaydiumInstruction::SwapBaseIn(i) | RaydiumInstruction::SwapBaseOut(i),
+            )
+            | DecodedInstruction::Orca(OrcaInstruction::Swap(i) | OrcaInstruction::SwapV2(i))
+            | DecodedInstruction::Meteora(
+                MeteoraInstruction::Swap(i)
+                | MeteoraInstruction::Swap2(i)
+                | MeteoraInstruction::SwapExactOut(i),
+            )
+            | DecodedInstruction::Pumpfun(
+                PumpfunInstruction::Buy(i) | PumpfunInstruction::Sell(i),
+            )
+            | DecodedInstruction::Pumpswap(
+                PumpswapInstruction::Buy(i) | PumpswapInstruction::Sell(i),
+            ) => Some(i),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShredInstruction {
+    pub program_id: String,
+    pub outer_index: usize,
+    pub inner_index: Option<usize>,
+    pub decoded: DecodedInstruction,
+}
+
+/// Decoded instructions keyed by program id, in order of first appearance.
+/// Serializes as a JSON object whose keys keep that order.
+#[derive(Debug, Clone, Default)]
+pub struct ProgramInstructions {
+    entries: Vec<(String, Vec<ShredInstruction>)>,
+}
+
+impl ProgramInstructions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an instruction under its program id, creating the entry on first use.
+    pub fn push(&mut self, instruction: ShredInstruction) {
+        match self
+            .entries
+            .iter_mut()
+            .find(|(id, _)| *id == instruction.program_id)
+        {
+            Some((_, list)) => list.push(instruction),
+            None => self
+                .entries
+                .push((instruction.program_id.clone(), vec![instruction])),
+        }
+    }
+
+    pub fn get(&self, program_id: &str) -> Option<&[ShredInstruction]> {
+        self.entries
+            .iter()
+            .find(|(id, _)| id == program_id)
+            .map(|(_, list)| list.as_slice())
+    }
+
+    pub fn program_ids(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|(id, _)| id.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[ShredInstruction])> {
+        self.entries
+            .iter()
+            .map(|(id, list)| (id.as_str(), list.as_slice()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Serialize for ProgramInstructions {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(Some(self.entries.len()))?;
+        for (program_id, list) in &self.entries {
+            map.serialize_entry(program_id, list)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for ProgramInstructions {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntriesVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for EntriesVisitor {
+            type Value = ProgramInstructions;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a map of program id to decoded instructions")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut access: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut entries = Vec::new();
+                while let Some((program_id, list)) =
+                    access.next_entry::<String, Vec<ShredInstruction>>()?
+                {
+                    entries.push((program_id, list));
+                }
+                Ok(ProgramInstructions { entries })
+            }
+        }
+
+        deserializer.deserialize_map(EntriesVisitor)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParseShredResult {
     pub state: bool,
     pub signature: String,
-    pub instructions: std::collections::HashMap<String, Vec<serde_json::Value>>,
+    pub instructions: ProgramInstructions,
+    #[serde(default)]
+    pub swap_intents: Vec<SwapIntent>,
     pub msg: Option<String>,
 }
 
 /// Configuration for parsing
 #[derive(Debug, Clone, Default)]
 pub struct ParseConfig {
+    /// Ranked quote mints for Buy/Sell classification and base/quote orientation.
+    pub quote_assets: crate::quote_assets::QuoteAssets,
+    /// Decimals for mints the transaction itself doesn't reveal (e.g. Token-2022 launches
+    /// with no balance entries), consulted before falling back to a default.
+    pub mint_decimals: Option<std::collections::HashMap<String, u8>>,
+    /// Mint metadata source consulted after [ParseConfig::mint_decimals]. Overrides the
+    /// provider set on [crate::DexParserBuilder::token_metadata].
+    pub token_metadata: Option<std::sync::Arc<dyn crate::token_metadata::TokenMetadataProvider>>,
+    /// Fill [TokenInfo::symbol] and [TokenInfo::name] of trades and meme events from
+    /// [ParseConfig::token_metadata].
+    pub enrich_token_metadata: bool,
+    /// USD prices for trade valuation. Without one, only stable- and SOL-quoted trades get
+    /// a USD value (SOL priced from the transaction's own SOL/stable trades). Overrides the
+    /// source set on [crate::DexParserBuilder::price_source].
+    pub price_source: Option<std::sync::Arc<dyn crate::valuation::PriceSource>>,
+    /// Tip accounts reported in [ParseResult::tips], by provider.
+    pub tip_accounts: crate::tips::TipAccounts,
+    /// Infer swaps from token transfers for programs without a dedicated parser.
     pub try_unknown_dex: bool,
     pub program_ids: Option<Vec<String>>,
     pub ignore_program_ids: Option<Vec<String>>,
+    /// Fail on the first instruction decode error instead of recording it in
+    /// [ParseResult::diagnostics] and moving on.
     pub throw_error: bool,
     pub aggregate_trades: bool,
 }
@@ -238,17 +596,33 @@ pub struct SolanaTransactionInput {
     pub block_time: Option<i64>,
     pub version: Option<u8>,
     pub signatures: Vec<Vec<u8>>,
+    /// Message header. Without it, the first `signatures.len()` account keys are the signers.
+    pub header: Option<MessageHeader>,
     pub account_keys: Vec<String>,
+    /// Address lookup tables of a v0 message, in message order. Their loaded addresses
+    /// are `meta.loaded_addresses`.
+    pub address_table_lookups: Option<Vec<AddressTableLookupInput>>,
     pub instructions: Vec<RawInstruction>,
     pub inner_instructions: Option<Vec<InnerInstructionSet>>,
     pub meta: Option<TransactionMetaInput>,
 }
 
+/// Counts that split the static account keys into signed/unsigned and writable/readonly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MessageHeader {
+    pub num_required_signatures: u8,
+    pub num_readonly_signed_accounts: u8,
+    pub num_readonly_unsigned_accounts: u8,
+}
+
 #[derive(Debug, Clone)]
 pub struct RawInstruction {
     pub program_id_index: u8,
     pub data: Vec<u8>,
     pub account_key_indexes: Vec<u8>,
+    /// Invocation depth reported for inner instructions (`stackHeight`): 2 for a CPI made
+    /// by the outer instruction, 3 for a CPI made by that one, ... `None` when unknown.
+    pub stack_height: Option<u32>,
 }
 
 #[derive(Debug, Clone)]
@@ -291,3 +665,11 @@ pub struct LoadedAddressesInput {
     pub writable: Vec<String>,
     pub readonly: Vec<String>,
 }
+
+#[derive(Debug, Clone)]
+pub struct AddressTableLookupInput {
+    /// Address of the lookup table account.
+    pub account_key: String,
+    pub writable_indexes: Vec<u8>,
+    pub readonly_indexes: Vec<u8>,
+}
@@ -1,8 +1,10 @@
 //! Utility functions for parsing and conversion.
 
-use crate::constants::tokens;
+use crate::instruction_index::InstructionIndex;
+use crate::quote_assets::QuoteAssets;
 use crate::types::TradeType;
 use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
 
 pub fn decode_instruction_data(data: &[u8]) -> Vec<u8> {
     data.to_vec()
@@ -12,6 +14,11 @@ pub fn decode_instruction_data_base58(data: &str) -> Result<Vec<u8>, bs58::decod
     bs58::decode(data).into_vec()
 }
 
+/// Leading instruction data bytes (up to 8), used to report instructions no decoder recognized.
+pub fn get_instruction_discriminator(data: &[u8]) -> Vec<u8> {
+    data[..data.len().min(8)].to_vec()
+}
+
 pub fn get_pubkey_string(value: &[u8; 32]) -> String {
     bs58::encode(value).into_string()
 }
@@ -34,101 +41,115 @@ pub fn convert_to_ui_amount_u128(amount: u128, decimals: u8) -> f64 {
     amount as f64 / 10_f64.powi(decimals as i32)
 }
 
+/// Buy/Sell of `in_mint` -> `out_mint` under the default [QuoteAssets].
 pub fn get_trade_type(in_mint: &str, out_mint: &str) -> TradeType {
-    if in_mint == tokens::SOL {
-        return TradeType::Buy;
-    }
-    if out_mint == tokens::SOL {
-        return TradeType::Sell;
-    }
-    if is_known_stable(in_mint) {
-        return TradeType::Buy;
-    }
-    TradeType::Sell
-}
-
-fn is_known_stable(mint: &str) -> bool {
-    matches!(
-        mint,
-        tokens::USDC | tokens::USDT | tokens::USD1 | tokens::USDG | tokens::PYUSD
-            | tokens::EURC | tokens::USDY | tokens::FDUSD
-    )
+    QuoteAssets::shared().trade_type(in_mint, out_mint)
 }
 
+/// See [QuoteAssets::transfer_token_mint]; uses the default ranking.
 pub fn get_transfer_token_mint(token1: Option<&str>, token2: Option<&str>) -> Option<String> {
-    match (token1, token2) {
-        (Some(a), Some(b)) if a == b => Some(a.to_string()),
-        (Some(a), _) if a != tokens::SOL => Some(a.to_string()),
-        (_, Some(b)) if b != tokens::SOL => Some(b.to_string()),
-        (a, b) => a.or(b).map(String::from),
-    }
+    QuoteAssets::shared().transfer_token_mint(token1, token2)
 }
 
 #[derive(Clone)]
 pub struct IdxSortable<T> {
     pub item: T,
-    pub idx: String,
+    pub idx: InstructionIndex,
 }
 
-pub fn sort_by_idx<T: Clone>(items: &[(T, String)]) -> Vec<T> {
-    if items.len() <= 1 {
-        return items.iter().map(|(t, _)| t.clone()).collect();
-    }
-    let mut with_idx: Vec<_> = items
-        .iter()
-        .map(|(t, idx)| (t.clone(), idx.clone()))
-        .collect();
-    with_idx.sort_by(|a, b| {
-        let pa: Vec<&str> = a.1.split('-').collect();
-        let pb: Vec<&str> = b.1.split('-').collect();
-        let a_main: u32 = pa.get(0).and_then(|s| s.parse().ok()).unwrap_or(0);
-        let a_sub: u32 = pa.get(1).and_then(|s| s.parse().ok()).unwrap_or(0);
-        let b_main: u32 = pb.get(0).and_then(|s| s.parse().ok()).unwrap_or(0);
-        let b_sub: u32 = pb.get(1).and_then(|s| s.parse().ok()).unwrap_or(0);
-        a_main.cmp(&b_main).then(a_sub.cmp(&b_sub))
-    });
+pub fn sort_by_idx<T: Clone>(items: &[(T, InstructionIndex)]) -> Vec<T> {
+    let mut with_idx = items.to_vec();
+    with_idx.sort_by_key(|(_, idx)| *idx);
     with_idx.into_iter().map(|(t, _)| t).collect()
 }
 
+/// Collapse the hops of a multi-hop swap into one trade from the route's start mint to
+/// the last hop's output mint.
+///
+/// Only the first hop's `user` is considered. Its hops must form one route in execution
+/// order: each hop spends the start mint or a mint an earlier hop produced, so paths may
+/// split and merge again. Unrelated swaps yield `None`. Amounts are the net flow of the
+/// start and end mints across all hops. When they coincide the route is `circular` and
+/// the amounts are the total spent and received. `fees` collects every hop's fees: its
+/// `fees` when set, which already include its `fee`, otherwise its `fee`. A single trade
+/// is a route of one leg.
 pub fn get_final_swap(
     trades: &[crate::types::TradeInfo],
     dex_amm: Option<&str>,
     dex_route: Option<&str>,
+    quote_assets: &QuoteAssets,
 ) -> Option<crate::types::TradeInfo> {
     if trades.is_empty() {
         return None;
     }
-    if trades.len() == 1 {
-        return Some(trades[0].clone());
+    let mut sorted: Vec<&crate::types::TradeInfo> = trades.iter().collect();
+    sorted.sort_by_key(|t| t.idx);
+    let user = sorted[0].user.as_str();
+    sorted.retain(|t| t.user == user);
+    if !is_connected_route(&sorted) {
+        return None;
     }
-    let sorted = {
-        let with_idx: Vec<_> = trades.iter().map(|t| (t.clone(), t.idx.clone())).collect();
-        sort_by_idx(&with_idx)
-    };
     let input_trade = sorted.first()?;
     let output_trade = sorted.last()?;
+    let in_mint = input_trade.input_token.mint.as_str();
+    let out_mint = output_trade.output_token.mint.as_str();
+    let circular = in_mint == out_mint;
+
+    let raw = |t: &crate::types::TokenInfo| t.amount_raw.parse::<u128>().unwrap_or(0);
+    let (mut spent, mut received): (HashMap<&str, u128>, HashMap<&str, u128>) = Default::default();
     let mut pools: Vec<String> = Vec::new();
-    let mut input_amount: u128 = 0;
-    let mut output_amount: u128 = 0;
+    let mut amms: Vec<String> = Vec::new();
+    let mut fees: Vec<crate::types::FeeInfo> = Vec::new();
     for trade in &sorted {
-        if trade.input_token.mint == input_trade.input_token.mint {
-            input_amount += trade.input_token.amount_raw.parse::<u128>().unwrap_or(0);
-        }
-        if trade.output_token.mint == output_trade.output_token.mint {
-            output_amount += trade.output_token.amount_raw.parse::<u128>().unwrap_or(0);
-        }
-        if let Some(p) = trade.pool.first() {
+        *spent.entry(trade.input_token.mint.as_str()).or_default() += raw(&trade.input_token);
+        *received
+            .entry(trade.output_token.mint.as_str())
+            .or_default() += raw(&trade.output_token);
+        for p in &trade.pool {
             if !pools.contains(p) {
                 pools.push(p.clone());
             }
         }
+        if let Some(amm) = &trade.amm {
+            if !amms.contains(amm) {
+                amms.push(amm.clone());
+            }
+        }
+        match &trade.fees {
+            Some(hop_fees) => fees.extend(hop_fees.iter().cloned()),
+            None => fees.extend(trade.fee.iter().cloned()),
+        }
     }
+    let spent_of = |mint: &str| spent.get(mint).copied().unwrap_or(0);
+    let received_of = |mint: &str| received.get(mint).copied().unwrap_or(0);
+    let (input_amount, output_amount) = if circular {
+        (spent_of(in_mint), received_of(out_mint))
+    } else {
+        (
+            spent_of(in_mint).saturating_sub(received_of(in_mint)),
+            received_of(out_mint).saturating_sub(spent_of(out_mint)),
+        )
+    };
+    let legs = sorted
+        .iter()
+        .map(|t| crate::types::RouteLeg {
+            idx: t.idx,
+            program_id: t.program_id.clone(),
+            amm: t.amm.clone(),
+            pool: t.pool.clone(),
+            input_token: t.input_token.clone(),
+            output_token: t.output_token.clone(),
+        })
+        .collect();
     Some(crate::types::TradeInfo {
         user: input_trade.user.clone(),
-        trade_type: get_trade_type(&input_trade.input_token.mint, &output_trade.output_token.mint),
+        trade_type: quote_assets.trade_type(
+            &input_trade.input_token.mint,
+            &output_trade.output_token.mint,
+        ),
         pool: pools,
         input_token: crate::types::TokenInfo {
-            mint: input_trade.input_token.mint.clone(),
+            mint: in_mint.to_string(),
             amount: crate::utils::convert_to_ui_amount_u128(
                 input_amount,
                 input_trade.input_token.decimals,
@@ -139,9 +160,12 @@ pub fn get_final_swap(
             destination: input_trade.input_token.destination.clone(),
             destination_owner: input_trade.input_token.destination_owner.clone(),
             source: input_trade.input_token.source.clone(),
+            decimals_defaulted: input_trade.input_token.decimals_defaulted,
+            symbol: input_trade.input_token.symbol.clone(),
+            name: input_trade.input_token.name.clone(),
         },
         output_token: crate::types::TokenInfo {
-            mint: output_trade.output_token.mint.clone(),
+            mint: out_mint.to_string(),
             amount: crate::utils::convert_to_ui_amount_u128(
                 output_amount,
                 output_trade.output_token.decimals,
@@ -152,18 +176,159 @@ pub fn get_final_swap(
             destination: output_trade.output_token.destination.clone(),
             destination_owner: output_trade.output_token.destination_owner.clone(),
             source: output_trade.output_token.source.clone(),
+            decimals_defaulted: output_trade.output_token.decimals_defaulted,
+            symbol: output_trade.output_token.symbol.clone(),
+            name: output_trade.output_token.name.clone(),
         },
         slippage_bps: input_trade.slippage_bps,
         fee: input_trade.fee.clone(),
-        fees: input_trade.fees.clone(),
+        fees: (!fees.is_empty()).then_some(fees),
         program_id: input_trade.program_id.clone(),
-        amm: dex_amm.map(String::from).or_else(|| input_trade.amm.clone()),
-        amms: input_trade.amms.clone(),
-        route: dex_route.map(String::from).or_else(|| input_trade.route.clone()),
+        amm: dex_amm
+            .map(String::from)
+            .or_else(|| input_trade.amm.clone()),
+        amms: if amms.is_empty() {
+            input_trade.amms.clone()
+        } else {
+            Some(amms)
+        },
+        route: dex_route
+            .map(String::from)
+            .or_else(|| input_trade.route.clone()),
         slot: input_trade.slot,
         timestamp: input_trade.timestamp,
         signature: input_trade.signature.clone(),
-        idx: input_trade.idx.clone(),
+        idx: input_trade.idx,
         signer: input_trade.signer.clone(),
+        valuation: None,
+        legs: Some(legs),
+        circular,
     })
 }
+
+/// Whether every hop, in execution order, spends the first hop's input mint or a mint an
+/// earlier hop produced.
+fn is_connected_route(hops: &[&crate::types::TradeInfo]) -> bool {
+    let Some(first) = hops.first() else {
+        return false;
+    };
+    let mut reachable = vec![first.input_token.mint.as_str()];
+    for hop in hops {
+        if !reachable.contains(&hop.input_token.mint.as_str()) {
+            return false;
+        }
+        reachable.push(hop.output_token.mint.as_str());
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{fee, trade};
+
+    const SOL: &str = crate::constants::tokens::SOL;
+    const USDC: &str = crate::constants::tokens::USDC;
+
+    fn final_swap(trades: &[crate::types::TradeInfo]) -> Option<crate::types::TradeInfo> {
+        get_final_swap(trades, None, None, QuoteAssets::shared())
+    }
+
+    fn amounts(trade: &crate::types::TradeInfo) -> (&str, &str, &str, &str) {
+        (
+            trade.input_token.mint.as_str(),
+            trade.input_token.amount_raw.as_str(),
+            trade.output_token.mint.as_str(),
+            trade.output_token.amount_raw.as_str(),
+        )
+    }
+
+    #[test]
+    fn nets_split_and_merged_paths() {
+        let trades = [
+            trade("u", "0-1", "p1", (SOL, 50), ("A", 500)),
+            trade("u", "0-2", "p2", (SOL, 50), ("A", 490)),
+            trade("u", "0-3", "p3", ("A", 990), (USDC, 100)),
+        ];
+        let swap = final_swap(&trades).unwrap();
+        assert_eq!(amounts(&swap), (SOL, "100", USDC, "100"));
+        assert!(!swap.circular);
+        assert_eq!(swap.pool, ["p1", "p2", "p3"]);
+        assert_eq!(swap.legs.unwrap().len(), 3);
+    }
+
+    #[test]
+    fn nets_a_route_passing_through_its_start_mint() {
+        let trades = [
+            trade("u", "1-1", "p1", (SOL, 10), (USDC, 1500)),
+            trade("u", "1-2", "p2", (USDC, 1500), (SOL, 10)),
+            trade("u", "1-3", "p3", (SOL, 10), ("A", 7)),
+        ];
+        assert_eq!(
+            amounts(&final_swap(&trades).unwrap()),
+            (SOL, "10", "A", "7")
+        );
+    }
+
+    #[test]
+    fn reports_circular_routes_gross() {
+        let trades = [
+            trade("u", "2-2", "p2", (USDC, 1500), (SOL, 11)),
+            trade("u", "2-1", "p1", (SOL, 10), (USDC, 1500)),
+        ];
+        let swap = final_swap(&trades).unwrap();
+        assert!(swap.circular);
+        assert_eq!(amounts(&swap), (SOL, "10", SOL, "11"));
+    }
+
+    #[test]
+    fn rejects_unrelated_swaps() {
+        let trades = [
+            trade("u", "0", "p1", (SOL, 10), ("A", 100)),
+            trade("u", "1", "p2", ("B", 100), (SOL, 12)),
+        ];
+        assert!(final_swap(&trades).is_none());
+    }
+
+    #[test]
+    fn ignores_other_users() {
+        let trades = [
+            trade("u", "0", "p1", (SOL, 10), ("A", 100)),
+            trade("v", "1", "p2", ("A", 100), (SOL, 12)),
+        ];
+        let swap = final_swap(&trades).unwrap();
+        assert_eq!(amounts(&swap), (SOL, "10", "A", "100"));
+        assert!(!swap.circular);
+    }
+
+    #[test]
+    fn collects_every_hops_fees_once() {
+        let mut first = trade("u", "0-1", "p1", (USDC, 10), (SOL, 100));
+        first.fee = Some(fee(USDC, 1));
+        // a Pumpfun hop: `fees` repeats the protocol `fee` next to the creator fee
+        let mut pumpfun = trade("u", "0-2", "p2", (SOL, 100), ("A", 5));
+        pumpfun.fee = Some(fee(SOL, 2));
+        pumpfun.fees = Some(vec![fee(SOL, 2), fee(SOL, 3)]);
+        let fees = final_swap(&[first, pumpfun]).unwrap().fees.unwrap();
+        let fees: Vec<(&str, &str)> = fees
+            .iter()
+            .map(|f| (f.mint.as_str(), f.amount_raw.as_str()))
+            .collect();
+        assert_eq!(fees, [(USDC, "1"), (SOL, "2"), (SOL, "3")]);
+    }
+
+    #[test]
+    fn a_single_trade_is_a_one_leg_route() {
+        let mut only = trade("u", "3", "p1", (SOL, 10), ("A", 100));
+        only.fee = Some(fee(SOL, 1));
+        let swap = final_swap(std::slice::from_ref(&only)).unwrap();
+        assert_eq!(amounts(&swap), (SOL, "10", "A", "100"));
+        let legs = swap.legs.unwrap();
+        assert_eq!(legs.len(), 1);
+        assert_eq!(
+            (legs[0].idx, legs[0].pool.as_slice()),
+            (only.idx, &["p1".to_string()][..])
+        );
+        assert_eq!(swap.fees.unwrap().len(), 1);
+    }
+}
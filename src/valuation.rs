@@ -0,0 +1,248 @@
+//! Trade valuation: base/quote orientation, execution price and USD value.
+
+use crate::constants::tokens;
+use crate::quote_assets::QuoteAssets;
+use crate::types::{ParseResult, TradeInfo};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+/// USD prices for mints, e.g. an oracle or price feed cache.
+/// Set it on [crate::ParseConfig::price_source] or [crate::DexParserBuilder::price_source].
+pub trait PriceSource: Debug + Send + Sync {
+    /// USD price of one whole token of `mint` at `timestamp` (unix seconds).
+    fn usd_price(&self, mint: &str, timestamp: i64) -> Option<f64>;
+}
+
+/// [PriceSource] with fixed prices, ignoring the timestamp.
+#[derive(Debug, Clone, Default)]
+pub struct StaticPriceSource {
+    prices: HashMap<String, f64>,
+}
+
+impl StaticPriceSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, mint: impl Into<String>, usd_price: f64) -> Self {
+        self.prices.insert(mint.into(), usd_price);
+        self
+    }
+}
+
+impl PriceSource for StaticPriceSource {
+    fn usd_price(&self, mint: &str, _timestamp: i64) -> Option<f64> {
+        self.prices.get(mint).copied()
+    }
+}
+
+/// A trade seen as base traded against quote.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeValuation {
+    pub base_mint: String,
+    pub quote_mint: String,
+    pub base_amount: f64,
+    pub quote_amount: f64,
+    /// Execution price: quote per base.
+    pub price: f64,
+    /// USD price of the base token implied by this trade.
+    pub base_usd_price: Option<f64>,
+    pub usd_value: Option<f64>,
+}
+
+/// USD stablecoins, valued at $1 when no [PriceSource] prices them. EURC and the
+/// yield-bearing USDY are priced through a [PriceSource] only.
+fn is_usd_stable(mint: &str) -> bool {
+    matches!(
+        mint,
+        tokens::USDC | tokens::USDT | tokens::USD1 | tokens::USDG | tokens::PYUSD | tokens::FDUSD
+    )
+}
+
+/// Volume-weighted SOL/USD price from SOL <-> USD stable trades, if there are any.
+pub fn derive_sol_price<'a>(trades: impl IntoIterator<Item = &'a TradeInfo>) -> Option<f64> {
+    let (mut sol, mut usd) = (0.0, 0.0);
+    for trade in trades {
+        let (a, b) = (&trade.input_token, &trade.output_token);
+        let (sol_leg, stable_leg) = if a.mint == tokens::SOL && is_usd_stable(&b.mint) {
+            (a, b)
+        } else if b.mint == tokens::SOL && is_usd_stable(&a.mint) {
+            (b, a)
+        } else {
+            continue;
+        };
+        if sol_leg.amount > 0.0 && stable_leg.amount > 0.0 {
+            sol += sol_leg.amount;
+            usd += stable_leg.amount;
+        }
+    }
+    (sol > 0.0).then(|| usd / sol)
+}
+
+/// Whether the input side is the quote of a valuation. A USD stable against anything
+/// else is the quote, so prices read in dollars; otherwise `quote_assets` decides. This
+/// differs from the Buy/Sell order, where SOL outranks the stables.
+fn input_is_quote(in_mint: &str, out_mint: &str, quote_assets: &QuoteAssets) -> bool {
+    match (is_usd_stable(in_mint), is_usd_stable(out_mint)) {
+        (true, false) => true,
+        (false, true) => false,
+        _ => quote_assets.outranks(in_mint, out_mint),
+    }
+}
+
+/// Value one trade. `sol_usd` is used for SOL-quoted trades when `prices` has no SOL price.
+pub fn value_trade(
+    trade: &TradeInfo,
+    sol_usd: Option<f64>,
+    prices: Option<&dyn PriceSource>,
+    quote_assets: &QuoteAssets,
+) -> Option<TradeValuation> {
+    let (input, output) = (&trade.input_token, &trade.output_token);
+    if input.mint == output.mint {
+        return None;
+    }
+    let (base, quote) = if input_is_quote(&input.mint, &output.mint, quote_assets) {
+        (output, input)
+    } else {
+        (input, output)
+    };
+    if base.amount <= 0.0 || quote.amount <= 0.0 {
+        return None;
+    }
+    let usd_price = |mint: &str| -> Option<f64> {
+        prices
+            .and_then(|p| p.usd_price(mint, trade.timestamp))
+            .or_else(|| match mint {
+                tokens::SOL => sol_usd,
+                m if is_usd_stable(m) => Some(1.0),
+                _ => None,
+            })
+    };
+    let price = quote.amount / base.amount;
+    let usd_value = usd_price(&quote.mint)
+        .map(|p| quote.amount * p)
+        .or_else(|| usd_price(&base.mint).map(|p| base.amount * p));
+    Some(TradeValuation {
+        base_mint: base.mint.clone(),
+        quote_mint: quote.mint.clone(),
+        base_amount: base.amount,
+        quote_amount: quote.amount,
+        price,
+        base_usd_price: usd_value.map(|v| v / base.amount),
+        usd_value,
+    })
+}
+
+/// Value every trade of `results`, deriving the SOL price from their SOL <-> USD stable trades
+/// when `prices` doesn't provide one. Pass all transactions of a block to share that price.
+pub fn apply_valuation(
+    results: &mut [ParseResult],
+    prices: Option<&dyn PriceSource>,
+    quote_assets: &QuoteAssets,
+) {
+    // An aggregate repeats the volume of its hops, so it counts only where they are absent.
+    let sol_usd = derive_sol_price(results.iter().flat_map(|r| {
+        r.trades
+            .iter()
+            .chain(r.aggregate_trade.iter().filter(|_| r.trades.is_empty()))
+    }));
+    for result in results.iter_mut() {
+        for trade in result
+            .trades
+            .iter_mut()
+            .chain(result.aggregate_trade.iter_mut())
+        {
+            trade.valuation = value_trade(trade, sol_usd, prices, quote_assets);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{result, trade};
+
+    const SOL: &str = tokens::SOL;
+    const USDC: &str = tokens::USDC;
+    const USDT: &str = tokens::USDT;
+    /// Raw amounts of one whole SOL and one whole USD stable.
+    const ONE_SOL: u64 = 1_000_000_000;
+    const ONE_USD: u64 = 1_000_000;
+
+    fn value(t: &TradeInfo, sol_usd: Option<f64>) -> Option<TradeValuation> {
+        value_trade(t, sol_usd, None, QuoteAssets::shared())
+    }
+
+    #[test]
+    fn values_usd_stables_at_a_dollar() {
+        let t = trade("u", "0", "p", (USDC, 300 * ONE_USD), ("A", 1_000));
+        let valuation = value(&t, None).unwrap();
+        assert_eq!(
+            (valuation.base_mint.as_str(), valuation.quote_mint.as_str()),
+            ("A", USDC)
+        );
+        assert_eq!(valuation.price, 0.3);
+        assert_eq!(valuation.usd_value, Some(300.0));
+        assert_eq!(valuation.base_usd_price, Some(0.3));
+    }
+
+    #[test]
+    fn quotes_sol_in_usd_stables_and_tokens_in_sol() {
+        let t = trade("u", "0", "p", (SOL, 2 * ONE_SOL), (USDT, 300 * ONE_USD));
+        let valuation = value(&t, None).unwrap();
+        assert_eq!(
+            (valuation.base_mint.as_str(), valuation.quote_mint.as_str()),
+            (SOL, USDT)
+        );
+        assert_eq!(valuation.price, 150.0);
+        assert_eq!(valuation.usd_value, Some(300.0));
+
+        let t = trade("u", "0", "p", ("A", 500), (SOL, 2 * ONE_SOL));
+        let valuation = value(&t, Some(150.0)).unwrap();
+        assert_eq!(
+            (valuation.base_mint.as_str(), valuation.quote_mint.as_str()),
+            ("A", SOL)
+        );
+        assert_eq!(valuation.usd_value, Some(300.0));
+        assert!(value(&t, None).unwrap().usd_value.is_none());
+    }
+
+    #[test]
+    fn prefers_the_price_source() {
+        let prices = StaticPriceSource::new().with("A", 2.0).with(USDC, 0.99);
+        let t = trade("u", "0", "p", ("A", 10), ("B", 40));
+        let valuation = value_trade(&t, None, Some(&prices), QuoteAssets::shared()).unwrap();
+        assert_eq!(valuation.usd_value, Some(20.0));
+        let t = trade("u", "0", "p", (USDC, 100 * ONE_USD), ("A", 10));
+        let valuation = value_trade(&t, None, Some(&prices), QuoteAssets::shared()).unwrap();
+        assert_eq!(valuation.usd_value, Some(99.0));
+    }
+
+    #[test]
+    fn derives_a_volume_weighted_sol_price() {
+        let trades = [
+            trade("u", "0", "p1", (SOL, ONE_SOL), (USDC, 100 * ONE_USD)),
+            trade("u", "1", "p2", (USDT, 600 * ONE_USD), (SOL, 3 * ONE_SOL)),
+            trade("u", "2", "p3", (SOL, ONE_SOL), ("A", 100)),
+        ];
+        assert_eq!(derive_sol_price(&trades), Some(175.0));
+        assert_eq!(derive_sol_price(&trades[2..]), None);
+    }
+
+    #[test]
+    fn counts_an_aggregate_only_without_its_hops() {
+        let hop = trade("u", "0-1", "p1", (SOL, ONE_SOL), (USDC, 100 * ONE_USD));
+        let mut routed = result(1, "u", 0, vec![hop.clone()]);
+        routed.aggregate_trade = Some(hop);
+        let mut aggregated_only = result(1, "v", 0, Vec::new());
+        aggregated_only.aggregate_trade =
+            Some(trade("v", "0", "p2", (SOL, ONE_SOL), (USDC, 400 * ONE_USD)));
+        let token = trade("w", "0", "p3", ("A", 10), (SOL, 2 * ONE_SOL));
+        let mut results = [routed, aggregated_only, result(1, "w", 0, vec![token])];
+        apply_valuation(&mut results, None, QuoteAssets::shared());
+        // $500 over 2 SOL; counting the routed aggregate too would give $600 over 3 SOL
+        let usd = results[2].trades[0].valuation.as_ref().unwrap().usd_value;
+        assert_eq!(usd, Some(500.0));
+    }
+}